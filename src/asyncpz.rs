@@ -0,0 +1,403 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::io::ErrorKind;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use num_traits::ToPrimitive;
+use reqwest::header::{CONTENT_LENGTH, RANGE};
+use reqwest::{Client, StatusCode};
+use zip::read::ZipFile;
+use zip::ZipArchive;
+
+use super::cache::{BLOCK_LEN, BLOCK_SIZE};
+use super::partzip::{
+    data_descriptor_flag, index_for_name, open_index, version_needed, PartialZipError,
+    PartialZipFileDetailed, PartialZipFileExtra, PartialZipFileFull,
+};
+use super::utils;
+
+/// Async version of [`crate::PartialZip`], fetching the archive with range requests through [`reqwest`].
+///
+/// The zip structures are parsed from the blocks of the archive fetched so far: when the parsing needs a block
+/// we don't have yet, it's fetched asynchronously and the parsing is done again.
+/// Only `http` and `https` URLs are supported, and `https` needs the `native-tls` or the `rustls` feature
+/// for the TLS backend of reqwest.
+#[derive(Debug)]
+pub struct AsyncPartialZip {
+    /// Client fetching the blocks of the archive
+    remote: RemoteBlocks,
+    /// The archive object, reading from the blocks fetched so far
+    archive: Mutex<ZipArchive<BlockReader>>,
+}
+
+/// Blocks of the archive fetched so far
+#[derive(Debug, Default)]
+struct BlockStore {
+    /// Content of the blocks, indexed by block number (offset / [`BLOCK_SIZE`])
+    blocks: HashMap<u64, Vec<u8>>,
+    /// First and last block of the read which couldn't be served, for the call parsing the archive,
+    /// see [`RemoteBlocks::with_blocks`]
+    missing: Option<(u64, u64)>,
+    /// Number of downloads in progress using each block
+    pins: HashMap<u64, usize>,
+    /// Blocks fetched only for the content of a download, removed once no download uses them
+    transient: HashSet<u64>,
+}
+
+/// Blocks used by a download in progress, they aren't removed until it's dropped
+struct PinnedBlocks<'a> {
+    store: &'a Mutex<BlockStore>,
+    first: u64,
+    last: u64,
+}
+
+impl<'a> PinnedBlocks<'a> {
+    fn new(store: &'a Mutex<BlockStore>, first: u64, last: u64) -> Self {
+        let mut locked = lock(store);
+        for block in first..=last {
+            *locked.pins.entry(block).or_default() += 1;
+        }
+        drop(locked);
+        Self { store, first, last }
+    }
+}
+
+impl Drop for PinnedBlocks<'_> {
+    fn drop(&mut self) {
+        let mut store = lock(self.store);
+        for block in self.first..=self.last {
+            let pins = store.pins.entry(block).or_default();
+            *pins = pins.saturating_sub(1);
+            if *pins == 0 {
+                store.pins.remove(&block);
+                if store.transient.remove(&block) {
+                    store.blocks.remove(&block);
+                }
+            }
+        }
+    }
+}
+
+/// Reader of the archive serving only the blocks already fetched, recording the missing ones
+#[derive(Debug, Clone)]
+struct BlockReader {
+    store: Arc<Mutex<BlockStore>>,
+    file_size: u64,
+    pos: u64,
+}
+
+/// Fetches the blocks of a remote archive
+#[derive(Debug)]
+struct RemoteBlocks {
+    /// URL of the zip archive
+    url: String,
+    client: Client,
+    /// The archive size
+    file_size: u64,
+    store: Arc<Mutex<BlockStore>>,
+    /// Held while parsing, so the missing blocks recorded in the store are the ones of a single call
+    parsing: Mutex<()>,
+}
+
+/// Lock a mutex, a panic while holding it doesn't leave the blocks in an inconsistent state
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl AsyncPartialZip {
+    /// Create a new [`AsyncPartialZip`]
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] enum depending on what error happened,
+    /// [`PartialZipError::InvalidUrl`] if it isn't an `http` or `https` URL,
+    /// [`PartialZipError::RangeNotSupported`] if the server doesn't support range requests
+    pub async fn new(url: &(dyn ToString + Sync)) -> Result<Self, PartialZipError> {
+        let url = url.to_string();
+        if !utils::is_http_url(&url) {
+            return Err(PartialZipError::InvalidUrl);
+        }
+        let client = Client::new();
+        let response = client.head(&url).send().await?;
+        let status = response.status();
+        if !status.is_success() && !status.is_redirection() {
+            log::warn!("HTTP status {status} from {}", response.url());
+            return Err(PartialZipError::HttpStatus(status.as_u16().into()));
+        }
+        let file_size = response
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "invalid content length"))?;
+        let store = Arc::new(Mutex::new(BlockStore::default()));
+        let remote = RemoteBlocks {
+            url,
+            client,
+            file_size,
+            store: store.clone(),
+            parsing: Mutex::new(()),
+        };
+        // the central directory is at the end of the archive, start from the last block
+        if let Some(last) = file_size.checked_sub(1) {
+            remote
+                .fetch(last / BLOCK_SIZE, last / BLOCK_SIZE, false)
+                .await?;
+        }
+        let reader = BlockReader {
+            store,
+            file_size,
+            pos: 0,
+        };
+        let archive = remote
+            .with_blocks(|| Ok(ZipArchive::new(reader.clone())?))
+            .await?;
+        Ok(Self {
+            remote,
+            archive: Mutex::new(archive),
+        })
+    }
+
+    /// Returns the url for the [`AsyncPartialZip`]
+    #[must_use]
+    pub fn url(&self) -> String {
+        self.remote.url.clone()
+    }
+
+    /// Returns the file size for the [`AsyncPartialZip`]
+    #[must_use]
+    pub const fn file_size(&self) -> u64 {
+        self.remote.file_size
+    }
+
+    /// Returns the number of files in the archive, without fetching anything
+    #[must_use]
+    pub fn len(&self) -> usize {
+        lock(&self.archive).len()
+    }
+
+    /// Returns `true` if the archive doesn't contain any file
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if the archive contains a file named `filename`, without fetching anything
+    #[must_use]
+    pub fn contains(&self, filename: &str) -> bool {
+        lock(&self.archive).index_for_name(filename).is_some()
+    }
+
+    /// Get a list of the filenames in the archive
+    #[must_use]
+    pub fn list_names(&self) -> Vec<String> {
+        lock(&self.archive)
+            .file_names()
+            .map(std::borrow::ToOwned::to_owned)
+            .collect()
+    }
+
+    /// Get a list of the files in the archive with details, see [`crate::PartialZip::list_detailed`]
+    pub async fn list_detailed(&self) -> Vec<PartialZipFileDetailed> {
+        // the central headers are in the blocks fetched to parse the archive
+        let mut reader = BlockReader {
+            store: self.remote.store.clone(),
+            file_size: self.remote.file_size,
+            pos: 0,
+        };
+        self.list_with(|file| {
+            let mut details = PartialZipFileDetailed::from_zip_file(file);
+            let offset = file.central_header_start();
+            details.has_data_descriptor = data_descriptor_flag(&mut reader, offset)?;
+            details.version_needed = version_needed(&mut reader, offset)?;
+            Ok(details)
+        })
+        .await
+    }
+
+    /// Get a list of the files in the archive with all their attributes, see [`crate::PartialZip::list_full`]
+    pub async fn list_full(&self) -> Vec<PartialZipFileFull> {
+        self.list_with(|file| Ok(PartialZipFileFull::from_zip_file(file)))
+            .await
+    }
+
+    /// Get a list of the files in the archive with their comment and extra fields, see [`crate::PartialZip::list_with_extra`]
+    pub async fn list_with_extra(&self) -> Vec<PartialZipFileExtra> {
+        self.list_with(|file| Ok(PartialZipFileExtra::from_zip_file(file)))
+            .await
+    }
+
+    /// Walk all the files in the archive, collecting the result of `f` for each of them
+    ///
+    /// The files for which `f` fails are skipped with a warning, like the ones which can't be read.
+    async fn list_with<T: Send>(
+        &self,
+        mut f: impl FnMut(&ZipFile) -> Result<T, PartialZipError> + Send,
+    ) -> Vec<T> {
+        let mut file_list = Vec::new();
+        let len = lock(&self.archive).len();
+        for i in 0..len {
+            let file = self
+                .remote
+                .with_blocks(|| f(&lock(&self.archive).by_index_raw(i)?))
+                .await;
+            match file {
+                Ok(file) => file_list.push(file),
+                Err(e) => {
+                    // We are unable to get a file, let's try to continue,
+                    // and at least return the files we can
+                    log::warn!("list: error while matching file by index: {i} - {e}");
+                }
+            }
+        }
+        file_list
+    }
+
+    /// Download a single file from the archive
+    ///
+    /// The compressed content is fetched with a single request, and it's not kept after the download,
+    /// unless the blocks were already there or another download in progress uses them.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened
+    pub async fn download(&self, filename: &str) -> Result<Vec<u8>, PartialZipError> {
+        let index = index_for_name(&lock(&self.archive), filename)?;
+        let (start, end) = self
+            .remote
+            .with_blocks(|| {
+                Ok(lock(&self.archive).by_index_raw(index).map(|file| {
+                    (
+                        file.data_start(),
+                        file.data_start() + file.compressed_size(),
+                    )
+                })?)
+            })
+            .await?;
+        let blocks = (start / BLOCK_SIZE, end.saturating_sub(1) / BLOCK_SIZE);
+        let _pinned = PinnedBlocks::new(&self.remote.store, blocks.0, blocks.1);
+        if end > start {
+            self.remote.fetch(blocks.0, blocks.1, true).await?;
+        }
+        self.remote
+            .with_blocks(|| {
+                let mut archive = lock(&self.archive);
+                let mut content = Vec::new();
+                io::copy(&mut open_index(&mut archive, index)?, &mut content)?;
+                Ok(content)
+            })
+            .await
+    }
+}
+
+impl RemoteBlocks {
+    /// Fetch the blocks from `first` to `last` (included) with a single range request
+    ///
+    /// The `transient` blocks are only kept while a download uses them, see [`PinnedBlocks`].
+    async fn fetch(&self, first: u64, last: u64, transient: bool) -> Result<(), PartialZipError> {
+        let start = first * BLOCK_SIZE;
+        let end = std::cmp::min((last + 1) * BLOCK_SIZE, self.file_size).saturating_sub(1);
+        log::trace!("range = {start}-{end}");
+        let response = self
+            .client
+            .get(&self.url)
+            .header(RANGE, format!("bytes={start}-{end}"))
+            .send()
+            .await?
+            .error_for_status()?;
+        // a server can answer with the whole archive when it's what we asked for
+        let whole = start == 0 && end + 1 == self.file_size;
+        if response.status() != StatusCode::PARTIAL_CONTENT && !whole {
+            return Err(PartialZipError::RangeNotSupported);
+        }
+        let content = response.bytes().await?;
+        if content.len().to_u64() != Some(end - start + 1) {
+            return Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                format!("short range response {start}-{end}"),
+            )
+            .into());
+        }
+        let mut store = lock(&self.store);
+        for (block, content) in (first..).zip(content.chunks(BLOCK_LEN)) {
+            if !transient {
+                store.transient.remove(&block);
+            } else if !store.blocks.contains_key(&block) {
+                store.transient.insert(block);
+            }
+            store.blocks.insert(block, content.to_vec());
+        }
+        drop(store);
+        Ok(())
+    }
+
+    /// Run `f` until it doesn't need any block missing, fetching them between the attempts
+    ///
+    /// The attempts of the concurrent calls are serialized, so each one only sees the blocks it missed itself,
+    /// while the fetches are done concurrently.
+    async fn with_blocks<T>(
+        &self,
+        mut f: impl FnMut() -> Result<T, PartialZipError> + Send,
+    ) -> Result<T, PartialZipError> {
+        loop {
+            let (result, missing) = {
+                let _parsing = lock(&self.parsing);
+                lock(&self.store).missing = None;
+                let result = f();
+                (result, lock(&self.store).missing.take())
+            };
+            match missing {
+                Some((first, last)) => self.fetch(first, last, false).await?,
+                None => return result,
+            }
+        }
+    }
+}
+
+impl io::Read for BlockReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.file_size || buf.is_empty() {
+            return Ok(0);
+        }
+        let block = self.pos / BLOCK_SIZE;
+        let offset = (self.pos % BLOCK_SIZE)
+            .to_usize()
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "invalid block offset"))?;
+        let mut store = lock(&self.store);
+        if let Some(content) = store.blocks.get(&block) {
+            let n = io::Read::read(&mut content.get(offset..).unwrap_or_default(), buf)?;
+            drop(store);
+            self.pos += n.to_u64().unwrap_or_default();
+            return Ok(n);
+        }
+        let end = std::cmp::min(
+            self.pos
+                .saturating_add(buf.len().to_u64().unwrap_or(u64::MAX)),
+            self.file_size,
+        ) - 1;
+        store.missing = Some((block, end / BLOCK_SIZE));
+        drop(store);
+        Err(io::Error::new(
+            ErrorKind::WouldBlock,
+            format!("block {block:x} not fetched yet"),
+        ))
+    }
+}
+
+impl io::Seek for BlockReader {
+    fn seek(&mut self, style: io::SeekFrom) -> io::Result<u64> {
+        let (base_pos, offset) = match style {
+            io::SeekFrom::Start(n) => {
+                self.pos = n;
+                return Ok(n);
+            }
+            io::SeekFrom::End(n) => (self.file_size, n),
+            io::SeekFrom::Current(n) => (self.pos, n),
+        };
+        self.pos = base_pos.checked_add_signed(offset).ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        Ok(self.pos)
+    }
+}
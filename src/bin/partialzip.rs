@@ -1,66 +1,482 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use bytesize::ByteSize;
-use clap::{Parser, Subcommand};
-use partialzip::partzip::PartialZip;
-use std::fs::File;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use log::LevelFilter;
+use partialzip::entry_path;
+use partialzip::partzip::{
+    write_new_file, HashAlgorithm, PartialReader, PartialZip, PartialZipError,
+    PartialZipFileDetailed, Preserve, SortKey,
+};
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use url::Url;
 
+/// Number of files downloaded at the same time by the commands extracting several files
+const BATCH_CONCURRENCY: usize = 4;
+
 /// Handler to list the files from command line
-fn list(url: &str, detailed: bool, check_range: bool) -> Result<()> {
-    let url = Url::parse(url).context("invalid URL for listing")?;
-    let pz = PartialZip::new_check_range(&url, check_range)
+// the flags of the list command, each changing another part of the output
+#[allow(clippy::fn_params_excessive_bools)]
+fn list(
+    url: &str,
+    detailed: bool,
+    json: bool,
+    comment: bool,
+    sort: Option<SortOrder>,
+    version_needed: bool,
+    options: &Options,
+) -> Result<()> {
+    let url = Source::parse(url).context("invalid URL for listing")?;
+    let pz = options
+        .open(&url)
         .context("Cannot create PartialZip instance for listing")?;
-    if detailed {
-        pz.list_detailed().into_iter().for_each(|f| {
-            println!(
-                "{} - {} - Supported: {}",
-                f.name,
-                ByteSize(f.compressed_size),
-                f.supported
-            );
+    let archive_comment = pz.comment();
+    if comment && !archive_comment.is_empty() {
+        println!("{}", String::from_utf8_lossy(&archive_comment));
+    }
+    if json {
+        let list = serde_json::to_string_pretty(&pz.list_full())
+            .context("cannot serialize the list to JSON")?;
+        println!("{list}");
+    } else if detailed {
+        let files = sort.map_or_else(|| pz.list_detailed(), |sort| pz.list_sorted(sort.into()));
+        for file in &files {
+            print_detailed(file, version_needed);
+        }
+        let summary = pz.summary().context("cannot read the central directory")?;
+        let ratio = summary.ratio().map_or_else(String::new, |ratio| {
+            format!(" - ratio {:.1}%", ratio * 100.0)
         });
+        println!(
+            "{} files - {} compressed - {} uncompressed{ratio}",
+            summary.files,
+            ByteSize(summary.compressed_size),
+            ByteSize(summary.uncompressed_size)
+        );
     } else {
         pz.list_names().into_iter().for_each(|f| println!("{f}"));
     }
     Ok(())
 }
 
+/// Handler to show the details of a single file from command line
+fn stat(url: &str, filename: &str, options: &Options) -> Result<()> {
+    let url = Source::parse(url).context("invalid URL for stat")?;
+    let pz = options
+        .open(&url)
+        .context("Cannot create PartialZip instance for stat")?;
+    print_detailed(&pz.stat(filename).context("stat failed")?, false);
+    Ok(())
+}
+
+/// Print the details of a file, one per line, with the version needed to extract it if asked
+fn print_detailed(f: &PartialZipFileDetailed, version_needed: bool) {
+    let version = if version_needed {
+        // the upper byte is reserved, the lower one is the major version times 10 plus the minor one
+        let version = f.version_needed & 0xff;
+        format!(" - Version needed: {}.{}", version / 10, version % 10)
+    } else {
+        String::new()
+    };
+    println!(
+        "{} - {} - Supported: {}{version}",
+        f.name,
+        ByteSize(f.compressed_size),
+        f.supported
+    );
+}
+
 /// Handler to download the file from command line
-fn download(url: &str, filename: &str, outputfile: &str, check_range: bool) -> Result<()> {
-    let url = Url::parse(url).context("invalid URL for downloading")?;
-    let pz = PartialZip::new_check_range(&url, check_range)
+fn download(
+    url: &str,
+    filename: &str,
+    outputfile: &str,
+    password: Option<&str>,
+    options: &Options,
+) -> Result<()> {
+    let url = Source::parse(url).context("invalid URL for downloading")?;
+    let pz = options
+        .open(&url)
         .context("Cannot create PartialZip instance for downloading")?;
-    let mut f = File::create_new(outputfile).context("cannot create the output file")?;
-    #[cfg(feature = "progressbar")]
-    pz.download_to_write_with_progressbar(filename, &mut f)
-        .context("download failed")?;
-    #[cfg(not(feature = "progressbar"))]
-    pz.download_to_write(filename, &mut f)
-        .context("download failed")?;
+    // never overwrite an existing file
+    write_new_file(Path::new(outputfile), |file| {
+        if let Some(password) = password {
+            return Ok(file.write_all(&pz.download_encrypted(filename, password.as_bytes())?)?);
+        }
+        #[cfg(feature = "progressbar")]
+        return pz.download_to_write_with_progressbar(filename, file);
+        #[cfg(not(feature = "progressbar"))]
+        return pz.download_to_write(filename, file);
+    })
+    .context("download failed")?;
     println!("{filename} extracted to {outputfile}");
+    options.print_stats(&pz);
+    Ok(())
+}
+
+/// Handler to download the file at an index of the listing from command line
+fn download_index(url: &str, index: usize, outputfile: &str, options: &Options) -> Result<()> {
+    let url = Source::parse(url).context("invalid URL for downloading")?;
+    let pz = options
+        .open(&url)
+        .context("Cannot create PartialZip instance for downloading")?;
+    // never overwrite an existing file
+    write_new_file(Path::new(outputfile), |file| {
+        Ok(file.write_all(&pz.download_by_index(index)?)?)
+    })
+    .context("download failed")?;
+    println!("#{index} extracted to {outputfile}");
+    options.print_stats(&pz);
+    Ok(())
+}
+
+/// Handler to download all the files matching a glob pattern from command line
+fn download_matching(url: &str, pattern: &str, outputdir: &str, options: &Options) -> Result<()> {
+    let url = Source::parse(url).context("invalid URL for downloading")?;
+    let pz = options
+        .open(&url)
+        .context("Cannot create PartialZip instance for downloading")?;
+    let written = pz
+        .download_matching(pattern, Path::new(outputdir))
+        .context("download failed")?;
+    for path in written {
+        println!("extracted {}", path.display());
+    }
+    options.print_stats(&pz);
+    Ok(())
+}
+
+/// Handler to download the files under a directory at the paths of the entries from command line
+fn download_to_dir(
+    url: &str,
+    filenames: &[String],
+    outputdir: &str,
+    continue_on_error: bool,
+    options: &Options,
+) -> Result<()> {
+    let url = Source::parse(url).context("invalid URL for downloading")?;
+    let pz = options
+        .open(&url)
+        .context("Cannot create PartialZip instance for downloading")?;
+    if continue_on_error {
+        let names: Vec<&str> = filenames.iter().map(String::as_str).collect();
+        let results = pz.download_many(&names, Path::new(outputdir), BATCH_CONCURRENCY);
+        options.print_stats(&pz);
+        return report_batch(&names, results);
+    }
+    for filename in filenames {
+        let path = pz
+            .download_to_dir(filename, Path::new(outputdir))
+            .context("download failed")?;
+        println!("{filename} extracted to {}", path.display());
+    }
+    options.print_stats(&pz);
+    Ok(())
+}
+
+/// Print where every file was extracted or why it failed, then the totals, failing if any file failed
+fn report_batch(names: &[&str], results: Vec<Result<PathBuf, PartialZipError>>) -> Result<()> {
+    let mut failed = 0;
+    for (name, result) in names.iter().zip(results) {
+        match result {
+            Ok(path) => println!("{name} extracted to {}", path.display()),
+            Err(e) => {
+                failed += 1;
+                eprintln!("{name} failed: {e}");
+            }
+        }
+    }
+    println!("{} extracted, {failed} failed", names.len() - failed);
+    if failed > 0 {
+        return Err(anyhow!("{failed} of {} files failed", names.len()));
+    }
+    Ok(())
+}
+
+/// Handler to extract the files matching any of the glob patterns, or all of them, like `unzip`
+// one argument per flag of the extract command
+#[allow(clippy::too_many_arguments)]
+fn extract(
+    url: &str,
+    outputdir: &str,
+    patterns: &[String],
+    list_only: bool,
+    force: bool,
+    continue_on_error: bool,
+    preserve: Preserve,
+    options: &Options,
+) -> Result<()> {
+    let url = Source::parse(url).context("invalid URL for extracting")?;
+    let patterns = patterns
+        .iter()
+        .map(|pattern| glob::Pattern::new(pattern))
+        .collect::<Result<Vec<_>, _>>()
+        .context("invalid glob pattern")?;
+    let pz = options
+        .open(&url)
+        .context("Cannot create PartialZip instance for extracting")?;
+    let names: Vec<String> = pz
+        .list_names()
+        .into_iter()
+        .filter(|name| !name.ends_with('/'))
+        .filter(|name| patterns.is_empty() || patterns.iter().any(|p| p.matches(name)))
+        .collect();
+    if names.is_empty() {
+        return Err(PartialZipError::FileNotFound {
+            name: patterns
+                .iter()
+                .map(glob::Pattern::as_str)
+                .collect::<Vec<_>>()
+                .join(" "),
+        })
+        .context("no file matches");
+    }
+    if list_only {
+        for name in &names {
+            println!("{name}");
+        }
+        return Ok(());
+    }
+    // check everything before writing anything
+    if !force {
+        // where the files are extracted, the unsafe names are refused when extracting them
+        if let Some(name) = names.iter().find(|name| {
+            entry_path(name).is_some_and(|path| Path::new(outputdir).join(path).exists())
+        }) {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, "File exists"))
+                .with_context(|| format!("{name} is already extracted, use --force to overwrite"));
+        }
+    }
+    if continue_on_error {
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let results = pz
+            .download_many(&names, Path::new(outputdir), BATCH_CONCURRENCY)
+            .into_iter()
+            .zip(&names)
+            .map(|(result, name)| {
+                result.and_then(|path| {
+                    pz.restore_attributes(name, &path, preserve)?;
+                    Ok(path)
+                })
+            })
+            .collect();
+        options.print_stats(&pz);
+        return report_batch(&names, results);
+    }
+    for name in &names {
+        let path = pz
+            .download_to_dir(name, Path::new(outputdir))
+            .context("download failed")?;
+        pz.restore_attributes(name, &path, preserve)
+            .with_context(|| format!("cannot set the attributes of {}", path.display()))?;
+        println!("extracted {}", path.display());
+    }
+    options.print_stats(&pz);
+    Ok(())
+}
+
+/// Handler to hash a file of the zip from command line, printed like `sha256sum` does
+fn checksum(url: &str, filename: &str, algorithm: Algorithm, options: &Options) -> Result<()> {
+    let url = Source::parse(url).context("invalid URL for hashing")?;
+    let pz = options
+        .open(&url)
+        .context("Cannot create PartialZip instance for hashing")?;
+    let digest = pz
+        .hash_entry(filename, algorithm.into())
+        .context("hashing failed")?;
+    println!("{digest}  {filename}");
+    options.print_stats(&pz);
     Ok(())
 }
 
 /// Handler to download the file and pipe it to stdout
-fn pipe(url: &str, filename: &str, check_range: bool) -> Result<()> {
-    let url = Url::parse(url).context("invalid URL for piping")?;
-    let pz = PartialZip::new_check_range(&url, check_range)
+fn pipe(url: &str, filename: &str, options: &Options) -> Result<()> {
+    let url = Source::parse(url).context("invalid URL for piping")?;
+    let pz = options
+        .open(&url)
         .context("Cannot create PartialZip instance for piping")?;
     pz.download_to_write(filename, &mut std::io::stdout())
         .context("download failed")?;
     Ok(())
 }
 
-#[derive(Parser)]
-#[command(version, about)]
-struct Cli {
+/// Handler to repack all the files into a tar archive written to stdout
+fn repack(url: &str, options: &Options) -> Result<()> {
+    let url = Source::parse(url).context("invalid URL for repacking")?;
+    let pz = options
+        .open(&url)
+        .context("Cannot create PartialZip instance for repacking")?;
+    pz.repack_to_tar(&mut io::stdout().lock())
+        .context("repack failed")?;
+    Ok(())
+}
+
+/// Where the archive is read from
+enum Source {
+    /// The standard input, given as `-` instead of an URL
+    Stdin,
+    /// The archive at this URL
+    Url(Url),
+}
+
+impl Source {
+    /// Parse the URL given on the command line
+    fn parse(url: &str) -> Result<Self, url::ParseError> {
+        if url == "-" {
+            Ok(Self::Stdin)
+        } else {
+            Url::parse(url).map(Self::Url)
+        }
+    }
+}
+
+/// Order of the detailed listing
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SortOrder {
+    /// by name, ignoring the case
+    Name,
+    /// by compressed size, smallest first
+    Compressed,
+    /// by uncompressed size, smallest first
+    Uncompressed,
+}
+
+impl From<SortOrder> for SortKey {
+    fn from(sort: SortOrder) -> Self {
+        match sort {
+            SortOrder::Name => Self::Name,
+            SortOrder::Compressed => Self::CompressedSize,
+            SortOrder::Uncompressed => Self::UncompressedSize,
+        }
+    }
+}
+
+/// Hash algorithm of the checksum command
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Algorithm {
+    /// MD5
+    Md5,
+    /// SHA-1
+    Sha1,
+    /// SHA-256
+    Sha256,
+}
+
+impl From<Algorithm> for HashAlgorithm {
+    fn from(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::Md5 => Self::Md5,
+            Algorithm::Sha1 => Self::Sha1,
+            Algorithm::Sha256 => Self::Sha256,
+        }
+    }
+}
+
+/// Flags choosing the attributes restored on the extracted files
+#[derive(Args, Debug, Clone, Copy)]
+struct PreserveFlags {
+    /// set the modification time of the files, and on Unix their permissions, to the ones stored in the zip
+    #[arg(short = 'p', long)]
+    preserve: bool,
+    /// with --preserve, keep the setuid and setgid bits of the permissions
+    #[arg(long, requires = "preserve")]
+    allow_setid: bool,
+}
+
+impl From<PreserveFlags> for Preserve {
+    fn from(flags: PreserveFlags) -> Self {
+        match (flags.preserve, flags.allow_setid) {
+            (false, _) => Self::Nothing,
+            (true, false) => Self::Attributes,
+            (true, true) => Self::AttributesWithSetid,
+        }
+    }
+}
+
+/// Options of the connection to the server, shared by all the commands
+#[derive(Args)]
+struct Options {
     /// Require using url with range support
     #[arg(short = 'r', long)]
     check_range: bool,
+    /// Maximum download speed, for example 500KB or 2MiB
+    #[arg(long)]
+    limit_rate: Option<ByteSize>,
+    /// User-Agent sent to the server instead of the curl default one
+    #[arg(long)]
+    user_agent: Option<String>,
+    /// Don't verify the TLS certificate of the server, dangerous
+    #[arg(short = 'k', long)]
+    insecure: bool,
+    /// Print the number of requests done and bytes received after a download
+    #[arg(long)]
+    stats: bool,
+}
+
+impl Options {
+    /// Open the archive at `source` with these options, the standard input is copied to a temporary file first
+    fn open(&self, source: &Source) -> Result<PartialZip, PartialZipError> {
+        let url = match source {
+            Source::Stdin => return PartialZip::from_reader_spilled(io::stdin().lock()),
+            Source::Url(url) => url,
+        };
+        #[cfg(feature = "s3")]
+        if url.scheme() == "s3" {
+            return PartialZip::new(url);
+        }
+        let mut reader = PartialReader::new_check_range(url, self.check_range)?
+            .danger_accept_invalid_certs(self.insecure);
+        if let Some(limit) = self.limit_rate {
+            reader = reader.with_max_recv_speed(limit.as_u64());
+        }
+        if let Some(user_agent) = &self.user_agent {
+            reader = reader.with_user_agent(user_agent);
+        }
+        PartialZip::from_reader(reader)
+    }
+
+    /// Print the statistics of the requests to stderr, if asked to
+    fn print_stats(&self, pz: &PartialZip) {
+        if self.stats {
+            let stats = pz.stats();
+            eprintln!(
+                "{} requests, {} received",
+                stats.requests,
+                ByteSize(stats.bytes)
+            );
+        }
+    }
+}
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(flatten)]
+    options: Options,
+    /// Print more logs, repeat it for more details: -v for the informative messages and the warnings, -vv for debugging, -vvv to trace every range request
+    #[arg(short = 'v', long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+    /// Don't print any log, not even the errors
+    #[arg(short = 'q', long, conflicts_with = "verbose", global = true)]
+    quiet: bool,
     #[command(subcommand)]
     command: Commands,
 }
 
+impl Cli {
+    /// Level of the logs asked with the flags, `None` to leave it to `RUST_LOG`
+    const fn log_level(&self) -> Option<LevelFilter> {
+        match (self.quiet, self.verbose) {
+            (true, _) => Some(LevelFilter::Off),
+            (false, 0) => None,
+            (false, 1) => Some(LevelFilter::Info),
+            (false, 2) => Some(LevelFilter::Debug),
+            (false, _) => Some(LevelFilter::Trace),
+        }
+    }
+}
+
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// list file size and support not only names
@@ -68,30 +484,184 @@ enum Commands {
         /// list file size and support not only names
         #[arg(short = 'd', long)]
         detailed: bool,
-        /// url of the zip file
+        /// print all the attributes of the files as a JSON array
+        #[arg(long)]
+        json: bool,
+        /// print the comment of the archive before the files, if it has one
+        #[arg(long, conflicts_with = "json")]
+        comment: bool,
+        /// sort the detailed listing instead of keeping the order of the archive
+        #[arg(long, value_enum, requires = "detailed")]
+        sort: Option<SortOrder>,
+        /// print the version of the zip specification needed to extract every file in the detailed listing
+        #[arg(long, requires = "detailed")]
+        version_needed: bool,
+        /// url of the zip file, - to read it from the standard input
         url: String,
     },
     /// download a file from the zip
     Download {
+        /// treat the filename as a glob pattern and extract all the matching files in the outputfile directory
+        #[arg(short = 'g', long)]
+        glob: bool,
+        /// password of the encrypted file
+        #[arg(short = 'p', long, conflicts_with = "glob")]
+        password: Option<String>,
+        /// extract the files in this directory at the path they have in the zip, then all the arguments after the url are files to extract
+        #[arg(long, conflicts_with_all = ["glob", "password"])]
+        dir: Option<String>,
+        /// with --dir, keep extracting the other files when one fails, and print a summary at the end
+        #[arg(long, requires = "dir")]
+        continue_on_error: bool,
+        /// download the file at this position in the listing instead of by name, then only the outputfile is given
+        #[arg(long, conflicts_with_all = ["glob", "password", "dir"])]
+        index: Option<usize>,
         url: String,
-        filename: String,
-        outputfile: String,
+        #[arg(required_unless_present = "index")]
+        filename: Option<String>,
+        #[arg(required_unless_present_any = ["dir", "index"])]
+        outputfile: Option<String>,
+        /// more files to extract with --dir
+        #[arg(requires = "dir")]
+        filenames: Vec<String>,
     },
+    /// extract the files matching the glob patterns, all of them without any, recreating the directory tree
+    Extract {
+        /// directory where the files are extracted
+        #[arg(short = 'd', long, default_value = ".")]
+        dir: String,
+        /// only print the files which would be extracted
+        #[arg(short = 'l', long)]
+        list_only: bool,
+        /// overwrite the files already there
+        #[arg(short = 'f', long)]
+        force: bool,
+        /// keep extracting the other files when one fails, and print a summary at the end
+        #[arg(long, conflicts_with = "list_only")]
+        continue_on_error: bool,
+        #[command(flatten)]
+        preserve: PreserveFlags,
+        /// url of the zip file, - to read it from the standard input
+        url: String,
+        /// glob patterns of the files to extract
+        patterns: Vec<String>,
+    },
+    /// show the details of a single file of the zip
+    Stat { url: String, filename: String },
     /// stream a file from the zip to stdout
     Pipe { url: String, filename: String },
+    /// print the hash of a file of the zip, without writing it to disk
+    Checksum {
+        /// hash algorithm
+        #[arg(long, value_enum, default_value_t = Algorithm::Sha256)]
+        algo: Algorithm,
+        url: String,
+        filename: String,
+    },
+    /// stream all the files of the zip to stdout as a tar archive
+    Repack { url: String },
 }
 
 fn main() -> Result<()> {
-    env_logger::init();
-
     let cli = Cli::parse();
+    // the flags override RUST_LOG
+    match cli.log_level() {
+        Some(level) => env_logger::Builder::new().filter_level(level).init(),
+        None => env_logger::init(),
+    }
     match cli.command {
-        Commands::List { detailed, url } => list(&url, detailed, cli.check_range),
+        Commands::List {
+            detailed,
+            json,
+            comment,
+            sort,
+            version_needed,
+            url,
+        } => list(
+            &url,
+            detailed,
+            json,
+            comment,
+            sort,
+            version_needed,
+            &cli.options,
+        ),
         Commands::Download {
+            index: Some(index),
             url,
             filename,
             outputfile,
-        } => download(&url, &filename, &outputfile, cli.check_range),
-        Commands::Pipe { url, filename } => pipe(&url, &filename, cli.check_range),
+            ..
+        } => match (filename, outputfile) {
+            // the only file argument is the output file
+            (Some(outputfile), None) => download_index(&url, index, &outputfile, &cli.options),
+            _ => Err(anyhow!("only the output file is expected with --index")),
+        },
+        Commands::Download {
+            dir: Some(dir),
+            continue_on_error,
+            url,
+            filename: Some(filename),
+            outputfile,
+            filenames,
+            ..
+        } => {
+            // every argument after the URL is a file to extract
+            let filenames: Vec<String> = std::iter::once(filename)
+                .chain(outputfile)
+                .chain(filenames)
+                .collect();
+            download_to_dir(&url, &filenames, &dir, continue_on_error, &cli.options)
+        }
+        Commands::Download {
+            glob: true,
+            url,
+            filename: Some(filename),
+            outputfile: Some(outputfile),
+            ..
+        } => download_matching(&url, &filename, &outputfile, &cli.options),
+        Commands::Download {
+            glob: false,
+            password,
+            url,
+            filename: Some(filename),
+            outputfile: Some(outputfile),
+            ..
+        } => download(
+            &url,
+            &filename,
+            &outputfile,
+            password.as_deref(),
+            &cli.options,
+        ),
+        Commands::Download { .. } => Err(anyhow!(
+            "the filename and the output file are required without an index or a directory"
+        )),
+        Commands::Extract {
+            dir,
+            list_only,
+            force,
+            continue_on_error,
+            preserve,
+            url,
+            patterns,
+        } => extract(
+            &url,
+            &dir,
+            &patterns,
+            list_only,
+            force,
+            continue_on_error,
+            preserve.into(),
+            &cli.options,
+        ),
+        Commands::Stat { url, filename } => stat(&url, &filename, &cli.options),
+        Commands::Pipe { url, filename } => pipe(&url, &filename, &cli.options),
+        Commands::Checksum {
+            algo,
+            url,
+            filename,
+        } => checksum(&url, &filename, algo, &cli.options),
+        Commands::Repack { url } => repack(&url, &cli.options),
     }
 }
@@ -0,0 +1,78 @@
+use std::collections::{HashMap, VecDeque};
+
+/// Size of the aligned blocks kept in the cache
+pub const BLOCK_LEN: usize = 0x0001_0000;
+/// Size of the aligned blocks kept in the cache, as an archive offset
+pub const BLOCK_SIZE: u64 = BLOCK_LEN as u64;
+
+/// LRU cache of aligned blocks of the archive, indexed by block number (offset / [`BLOCK_SIZE`])
+#[derive(Debug, Clone, Default)]
+pub struct BlockCache {
+    /// Maximum number of blocks kept
+    capacity: usize,
+    /// Content of the cached blocks
+    blocks: HashMap<u64, Vec<u8>>,
+    /// Block numbers from the least to the most recently used
+    lru: VecDeque<u64>,
+    /// Number of reads served from the cache
+    hits: u64,
+}
+
+impl BlockCache {
+    /// Create a cache holding up to `size` bytes, rounded down to whole blocks
+    pub fn new(size: u64) -> Self {
+        Self {
+            capacity: usize::try_from(size / BLOCK_SIZE).unwrap_or(usize::MAX),
+            ..Default::default()
+        }
+    }
+
+    /// Is the cache storing anything at all
+    pub const fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Maximum number of blocks kept
+    pub const fn capacity(&self) -> u64 {
+        self.capacity as u64
+    }
+
+    /// Number of reads served from the cache
+    pub const fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Is the block cached
+    pub fn contains(&self, block: u64) -> bool {
+        self.blocks.contains_key(&block)
+    }
+
+    /// Get the content of a block starting from `offset`, marking it as the most recently used
+    pub fn get(&mut self, block: u64, offset: usize) -> Option<&[u8]> {
+        let content = self.blocks.get(&block)?;
+        if let Some(i) = self.lru.iter().position(|b| *b == block) {
+            self.lru.remove(i);
+        }
+        self.lru.push_back(block);
+        self.hits += 1;
+        content.get(offset..)
+    }
+
+    /// Store a block, evicting the least recently used ones if the cache is full
+    pub fn insert(&mut self, block: u64, content: Vec<u8>) {
+        if !self.is_enabled() {
+            return;
+        }
+        if self.blocks.insert(block, content).is_some() {
+            if let Some(i) = self.lru.iter().position(|b| *b == block) {
+                self.lru.remove(i);
+            }
+        }
+        self.lru.push_back(block);
+        while self.lru.len() > self.capacity {
+            if let Some(evicted) = self.lru.pop_front() {
+                self.blocks.remove(&evicted);
+            }
+        }
+    }
+}
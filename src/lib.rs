@@ -18,11 +18,53 @@
 //! ```
 /// Core module for the partialzip crate
 pub mod partzip;
+pub use partzip::ArchiveSummary;
+pub use partzip::DownloadPlan;
+pub use partzip::FtpMode;
+#[cfg(feature = "checksums")]
+pub use partzip::HashAlgorithm;
+pub use partzip::HttpVersion;
 pub use partzip::PartialReader;
 pub use partzip::PartialZip;
+pub use partzip::PartialZipClient;
 pub use partzip::PartialZipError;
 pub use partzip::PartialZipFileDetailed;
+pub use partzip::PartialZipFileExtra;
+pub use partzip::PartialZipFileFull;
+pub use partzip::Preserve;
+pub use partzip::Progress;
+pub use partzip::RangeReader;
+pub use partzip::ReadStats;
+pub use partzip::RedirectPolicy;
+pub use partzip::ResumeStatus;
+pub use partzip::SortKey;
+/// Async version of the partialzip crate, built on reqwest, enabled by the `async` feature
+///
+/// It fetches only `http` and `https` URLs, `https` needs the `native-tls` or the `rustls` feature too.
+#[cfg(feature = "async")]
+pub mod asyncpz;
+#[cfg(feature = "async")]
+pub use asyncpz::AsyncPartialZip;
+/// Archives stored in S3 buckets, read through the AWS SDK
+#[cfg(feature = "s3")]
+pub mod s3;
+#[cfg(feature = "s3")]
+pub use s3::S3RangeReader;
+/// Block cache used by the partial reader
+mod cache;
+/// Decompression of the LZMA entries
+mod lzma;
+/// Decompression of the `PPMd` entries
+#[cfg(feature = "ppmd")]
+mod ppmd;
+/// Reconstruction of the central directory of damaged archives
+mod recover;
+/// Streams which can't seek copied to a temporary file
+mod spill;
+/// Archives split in several volumes
+mod split;
 /// Small utilities mostly for URLs
 mod utils;
+pub use utils::entry_path;
 
 mod tests;
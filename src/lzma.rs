@@ -0,0 +1,108 @@
+use lzma_rs::decompress::{Options, Stream, UnpackedSize};
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind, Read, Write};
+
+/// Size of the LZMA header inside a zip entry: version (2 bytes) and properties size (2 bytes)
+const ZIP_LZMA_HEADER_LEN: usize = 4;
+/// Size of the LZMA properties following the zip LZMA header
+const LZMA_PROPERTIES_LEN: u16 = 5;
+/// Size of the compressed chunks fed to the decoder
+const CHUNK_LEN: usize = 0x1000;
+
+/// Reader decompressing a LZMA entry as stored in a zip archive (APPNOTE 5.8.8).
+///
+/// The zip crate feeds the entry to the decoder as a standalone `.lzma` stream, which has a different header,
+/// so we decode the raw entry ourselves, checking the CRC32 at the end like the zip crate does for the other methods.
+pub struct LzmaReader<R> {
+    /// Raw compressed content of the entry
+    inner: R,
+    /// The decoder, `None` once the compressed content is over
+    stream: Option<Stream<VecDeque<u8>>>,
+    /// Decompressed content not yet returned
+    remaining: VecDeque<u8>,
+    /// CRC32 of the uncompressed content, stored in the archive
+    crc32: u32,
+    /// CRC32 of the content returned so far
+    hasher: crc32fast::Hasher,
+    /// Did we already parse the zip LZMA header
+    header_read: bool,
+}
+
+impl<R: Read> LzmaReader<R> {
+    /// Create a new [`LzmaReader`] over the raw content of an entry with the given uncompressed `size` and `crc32`
+    pub fn new(inner: R, size: u64, crc32: u32) -> Self {
+        let options = Options {
+            unpacked_size: UnpackedSize::UseProvided(Some(size)),
+            memlimit: None,
+            allow_incomplete: true,
+        };
+        Self {
+            inner,
+            stream: Some(Stream::new_with_options(&options, VecDeque::new())),
+            remaining: VecDeque::new(),
+            crc32,
+            hasher: crc32fast::Hasher::new(),
+            header_read: false,
+        }
+    }
+
+    /// Skip the zip LZMA header, leaving the properties and the compressed data to the decoder
+    fn read_header(&mut self) -> io::Result<()> {
+        let mut header = [0; ZIP_LZMA_HEADER_LEN];
+        self.inner.read_exact(&mut header)?;
+        let properties_len = u16::from_le_bytes([header[2], header[3]]);
+        if properties_len != LZMA_PROPERTIES_LEN {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("unexpected LZMA properties size {properties_len}"),
+            ));
+        }
+        self.header_read = true;
+        Ok(())
+    }
+
+    /// Feed the decoder until some decompressed content is available or the entry is over
+    fn fill(&mut self) -> io::Result<()> {
+        while self.remaining.is_empty() {
+            let Some(stream) = self.stream.as_mut() else {
+                return Ok(());
+            };
+            let mut chunk = [0; CHUNK_LEN];
+            let n = self.inner.read(&mut chunk)?;
+            let mut data = &chunk[..n];
+            // the decoder stops accepting data once it produced the whole uncompressed size,
+            // anything left is the optional end of stream marker
+            while !data.is_empty() {
+                match stream.write(data)? {
+                    0 => break,
+                    written => data = &data[written..],
+                }
+            }
+            if let Some(output) = stream.get_output_mut() {
+                self.remaining.append(output);
+            }
+            if n == 0 || !data.is_empty() {
+                if let Some(stream) = self.stream.take() {
+                    self.remaining
+                        .append(&mut stream.finish().map_err(io::Error::from)?);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for LzmaReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.header_read {
+            self.read_header()?;
+        }
+        self.fill()?;
+        let n = self.remaining.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        if n == 0 && !buf.is_empty() && self.hasher.clone().finalize() != self.crc32 {
+            return Err(io::Error::other("Invalid checksum"));
+        }
+        Ok(n)
+    }
+}
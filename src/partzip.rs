@@ -2,20 +2,39 @@ use chrono::NaiveDate;
 use chrono::NaiveDateTime;
 use chrono::NaiveTime;
 use conv::{NoError, ValueFrom};
-use curl::easy::Easy;
+use curl::easy::{Easy, List};
+#[cfg(feature = "checksums")]
+use digest::Digest;
 use num_traits::ToPrimitive;
 use serde::Deserialize;
 use serde::Serialize;
-use std::cell::RefCell;
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::ffi::CStr;
+use std::fs;
+use std::fs::File;
 use std::io;
 use std::io::BufReader;
 use std::io::ErrorKind;
-use std::time::Duration;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard, OnceLock, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 use thiserror::Error;
 use zip::result::ZipError;
 
+use super::cache::{BlockCache, BLOCK_LEN, BLOCK_SIZE};
+use super::lzma::LzmaReader;
+#[cfg(feature = "ppmd")]
+use super::ppmd;
+use super::recover;
+use super::spill;
+use super::split;
 use super::utils;
 
+use zip::read::ZipFile;
 use zip::ZipArchive;
 
 /// Enum for errors thrown by the partialzip crate
@@ -25,54 +44,191 @@ pub enum PartialZipError {
     #[error("Invalid URL")]
     InvalidUrl,
     /// The file is not found
-    #[error("File Not Found")]
-    FileNotFound,
+    #[error("File Not Found: {name}")]
+    FileNotFound {
+        /// Name of the file requested, or the pattern nothing matched
+        name: String,
+    },
     /// Range request not supported
     #[error("Range request not supported")]
     RangeNotSupported,
+    /// The password of an encrypted file is wrong
+    #[error("Invalid password")]
+    InvalidPassword,
     /// The compression scheme is currently not supported
-    #[error("{0} is a Unsupported Compression")]
+    #[error("{}", utils::unsupported_compression(*.0))]
     UnsupportedCompression(u16),
     /// Error for the underlying zip crate
     #[error("zip error: {0}")]
-    ZipRsError(#[from] ZipError),
+    ZipRsError(#[source] ZipError),
+    /// The content served by the server isn't a zip archive
+    #[error("not a zip archive (Content-Type: {content_type}): {source}")]
+    NotAZip {
+        /// Content type announced by the server
+        content_type: String,
+        /// Error while parsing the archive
+        source: ZipError,
+    },
     /// `std::io::Error` wrapper
     #[error("io error: {0}")]
-    IOError(#[from] io::Error),
+    IOError(#[source] io::Error),
     /// Error for CURL
     #[error("CURL error: {0}")]
     CURLError(#[from] curl::Error),
+    /// Error for reqwest, used by the async API
+    #[cfg(feature = "async")]
+    #[error("reqwest error: {0}")]
+    ReqwestError(#[from] reqwest::Error),
+    /// Error of the AWS SDK, used by the S3 reader
+    #[cfg(feature = "s3")]
+    #[error("S3 error: {0}")]
+    S3Error(#[source] Box<aws_sdk_s3::Error>),
+    /// The server answered with an HTTP status which is neither a success nor a redirect
+    #[error("HTTP status {0}")]
+    HttpStatus(u32),
     /// `NoError` error
     #[error("NoError error: {0}")]
     NoError(#[from] NoError),
     /// Conversion Error
     #[error("Conversion error: {0}")]
     ConvError(#[from] conv::PosOverflow<u64>),
+    /// The glob pattern is invalid
+    #[error("invalid glob pattern: {0}")]
+    PatternError(#[from] glob::PatternError),
+    /// The entry path would be extracted outside of the destination directory
+    #[error("Unsafe path in the archive: {0}")]
+    UnsafePath(String),
+    /// The CRC32 of the downloaded content doesn't match the one stored in the archive
+    #[error("CRC32 mismatch: expected {expected:#010x}, got {actual:#010x}")]
+    CrcMismatch {
+        /// CRC32 stored in the central directory
+        expected: u32,
+        /// CRC32 computed on the downloaded content
+        actual: u32,
+    },
+    /// The size of the downloaded content doesn't match the uncompressed size stored in the archive
+    #[error("size mismatch: expected {expected} bytes, got {actual}")]
+    SizeMismatch {
+        /// Uncompressed size stored in the central directory
+        expected: u64,
+        /// Number of bytes written
+        actual: u64,
+    },
+    /// The structure of the archive is inconsistent, see [`PartialZip::verify_archive`]
+    #[error("invalid archive: {0}")]
+    InvalidArchive(String),
+    /// The normalized filename matches several files, see [`PartialZip::download_normalized`]
+    #[error("{name} matches several files: {}", candidates.join(", "))]
+    AmbiguousFilename {
+        /// Name of the file requested
+        name: String,
+        /// Names of the files it matches
+        candidates: Vec<String>,
+    },
+    /// The central directory exceeds a limit given to [`PartialZip::new_with_limits`]
+    #[error("the archive has {actual} {what}, more than the limit of {limit}")]
+    LimitExceeded {
+        /// What is limited, `entries` or `uncompressed bytes`
+        what: &'static str,
+        /// Value advertised by the central directory
+        actual: u64,
+        /// The limit
+        limit: u64,
+    },
+    /// A redirect refused by the [`RedirectPolicy`] of the reader
+    #[error("redirect to {0} refused")]
+    RedirectRefused(String),
+    /// The archive was replaced on the server since it was probed, its `ETag` or `Last-Modified` changed
+    #[error("the archive changed on the server")]
+    ArchiveChanged,
+    /// The download was cancelled with its token, see [`PartialZip::download_to_write_cancellable`]
+    #[error("the download was cancelled")]
+    Cancelled,
+    /// An option needs TLS but curl was built without a TLS backend, see the `rustls` feature
+    #[error("{0} needs curl built with TLS support, enable the rustls feature")]
+    TlsUnavailable(&'static str),
+}
+
+impl From<io::Error> for PartialZipError {
+    fn from(e: io::Error) -> Self {
+        // the reader wraps the errors of the requests, report them as they are
+        if e.get_ref()
+            .is_some_and(<dyn std::error::Error + Send + Sync>::is::<Self>)
+        {
+            if let Some(Ok(inner)) = e
+                .into_inner()
+                .map(<dyn std::error::Error + Send + Sync>::downcast::<Self>)
+            {
+                return *inner;
+            }
+            unreachable!("the inner error is a PartialZipError");
+        }
+        Self::IOError(e)
+    }
+}
+
+impl From<ZipError> for PartialZipError {
+    fn from(e: ZipError) -> Self {
+        match e {
+            ZipError::Io(e) => match Self::from(e) {
+                Self::IOError(e) => Self::ZipRsError(ZipError::Io(e)),
+                inner => inner,
+            },
+            e => Self::ZipRsError(e),
+        }
+    }
 }
 
 /// Core struct of the crate representing a zip file we want to access partially
+///
+/// It's [`Send`] and [`Sync`], so it can be shared between threads, but the operations on the archive lock its single
+/// reader: the downloads started from several threads run one after the other, not in parallel.
+/// [`PartialZip::download_many`] and [`PartialZip::open_entry`] read from their own copy of the reader instead,
+/// with its own connection, so they are the ones to use to download several files at the same time.
+/// The archive is fetched with a [`PartialReader`] through curl, or with the [`RangeReader`] `R` given to [`PartialZip::from_range_reader`].
 #[derive(Debug)]
-pub struct PartialZip {
+pub struct PartialZip<R: RangeReader = PartialReader> {
     /// URL of the zip archive
     url: String,
-    /// The archive object
-    archive: RefCell<ZipArchive<BufReader<PartialReader>>>,
+    /// The archive object, locked by every operation since its reader has a single position and connection
+    archive: Mutex<ZipArchive<ArchiveReader<R>>>,
     /// The archive size
     file_size: u64,
+    /// Number of HTTP requests done, shared with the reader of the archive
+    requests: Arc<AtomicU64>,
+    /// Number of bytes received, shared with the reader of the archive
+    bytes: Arc<AtomicU64>,
+    /// Offset where the central directory starts, or at least the lowest one read while parsing it
+    directory_start: u64,
+    /// Headers of the central directory for the fields the zip crate doesn't expose, parsed once the first time they're needed
+    ///
+    /// They are keyed by the absolute offset of their local header, like [`ZipFile::header_start`]: the zip crate
+    /// keeps only one file for the names found several times, so their position isn't the one in the central directory.
+    headers: OnceLock<BTreeMap<u64, CentralHeader>>,
+    /// URL of the archive after following the redirects
+    resolved_url: Option<String>,
+    /// Token of the cancellable download in progress, shared with the reader of the archive
+    cancel: Arc<Mutex<Option<Arc<AtomicBool>>>>,
 }
 
 /// Compression methods for the files inside the archive. Redefined structure to make it serializable.
 /// Maps directly to the zip crate `zip::CompressionMethod` enum.
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum PartialZipCompressionMethod {
     /// Stored (no compression)
     Stored,
     /// Deflated compression
     Deflated,
+    /// Deflate64 (enhanced deflate) compression
+    Deflate64,
     /// bzip2 compression
     Bzip2,
     /// zstd compression
     Zstd,
+    /// LZMA compression
+    Lzma,
+    /// `PPMd` compression, supported with the `ppmd` feature
+    Ppmd,
     /// unsupported compression
     Unsupported,
 }
@@ -82,16 +238,166 @@ impl From<zip::CompressionMethod> for PartialZipCompressionMethod {
         match value {
             zip::CompressionMethod::Stored => Self::Stored,
             zip::CompressionMethod::Deflated => Self::Deflated,
+            zip::CompressionMethod::Deflate64 => Self::Deflate64,
             zip::CompressionMethod::Bzip2 => Self::Bzip2,
             zip::CompressionMethod::Zstd => Self::Zstd,
+            zip::CompressionMethod::LZMA => Self::Lzma,
+            zip::CompressionMethod::PPMD => Self::Ppmd,
+            _ => Self::Unsupported,
+        }
+    }
+}
+
+impl PartialZipCompressionMethod {
+    /// Returns the compression method with the given identifier in the headers
+    const fn from_id(id: u16) -> Self {
+        match id {
+            0 => Self::Stored,
+            8 => Self::Deflated,
+            9 => Self::Deflate64,
+            12 => Self::Bzip2,
+            14 => Self::Lzma,
+            93 => Self::Zstd,
+            98 => Self::Ppmd,
             _ => Self::Unsupported,
         }
     }
 }
 
+/// Totals over the files of an archive, see [`PartialZip::summary`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveSummary {
+    /// Number of files, directories included
+    pub files: usize,
+    /// Total compressed size of the files
+    pub compressed_size: u64,
+    /// Total uncompressed size of the files
+    pub uncompressed_size: u64,
+    /// Number of files compressed with each method
+    pub methods: BTreeMap<PartialZipCompressionMethod, usize>,
+}
+
+impl ArchiveSummary {
+    /// Returns the total compressed size divided by the total uncompressed one, `None` if there's nothing to compress
+    #[must_use]
+    pub fn ratio(&self) -> Option<f64> {
+        ratio(self.compressed_size, self.uncompressed_size)
+    }
+}
+
+/// Returns `compressed` divided by `uncompressed`, `None` if `uncompressed` is zero
+#[allow(clippy::cast_precision_loss)] // an approximation is enough for a ratio
+fn ratio(compressed: u64, uncompressed: u64) -> Option<f64> {
+    (uncompressed != 0).then(|| compressed as f64 / uncompressed as f64)
+}
+
+/// How [`PartialZip::resume_download`] completed a partially written file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResumeStatus {
+    /// The content of the stored file was fetched from the given offset, right after the bytes already written
+    Resumed(u64),
+    /// The compressed file was fetched and decompressed again from the start, skipping the given number of bytes already written
+    Restarted(u64),
+}
+
+/// Bytes of the archive a download of a file would fetch, see [`PartialZip::download_plan`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadPlan {
+    /// Ranges of the archive read, first and last byte included: the local header, then the compressed content if it's not empty
+    pub ranges: Vec<(u64, u64)>,
+    /// Total number of bytes of the ranges
+    pub total_bytes: u64,
+}
+
 /// Struct for a file in the zip file with some attributes
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+// independent flags of the file, serialized as they are
+#[allow(clippy::struct_excessive_bools)]
 pub struct PartialZipFileDetailed {
+    /// Filename
+    pub name: String,
+    /// Compressed size of the file
+    pub compressed_size: u64,
+    /// Uncompressed size of the file
+    pub uncompressed_size: u64,
+    /// How it has been compressed (compression method, like bzip2, deflate, etc.)
+    pub compression_method: PartialZipCompressionMethod,
+    /// Is the compression supported or not by this crate?
+    pub supported: bool,
+    /// The date the file was last modified
+    pub last_modified: Option<NaiveDateTime>,
+    /// Is it a directory, from its name ending with `/` or its Unix mode?
+    pub is_dir: bool,
+    /// Is it a Unix symlink? Its content is the path of the target, see [`PartialZip::read_link`]
+    pub is_symlink: bool,
+    /// Was it written in streaming mode, with its sizes and CRC32 in a data descriptor after the content instead of the local header?
+    pub has_data_descriptor: bool,
+    /// Version of the zip specification needed to extract it, like 20 for 2.0, from its central header
+    pub version_needed: u16,
+}
+
+/// Order of the files returned by [`PartialZip::list_sorted`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortKey {
+    /// By name ignoring the case, names differing only by their case keep their byte order
+    #[default]
+    Name,
+    /// By compressed size, smallest first
+    CompressedSize,
+    /// By uncompressed size, smallest first
+    UncompressedSize,
+}
+
+/// Hash algorithms of [`PartialZip::hash_entry`]
+#[cfg(feature = "checksums")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    /// MD5, only to compare with published hashes, it's broken
+    Md5,
+    /// SHA-1
+    Sha1,
+    /// SHA-256
+    #[default]
+    Sha256,
+}
+
+/// Attributes of the entries restored on the extracted files, see [`PartialZip::restore_attributes`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Preserve {
+    /// Nothing, the files get the default permissions and the time they're written
+    #[default]
+    Nothing,
+    /// The modification time, and on Unix the permissions without the setuid and setgid bits
+    Attributes,
+    /// Like `Attributes`, keeping the setuid and setgid bits
+    AttributesWithSetid,
+}
+
+/// Struct for a file in the zip file with all its attributes
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialZipFileFull {
+    /// Filename
+    pub name: String,
+    /// Compressed size of the file
+    pub compressed_size: u64,
+    /// Uncompressed size of the file
+    pub uncompressed_size: u64,
+    /// How it has been compressed (compression method, like bzip2, deflate, etc.)
+    pub compression_method: PartialZipCompressionMethod,
+    /// Is the compression supported or not by this crate?
+    pub supported: bool,
+    /// The date the file was last modified, the archive has no time zone so it's given as UTC
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_modified: Option<time::OffsetDateTime>,
+    /// CRC32 of the uncompressed content
+    pub crc32: u32,
+    /// Unix permission bits, if the archive stores them
+    pub unix_mode: Option<u32>,
+}
+
+/// Struct for a file in the zip file with its comment and extra fields
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialZipFileExtra {
     /// Filename
     pub name: String,
     /// Compressed size of the file
@@ -102,6 +408,652 @@ pub struct PartialZipFileDetailed {
     pub supported: bool,
     /// The date the file was last modified
     pub last_modified: Option<NaiveDateTime>,
+    /// Comment of the file
+    pub comment: String,
+    /// Raw extra fields of the file in the central directory
+    pub extra_field: Vec<u8>,
+    /// The UTC time the file was last modified from the NTFS or Unix timestamp extra field, if any.
+    ///
+    /// Unlike `last_modified`, it doesn't depend on the time zone of the tool which created the archive.
+    pub modified: Option<NaiveDateTime>,
+}
+
+/// Returns if the compression method is supported by this build, see [`PartialZip::supported_methods`]
+fn is_supported(compression_method: zip::CompressionMethod) -> bool {
+    PartialZip::supported_methods().contains(&compression_method.into())
+}
+
+/// Convert the zip last modification date to a [`NaiveDateTime`], if it's valid
+fn last_modified(file: &ZipFile) -> Option<NaiveDateTime> {
+    let date = NaiveDate::from_ymd_opt(
+        file.last_modified().year().into(),
+        file.last_modified().month().into(),
+        file.last_modified().day().into(),
+    );
+    let time = NaiveTime::from_hms_opt(
+        file.last_modified().hour().into(),
+        file.last_modified().minute().into(),
+        file.last_modified().second().into(),
+    );
+    if let (Some(d), Some(t)) = (date, time) {
+        Some(NaiveDateTime::new(d, t))
+    } else {
+        None
+    }
+}
+
+/// Find the modification time in the NTFS (0x000a) or Unix extended timestamp (0x5455) extra field
+fn extra_modified(mut extra: &[u8]) -> Option<NaiveDateTime> {
+    while let [id0, id1, len0, len1, rest @ ..] = extra {
+        let len = usize::from(u16::from_le_bytes([*len0, *len1]));
+        let data = rest.get(..len)?;
+        let modified = match u16::from_le_bytes([*id0, *id1]) {
+            0x000a => ntfs_modified(data),
+            0x5455 => unix_modified(data),
+            _ => None,
+        };
+        if modified.is_some() {
+            return modified;
+        }
+        extra = &rest[len..];
+    }
+    None
+}
+
+/// Parse the modification time of the NTFS extra field, in 100ns intervals since 1601-01-01
+fn ntfs_modified(data: &[u8]) -> Option<NaiveDateTime> {
+    /// Seconds between 1601-01-01 and the Unix epoch
+    const EPOCH_OFFSET: i64 = 11_644_473_600;
+    // skip the reserved field, then walk the attributes looking for the timestamps one
+    let mut attributes = data.get(4..)?;
+    while let [tag0, tag1, size0, size1, rest @ ..] = attributes {
+        let size = usize::from(u16::from_le_bytes([*size0, *size1]));
+        let value = rest.get(..size)?;
+        if u16::from_le_bytes([*tag0, *tag1]) == 1 {
+            let ticks = u64::from_le_bytes(value.get(..8)?.try_into().ok()?);
+            let secs = i64::try_from(ticks / 10_000_000).ok()? - EPOCH_OFFSET;
+            let nanos = u32::try_from(ticks % 10_000_000).ok()? * 100;
+            return chrono::DateTime::from_timestamp(secs, nanos).map(|d| d.naive_utc());
+        }
+        attributes = &rest[size..];
+    }
+    None
+}
+
+/// Parse the modification time of the Unix extended timestamp extra field, in seconds since the epoch
+fn unix_modified(data: &[u8]) -> Option<NaiveDateTime> {
+    let [flags, time @ ..] = data else {
+        return None;
+    };
+    if flags & 1 == 0 {
+        return None;
+    }
+    let secs = i32::from_le_bytes(time.get(..4)?.try_into().ok()?);
+    chrono::DateTime::from_timestamp(secs.into(), 0).map(|d| d.naive_utc())
+}
+
+/// Find the index of `filename` in the archive
+///
+/// The names without the UTF-8 flag are decoded from CP437, but some tools write them in UTF-8 anyway:
+/// `filename` also matches a name whose bytes are the UTF-8 encoding of `filename`.
+pub(crate) fn index_for_name<R: io::Read + io::Seek>(
+    archive: &ZipArchive<R>,
+    filename: &str,
+) -> Result<usize, PartialZipError> {
+    archive
+        .index_for_name(filename)
+        .or_else(|| {
+            // only the names with non-ASCII characters are decoded differently
+            (!filename.is_ascii()).then(|| {
+                archive.file_names().position(|name| {
+                    !name.is_ascii()
+                        && utils::to_cp437(name).is_some_and(|raw| raw == filename.as_bytes())
+                })
+            })?
+        })
+        .ok_or_else(|| PartialZipError::FileNotFound {
+            name: filename.to_string(),
+        })
+}
+
+/// Read the file at `index` to memory, verifying its CRC32 while it is streamed
+fn read_verified<R: io::Read + io::Seek>(
+    archive: &mut ZipArchive<R>,
+    index: usize,
+) -> Result<Vec<u8>, PartialZipError> {
+    let mut content: Vec<u8> = Vec::new();
+    copy_verified(archive, index, &mut content)?;
+    Ok(content)
+}
+
+/// Write the decompressed content of the file at `index` to `writer`, verifying its CRC32 while it is streamed and its size
+fn copy_verified<R: io::Read + io::Seek>(
+    archive: &mut ZipArchive<R>,
+    index: usize,
+    writer: &mut dyn io::Write,
+) -> Result<(), PartialZipError> {
+    let (expected, size) = {
+        let file = archive.by_index_raw(index)?;
+        (file.crc32(), file.size())
+    };
+    let mut file = open_index(archive, index)?;
+    let mut writer = Crc32Writer::new(writer);
+    let copied = io::copy(&mut file, &mut writer);
+    drop(file);
+    let (actual, written) = writer.finalize();
+    match copied {
+        // an error before the end of the content, like a failed request, isn't about the checksum
+        Err(e) if written < size => Err(e.into()),
+        // the zip crate itself errors out at EOF on a bad checksum, so a mismatch takes precedence
+        _ if actual != expected => Err(PartialZipError::CrcMismatch { expected, actual }),
+        Err(e) => Err(e.into()),
+        Ok(_) => check_size(size, written),
+    }
+}
+
+/// Write the decompressed content of the file at `index` to `writer`, checking its size
+fn copy_index<R: io::Read + io::Seek>(
+    archive: &mut ZipArchive<R>,
+    index: usize,
+    writer: &mut dyn io::Write,
+) -> Result<(), PartialZipError> {
+    let expected = archive.by_index_raw(index)?.size();
+    let actual = io::copy(&mut open_index(archive, index)?, writer)?;
+    check_size(expected, actual)
+}
+
+/// Open the decompressed content of the file at `index`.
+///
+/// LZMA entries are decoded by [`LzmaReader`] since the zip crate doesn't handle their zip specific header,
+/// and `PPMd` entries by [`ppmd::PpmdReader`] since it doesn't handle them at all.
+pub(crate) fn open_index<R: io::Read + io::Seek>(
+    archive: &mut ZipArchive<R>,
+    index: usize,
+) -> Result<Box<dyn io::Read + '_>, PartialZipError> {
+    let compression = {
+        let file = archive.by_index_raw(index)?;
+        let start = file.data_start();
+        // the end is included like in the range requests, the local header is always before the data
+        log::debug!(
+            "fetching {} ({:?}) from bytes {start}-{}",
+            file.name(),
+            file.compression(),
+            start + file.compressed_size() - 1
+        );
+        file.compression()
+    };
+    if compression == zip::CompressionMethod::LZMA {
+        let file = archive.by_index_raw(index)?;
+        let (size, crc32) = (file.size(), file.crc32());
+        return Ok(Box::new(LzmaReader::new(file, size, crc32)));
+    }
+    #[cfg(feature = "ppmd")]
+    if compression == zip::CompressionMethod::PPMD {
+        let file = archive.by_index_raw(index)?;
+        let (size, crc32) = (file.size(), file.crc32());
+        return Ok(Box::new(ppmd::PpmdReader::new(file, size, crc32)?));
+    }
+    // tell which method is missing, the zip crate doesn't
+    #[allow(deprecated)]
+    if let zip::CompressionMethod::Unsupported(id) = compression {
+        return Err(PartialZipError::UnsupportedCompression(id));
+    }
+    Ok(Box::new(archive.by_index(index)?))
+}
+
+/// Check the number of bytes written against the uncompressed size stored in the archive
+///
+/// The CRC32 is only checked when the decompressor reaches the end of the content,
+/// a stream ending early because of a truncated response is caught here.
+const fn check_size(expected: u64, actual: u64) -> Result<(), PartialZipError> {
+    if expected != actual {
+        return Err(PartialZipError::SizeMismatch { expected, actual });
+    }
+    Ok(())
+}
+
+/// Signature of a file header in the central directory
+pub(crate) const CENTRAL_HEADER_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x01, 0x02];
+
+/// Location of a file of the archive, read from its header in the central directory
+#[derive(Debug, Clone)]
+struct CentralHeader {
+    /// Offset of the local header, relative to the start of the zip
+    offset: u64,
+    /// Compressed size of the file
+    compressed_size: u64,
+    /// Uncompressed size of the file
+    uncompressed_size: u64,
+    /// CRC32 of the uncompressed content
+    crc32: u32,
+    /// Identifier of the compression method
+    compression_method: u16,
+    /// Filename, in the encoding of the archive
+    name: Vec<u8>,
+    /// Length of the extra fields in the central directory, usually the same ones as in the local header
+    extra_len: u64,
+    /// General purpose bit flags
+    flags: u16,
+    /// Version needed to extract the file
+    version_needed: u16,
+}
+
+/// Returns the headers of the central directory at the start of `directory`, in the order of the archive
+fn central_headers(directory: &[u8]) -> Result<Vec<CentralHeader>, PartialZipError> {
+    let invalid = || ZipError::InvalidArchive("invalid central directory header");
+    let u16_at = |record: &[u8], at: usize| le_u16(record, at).ok_or_else(invalid);
+    let mut headers = Vec::new();
+    let mut record = directory;
+    while record.starts_with(&CENTRAL_HEADER_SIGNATURE) {
+        let name_len = usize::from(u16_at(record, 28)?);
+        let extra_len = usize::from(u16_at(record, 30)?);
+        let comment_len = usize::from(u16_at(record, 32)?);
+        let extra = record
+            .get(46 + name_len..46 + name_len + extra_len)
+            .ok_or_else(invalid)?;
+        // the zip64 extra field has the 64 bit values of the fields set to u32::MAX, in this order
+        let mut zip64 = zip64_field(extra)
+            .unwrap_or_default()
+            .chunks_exact(8)
+            .filter_map(|value| value.try_into().ok().map(u64::from_le_bytes));
+        let mut field = |at: usize| match le_u32(record, at).ok_or_else(invalid)? {
+            u32::MAX => zip64.next().ok_or_else(invalid),
+            value => Ok(u64::from(value)),
+        };
+        let uncompressed_size = field(24)?;
+        let compressed_size = field(20)?;
+        let offset = field(42)?;
+        headers.push(CentralHeader {
+            offset,
+            compressed_size,
+            uncompressed_size,
+            crc32: le_u32(record, 16).ok_or_else(invalid)?,
+            compression_method: u16_at(record, 10)?,
+            name: record.get(46..46 + name_len).ok_or_else(invalid)?.to_vec(),
+            extra_len: extra_len.to_u64().unwrap_or_default(),
+            flags: u16_at(record, 8)?,
+            version_needed: u16_at(record, 6)?,
+        });
+        record = record
+            .get(46 + name_len + extra_len + comment_len..)
+            .ok_or_else(invalid)?;
+    }
+    Ok(headers)
+}
+
+/// Returns the header of `headers` for the local header at the absolute offset `header_start`
+fn header_at(
+    headers: &BTreeMap<u64, CentralHeader>,
+    header_start: u64,
+) -> Result<&CentralHeader, PartialZipError> {
+    headers.get(&header_start).ok_or_else(|| {
+        ZipError::InvalidArchive("no central directory header for the local header").into()
+    })
+}
+
+/// Offset basis of the 64 bit FNV-1a hash
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+/// Prime of the 64 bit FNV-1a hash
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Add `bytes` to the 64 bit FNV-1a `hash`, which is the same on every platform and version unlike the ones of the standard library
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// Returns the content of the zip64 extended information extra field, if there is one
+pub(crate) fn zip64_field(mut extra: &[u8]) -> Option<&[u8]> {
+    while let [id0, id1, len0, len1, rest @ ..] = extra {
+        let len = usize::from(u16::from_le_bytes([*len0, *len1]));
+        let data = rest.get(..len)?;
+        if u16::from_le_bytes([*id0, *id1]) == 0x0001 {
+            return Some(data);
+        }
+        extra = &rest[len..];
+    }
+    None
+}
+
+/// Read a little endian `u16` at `at` in `data`
+pub(crate) fn le_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+/// Read a little endian `u32` at `at` in `data`
+pub(crate) fn le_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Signature of the end of central directory record
+pub(crate) const END_OF_CENTRAL_DIRECTORY_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x05, 0x06];
+/// Signature of a local file header
+pub(crate) const LOCAL_HEADER_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x03, 0x04];
+/// Size of the end of central directory record without the comment
+const END_OF_CENTRAL_DIRECTORY_LEN: usize = 22;
+
+/// Returns the number of files counted by the end of central directory record at the end of `directory`
+///
+/// The record is the last one whose comment length reaches exactly the end of the archive,
+/// `None` if there is no such record.
+fn end_of_central_directory_entries(directory: &[u8]) -> Option<u16> {
+    let last = directory.len().checked_sub(END_OF_CENTRAL_DIRECTORY_LEN)?;
+    (0..=last).rev().find_map(|start| {
+        let record = &directory[start..];
+        let comment_len = usize::from(le_u16(record, 20)?);
+        (record.starts_with(&END_OF_CENTRAL_DIRECTORY_SIGNATURE)
+            && END_OF_CENTRAL_DIRECTORY_LEN + comment_len == record.len())
+        .then(|| le_u16(record, 10))
+        .flatten()
+    })
+}
+
+/// Default size of the buffer the content of an entry goes through when it's extracted to a file
+pub const DEFAULT_ENTRY_BUFFER: usize = 64 * 1024;
+
+/// Write the decompressed content of the file at `index` to `path`, creating the parent directories, checking its size
+///
+/// The content goes through a buffer of `buffer_len` bytes, it's never held whole in memory.
+fn extract_index<R: io::Read + io::Seek>(
+    archive: &mut ZipArchive<R>,
+    index: usize,
+    path: &Path,
+    buffer_len: usize,
+) -> Result<(), PartialZipError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let expected = archive.by_index_raw(index)?.size();
+    let actual = copy_bounded(
+        open_index(archive, index)?,
+        &mut File::create(path)?,
+        buffer_len,
+    )?;
+    check_size(expected, actual)
+}
+
+/// Copy `reader` to `writer` through a single buffer of `buffer_len` bytes (at least one), returning the number of bytes copied
+fn copy_bounded(
+    reader: impl io::Read,
+    writer: &mut impl io::Write,
+    buffer_len: usize,
+) -> io::Result<u64> {
+    // the copy goes through the buffer of the reader instead of allocating its own
+    io::copy(
+        &mut BufReader::with_capacity(buffer_len.max(1), reader),
+        writer,
+    )
+}
+
+/// Write a new file at `dest` with `write`, never replacing an existing one, see [`PartialZip::download_to_file`]
+///
+/// The content is written to a temporary file in the same directory, linked to `dest` only once `write` succeeded,
+/// which fails if `dest` exists even when it's created during the download. On the file systems without hard links,
+/// `dest` is created empty first and then replaced by the temporary file.
+///
+/// # Errors
+/// Will return a [`PartialZipError`] depending on what happened, an [`ErrorKind::AlreadyExists`] io error if `dest` exists
+pub fn write_new_file(
+    dest: &Path,
+    write: impl FnOnce(&mut File) -> Result<(), PartialZipError>,
+) -> Result<(), PartialZipError> {
+    write_atomically(dest, false, write)
+}
+
+/// Number of temporary files created by this process, so the threads writing the same file each have their own
+static TEMP_FILES: AtomicU64 = AtomicU64::new(0);
+
+/// Write `dest` with `write`, through a temporary file in the same directory moved to `dest` on success
+///
+/// An existing `dest` is replaced only if `replace` is set.
+fn write_atomically(
+    dest: &Path,
+    replace: bool,
+    write: impl FnOnce(&mut File) -> Result<(), PartialZipError>,
+) -> Result<(), PartialZipError> {
+    let name = dest.file_name().ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid destination {}", dest.display()),
+        )
+    })?;
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(name);
+    temp_name.push(format!(
+        ".{}.{}.part",
+        std::process::id(),
+        TEMP_FILES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let temp = dest.with_file_name(temp_name);
+    // never a file left by another process, which isn't removed on errors
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temp)?;
+    let result = write(&mut file)
+        .and_then(|()| Ok(file.sync_all()?))
+        .and_then(|()| {
+            drop(file);
+            if replace {
+                fs::rename(&temp, dest)?;
+            } else {
+                link_new(&temp, dest)?;
+                let _ = fs::remove_file(&temp);
+            }
+            Ok(())
+        });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Make `temp` available as `dest`, failing with an [`ErrorKind::AlreadyExists`] io error if `dest` exists
+fn link_new(temp: &Path, dest: &Path) -> io::Result<()> {
+    // unlike a rename, the link fails if dest exists
+    match fs::hard_link(temp, dest) {
+        // some file systems, like FAT or some network shares, have no hard links
+        Err(e)
+            if matches!(
+                e.kind(),
+                ErrorKind::Unsupported | ErrorKind::PermissionDenied
+            ) =>
+        {
+            // an empty file reserves dest, then replaced by the content at once
+            fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(dest)?;
+            fs::rename(temp, dest).inspect_err(|_| {
+                let _ = fs::remove_file(dest);
+            })
+        }
+        result => result,
+    }
+}
+
+/// Download `filename` under `out_dir`, returning the path written
+fn download_one<R: io::Read + io::Seek>(
+    archive: &mut ZipArchive<R>,
+    filename: &str,
+    out_dir: &Path,
+) -> Result<PathBuf, PartialZipError> {
+    let path = utils::entry_path(filename)
+        .map(|path| out_dir.join(path))
+        .ok_or_else(|| PartialZipError::UnsafePath(filename.to_string()))?;
+    let index = index_for_name(archive, filename)?;
+    extract_index(archive, index, &path, DEFAULT_ENTRY_BUFFER)?;
+    Ok(path)
+}
+
+impl PartialZipFileDetailed {
+    /// Collect the details of a file of the archive
+    pub(crate) fn from_zip_file(file: &ZipFile) -> Self {
+        Self {
+            name: file.name().to_string(),
+            compressed_size: file.compressed_size(),
+            uncompressed_size: file.size(),
+            compression_method: file.compression().into(),
+            supported: is_supported(file.compression()),
+            last_modified: last_modified(file),
+            is_dir: file.is_dir() || unix_file_type(file) == Some(S_IFDIR),
+            is_symlink: unix_file_type(file) == Some(S_IFLNK),
+            // only in the headers, read separately
+            has_data_descriptor: false,
+            version_needed: 0,
+        }
+    }
+
+    /// Returns the compressed size divided by the uncompressed one, `None` for an empty file
+    #[must_use]
+    pub fn ratio(&self) -> Option<f64> {
+        ratio(self.compressed_size, self.uncompressed_size)
+    }
+}
+
+/// Bit of the general purpose flags set when the sizes and CRC32 of a file are in a data descriptor
+const DATA_DESCRIPTOR_FLAG: u16 = 0x0008;
+
+/// Returns if a file has its sizes and CRC32 in a data descriptor, from the flags of its central header at `offset` in `reader`
+///
+/// The sizes in its local header are zero, the ones of the central directory are used to read it.
+#[cfg(feature = "async")]
+pub(crate) fn data_descriptor_flag<R: io::Read + io::Seek>(
+    reader: &mut R,
+    offset: u64,
+) -> io::Result<bool> {
+    reader.seek(io::SeekFrom::Start(offset + 8))?;
+    let mut flags = [0; 2];
+    reader.read_exact(&mut flags)?;
+    Ok(u16::from_le_bytes(flags) & DATA_DESCRIPTOR_FLAG != 0)
+}
+
+/// Returns the version needed to extract a file, from its central header at `offset` in `reader`
+#[cfg(feature = "async")]
+pub(crate) fn version_needed<R: io::Read + io::Seek>(
+    reader: &mut R,
+    offset: u64,
+) -> io::Result<u16> {
+    reader.seek(io::SeekFrom::Start(offset + 6))?;
+    let mut version = [0; 2];
+    reader.read_exact(&mut version)?;
+    Ok(u16::from_le_bytes(version))
+}
+
+/// Mask of the file type in a Unix mode
+const S_IFMT: u32 = 0o170_000;
+/// Unix file type of a directory
+const S_IFDIR: u32 = 0o040_000;
+/// Unix file type of a symbolic link
+const S_IFLNK: u32 = 0o120_000;
+/// Unix file type of a regular file
+#[cfg(unix)]
+const S_IFREG: u32 = 0o100_000;
+
+/// Returns the Unix file type bits of a file, if the archive stores its Unix mode
+fn unix_file_type(file: &ZipFile) -> Option<u32> {
+    file.unix_mode().map(|mode| mode & S_IFMT)
+}
+
+/// Setuid and setgid bits of a Unix mode
+#[cfg(unix)]
+const S_ISID: u32 = 0o6000;
+
+/// Returns the permissions to give to the file extracted from `file`, `None` if the archive doesn't store them
+///
+/// Only the permission bits are kept, without the setuid and setgid ones unless `setid`.
+/// A mode the owner can't even read is bogus, the default one is used instead. The modes of the other
+/// types, like the usual `0o777` of a symlink extracted as a file holding its target, aren't applied either.
+#[cfg(unix)]
+fn extracted_mode(file: &ZipFile, setid: bool) -> Option<u32> {
+    let mode = file.unix_mode()?;
+    if !matches!(mode & S_IFMT, 0 | S_IFREG) {
+        return None;
+    }
+    let mut mode = mode & 0o7777;
+    if !setid {
+        mode &= !S_ISID;
+    }
+    Some(if mode & 0o400 == 0 { 0o644 } else { mode })
+}
+
+/// Set the modification time and the permissions of the extracted `path` to the ones of `file`
+///
+/// The permissions are only set on Unix, and only if the archive stores them.
+fn apply_attributes(file: &ZipFile, path: &Path, preserve: Preserve) -> io::Result<()> {
+    if preserve == Preserve::Nothing {
+        return Ok(());
+    }
+    let secs = last_modified(file).and_then(|date| u64::try_from(date.and_utc().timestamp()).ok());
+    if let Some(secs) = secs {
+        // owning the file is enough on Unix, Windows needs a handle allowed to write
+        let handle = if cfg!(windows) {
+            fs::OpenOptions::new().write(true).open(path)?
+        } else {
+            File::open(path)?
+        };
+        handle.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))?;
+    }
+    #[cfg(unix)]
+    if let Some(mode) = extracted_mode(file, preserve == Preserve::AttributesWithSetid) {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    }
+    Ok(())
+}
+
+/// Returns the tar header of a regular file or a symlink with the size, the mode and the date of `file`
+#[cfg(feature = "tar")]
+fn tar_header(file: &ZipFile) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    if unix_file_type(file) == Some(S_IFLNK) {
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+    } else {
+        header.set_size(file.size());
+    }
+    let default_mode = if file.is_dir() { 0o755 } else { 0o644 };
+    header.set_mode(file.unix_mode().map_or(default_mode, |mode| mode & 0o7777));
+    header.set_mtime(
+        last_modified(file)
+            .and_then(|date| date.and_utc().timestamp().to_u64())
+            .unwrap_or_default(),
+    );
+    header
+}
+
+impl PartialZipFileExtra {
+    /// Collect the details of a file of the archive with its comment and extra fields
+    pub(crate) fn from_zip_file(file: &ZipFile) -> Self {
+        let extra_field = file.extra_data().unwrap_or_default().to_vec();
+        Self {
+            name: file.name().to_string(),
+            compressed_size: file.compressed_size(),
+            compression_method: file.compression().into(),
+            supported: is_supported(file.compression()),
+            last_modified: last_modified(file),
+            comment: file.comment().to_string(),
+            modified: extra_modified(&extra_field),
+            extra_field,
+        }
+    }
+}
+
+impl PartialZipFileFull {
+    /// Collect all the attributes of a file of the archive
+    pub(crate) fn from_zip_file(file: &ZipFile) -> Self {
+        Self {
+            name: file.name().to_string(),
+            compressed_size: file.compressed_size(),
+            uncompressed_size: file.size(),
+            compression_method: file.compression().into(),
+            supported: is_supported(file.compression()),
+            last_modified: file.last_modified().to_time().ok(),
+            crc32: file.crc32(),
+            unix_mode: file.unix_mode(),
+        }
+    }
 }
 
 impl PartialZip {
@@ -114,196 +1066,3048 @@ impl PartialZip {
     }
 
     /// Create a new [`PartialZip`]
+    ///
+    /// With the `s3` feature, the `s3://bucket/key` URLs are read with [`crate::S3RangeReader`] in the region
+    /// of the standard AWS provider chain, the ranges are always supported there.
     /// # Errors
     ///
     /// Will return a [`PartialZipError`] enum depending on what error happened
     pub fn new_check_range(url: &dyn ToString, check_range: bool) -> Result<Self, PartialZipError> {
-        let reader = PartialReader::new_check_range(url, check_range)?;
+        #[cfg(feature = "s3")]
+        if utils::is_s3_url(&url.to_string()) {
+            let url = url.to_string();
+            let (bucket, key) = utils::s3_location(&url).ok_or(PartialZipError::InvalidUrl)?;
+            let reader = crate::s3::S3RangeReader::open(&bucket, &key, None)?;
+            let mut pz = Self::from_assembled(Assembled::S3(reader))?;
+            pz.url = url;
+            return Ok(pz);
+        }
+        Self::from_reader(PartialReader::new_check_range(url, check_range)?)
+    }
+
+    /// Create a new [`PartialZip`] from the first of the mirrors `urls` serving a valid archive
+    ///
+    /// The mirrors are tried in order, the one which succeeded is used for all the following requests.
+    /// # Errors
+    ///
+    /// Will return the error of the last mirror tried if none of them succeeded,
+    /// [`PartialZipError::InvalidUrl`] if `urls` is empty
+    pub fn new_with_mirrors(urls: &[url::Url], check_range: bool) -> Result<Self, PartialZipError> {
+        let mut last_error = PartialZipError::InvalidUrl;
+        for url in urls {
+            match Self::new_check_range(url, check_range) {
+                Ok(pz) => return Ok(pz),
+                Err(e) => {
+                    log::warn!("mirror {url} failed: {e}");
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Create a new [`PartialZip`] authenticating with the given username and password
+    ///
+    /// The credentials aren't sent after a redirect to another host, see [`PartialReader::with_unrestricted_auth`].
+    /// # Errors
+    ///
+    /// Will return a [`PartialZipError`] enum depending on what error happened
+    pub fn new_with_auth(
+        url: &dyn ToString,
+        username: &str,
+        password: &str,
+        check_range: bool,
+    ) -> Result<Self, PartialZipError> {
+        Self::from_reader(PartialReader::new_with_auth(
+            url,
+            username,
+            password,
+            check_range,
+        )?)
+    }
+
+    /// Create a new [`PartialZip`] with a timeout to `connect` and a timeout for each `transfer`
+    /// # Errors
+    ///
+    /// Will return a [`PartialZipError`] enum depending on what error happened
+    pub fn new_with_timeout(
+        url: &dyn ToString,
+        connect: Duration,
+        transfer: Duration,
+        check_range: bool,
+    ) -> Result<Self, PartialZipError> {
+        Self::from_reader(PartialReader::new_with_timeout(
+            url,
+            connect,
+            transfer,
+            check_range,
+        )?)
+    }
+
+    /// Create a new [`PartialZip`] refusing the archives with more than `max_entries` files
+    /// or more than `max_total_uncompressed` bytes once decompressed
+    ///
+    /// The number of files and the size of the central directory are checked against the end of central directory
+    /// record, before the directory is downloaded, and the uncompressed size against the directory, before
+    /// downloading any content, to reject the zip bombs and the servers advertising absurd sizes.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] enum depending on what error happened,
+    /// [`PartialZipError::LimitExceeded`] if the archive exceeds a limit
+    pub fn new_with_limits(
+        url: &dyn ToString,
+        max_entries: usize,
+        max_total_uncompressed: u64,
+    ) -> Result<Self, PartialZipError> {
+        // a central header is at most 46 bytes followed by a name, an extra field and a comment of 65535 bytes each
+        const MAX_HEADER_LEN: u64 = 46 + 3 * 65535;
+        let mut reader = PartialReader::new(url)?;
+        reader.probe()?;
+        let (entries, directory_size) = split::directory_counts(&mut reader)?;
+        let max_entries = max_entries.to_u64().unwrap_or(u64::MAX);
+        if entries > max_entries {
+            return Err(PartialZipError::LimitExceeded {
+                what: "entries",
+                actual: entries,
+                limit: max_entries,
+            });
+        }
+        let max_directory_size = max_entries.saturating_mul(MAX_HEADER_LEN);
+        if directory_size > max_directory_size {
+            return Err(PartialZipError::LimitExceeded {
+                what: "central directory bytes",
+                actual: directory_size,
+                limit: max_directory_size,
+            });
+        }
+        let pz = Self::from_reader(reader)?;
+        let uncompressed = pz
+            .headers()?
+            .values()
+            .map(|header| header.uncompressed_size)
+            .sum::<u64>();
+        if uncompressed > max_total_uncompressed {
+            return Err(PartialZipError::LimitExceeded {
+                what: "uncompressed bytes",
+                actual: uncompressed,
+                limit: max_total_uncompressed,
+            });
+        }
+        Ok(pz)
+    }
+
+    /// Create a new [`PartialZip`] for an archive split in volumes, like `archive.z01`, `archive.z02`, ..., `archive.zip`
+    ///
+    /// `url` is the one of the last volume, ending with `.zip`, the number of volumes is read from its end of central directory
+    /// record and the others are next to it. The reads are sent to the volumes holding the offsets, as if they were a single archive.
+    /// An archive which isn't split is opened like with [`PartialZip::new`].
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] enum depending on what error happened,
+    /// [`PartialZipError::InvalidUrl`] if the URL doesn't end with `.zip` for an archive split in volumes
+    pub fn new_split(url: &dyn ToString) -> Result<Self, PartialZipError> {
+        let url = url.to_string();
+        let requests = Arc::new(AtomicU64::new(0));
+        let bytes = Arc::new(AtomicU64::new(0));
+        // all the volumes count in the stats of the archive
+        let open = |url: &str| {
+            let mut reader = PartialReader::new(&url)?;
+            reader.requests = requests.clone();
+            reader.bytes = bytes.clone();
+            reader.probe()?;
+            Ok::<_, PartialZipError>(reader)
+        };
+        let mut last = open(&url)?;
+        let disks = split::volume_count(&mut last)?;
+        if disks <= 1 {
+            return Self::from_reader(last);
+        }
+        let mut volumes = (1..disks)
+            .map(|volume| {
+                let volume_url =
+                    utils::split_volume_url(&url, volume).ok_or(PartialZipError::InvalidUrl)?;
+                Ok(Box::new(open(&volume_url)?) as Box<dyn RangeReader + Send>)
+            })
+            .collect::<Result<Vec<_>, PartialZipError>>()?;
+        volumes.push(Box::new(last));
+        let mut pz = Self::from_assembled(Assembled::Split(split::SplitVolumes::new(volumes)?))?;
+        pz.url = url;
+        pz.requests = requests;
+        pz.bytes = bytes;
+        Ok(pz)
+    }
+
+    /// Create a new [`PartialZip`] sending the given headers with every request
+    /// # Errors
+    ///
+    /// Will return a [`PartialZipError`] enum depending on what error happened
+    pub fn new_with_headers(
+        url: &dyn ToString,
+        headers: Vec<(String, String)>,
+        check_range: bool,
+    ) -> Result<Self, PartialZipError> {
+        Self::from_reader(PartialReader::new_check_range(url, check_range)?.with_headers(headers))
+    }
+
+    /// Create a new [`PartialZip`] sending the requests through the proxy at `proxy_url`, see [`PartialReader::new_with_proxy`]
+    /// # Errors
+    ///
+    /// Will return a [`PartialZipError`] enum depending on what error happened
+    pub fn new_with_proxy(
+        url: &dyn ToString,
+        proxy_url: &str,
+        check_range: bool,
+    ) -> Result<Self, PartialZipError> {
+        Self::from_reader(PartialReader::new_with_proxy(url, proxy_url, check_range)?)
+    }
+
+    /// Create a new [`PartialZip`] for an archive of `size` bytes, without the HEAD request probing its size
+    ///
+    /// For callers knowing the size already, for example from a manifest, see [`PartialReader::with_file_size`].
+    /// # Errors
+    ///
+    /// Will return a [`PartialZipError`] enum depending on what error happened
+    pub fn new_with_size(
+        url: &dyn ToString,
+        size: u64,
+        check_range: bool,
+    ) -> Result<Self, PartialZipError> {
+        Self::from_reader(PartialReader::new_check_range(url, check_range)?.with_file_size(size))
+    }
+
+    /// Create a new [`PartialZip`] reading the archive with a curl handle of `client`
+    ///
+    /// The handle is given back when the [`PartialZip`] is dropped, so opening many archives on the same host
+    /// with one client reuses the same connections.
+    /// # Errors
+    ///
+    /// Will return a [`PartialZipError`] enum depending on what error happened
+    pub fn from_client(
+        client: &PartialZipClient,
+        url: &dyn ToString,
+    ) -> Result<Self, PartialZipError> {
+        Self::from_reader(client.reader(url)?)
+    }
+
+    /// Create a new [`PartialZip`] from the central directory of the archive obtained with [`PartialZip::export_directory`]
+    ///
+    /// `directory` is the end of the archive of `total_size` bytes, it's used instead of fetching it again,
+    /// so the archive is opened and its file names listed without any request. The content of the files is still fetched from `url`.
+    /// # Errors
+    ///
+    /// Will return a [`PartialZipError`] enum depending on what error happened
+    pub fn new_with_directory(
+        url: &dyn ToString,
+        directory: &[u8],
+        total_size: u64,
+    ) -> Result<Self, PartialZipError> {
+        Self::from_reader(PartialReader::new(url)?.with_directory(directory, total_size)?)
+    }
+
+    /// Create a new [`PartialZip`] parsing the archive through the given [`PartialReader`]
+    ///
+    /// The reader keeps its configuration and its connection, and it's not probed again if it already was,
+    /// so the same archive can be reused with an already configured reader.
+    /// # Errors
+    ///
+    /// Will return a [`PartialZipError`] enum depending on what error happened
+    pub fn from_reader(mut reader: PartialReader) -> Result<Self, PartialZipError> {
+        // probe now so errors like the lack of range support are reported as they are
+        reader.probe()?;
+        let url = reader.url();
         let file_size = reader.file_size;
+        let requests = reader.requests.clone();
+        let bytes = reader.bytes.clone();
+        let content_type = reader.content_type.clone();
+        let resolved_url = reader.resolved_url.clone();
+        let cancel = reader.cancel.clone();
+        let lowest_read = reader.lowest_read.clone();
+        lowest_read.store(u64::MAX, Ordering::Relaxed);
         // higher capacity BufReader has better performances
-        let bufreader = BufReader::with_capacity(0x0010_0000, reader);
-        let archive = ZipArchive::new(bufreader)?;
+        let bufreader = BufReader::with_capacity(ARCHIVE_BUFFER_SIZE, reader);
+        // the URL doesn't need a zip extension, only the content matters
+        let archive = ZipArchive::new(ArchiveReader::Remote(Box::new(bufreader))).map_err(|e| {
+            match (e, content_type) {
+                // tell what the server sent instead, it's often an HTML error page
+                (e @ ZipError::InvalidArchive(_), Some(content_type)) => PartialZipError::NotAZip {
+                    content_type,
+                    source: e,
+                },
+                (e, _) => e.into(),
+            }
+        })?;
+        // the zip crate reads only the end of the archive while parsing
+        let directory_start = lowest_read.load(Ordering::Relaxed).min(file_size);
         Ok(Self {
-            url: url.to_string(),
-            archive: RefCell::new(archive),
+            url,
+            archive: Mutex::new(archive),
             file_size,
+            requests,
+            bytes,
+            directory_start,
+            headers: OnceLock::new(),
+            resolved_url,
+            cancel,
         })
     }
 
-    /// Returns the url for the [`PartialZip`]
-    pub fn url(&self) -> String {
-        self.url.clone()
+    /// Create a new [`PartialZip`] recovering what it can from a damaged archive, with the warnings of the recovery
+    ///
+    /// The archive is first opened like with [`PartialZip::new`], without any warning if it works.
+    /// When its central directory can't be parsed, the whole archive is downloaded and its files are found
+    /// from their local headers instead, so only the files whose local header and content are intact are listed.
+    /// It's meant for data recovery: the normal constructors always validate the archive strictly.
+    /// # Errors
+    ///
+    /// Will return a [`PartialZipError`] enum depending on what error happened
+    pub fn new_lenient(url: &dyn ToString) -> Result<(Self, Vec<String>), PartialZipError> {
+        let error = match Self::new(url) {
+            Ok(pz) => return Ok((pz, Vec::new())),
+            Err(PartialZipError::ZipRsError(e) | PartialZipError::NotAZip { source: e, .. }) => e,
+            Err(e) => return Err(e),
+        };
+        log::warn!("cannot parse the archive, recovering it from the local headers: {error}");
+        let mut warnings = vec![format!("invalid central directory: {error}")];
+        let mut reader = PartialReader::new(url)?;
+        reader.probe()?;
+        let data = match reader.len().checked_sub(1) {
+            Some(end) => reader.read_range(0, end)?,
+            None => Vec::new(),
+        };
+        let mut pz = Self::from_bytes(recover::rebuild_archive(data, &mut warnings))?;
+        pz.url = reader.url();
+        pz.requests = reader.requests.clone();
+        pz.bytes = reader.bytes.clone();
+        pz.resolved_url = reader.resolved_url.take();
+        Ok((pz, warnings))
+    }
+
+    /// Create a new [`PartialZip`] from an archive already in memory, without any URL nor request
+    ///
+    /// Its URL is empty, everything else works like for a remote archive.
+    /// # Errors
+    ///
+    /// Will return a [`PartialZipError`] enum depending on what error happened
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, PartialZipError> {
+        let file_size = data.len().to_u64().unwrap_or(u64::MAX);
+        let mut archive = ZipArchive::new(ArchiveReader::Memory(io::Cursor::new(data.into())))?;
+        // the reads aren't tracked, but the headers are in memory: the directory starts at the first one
+        let directory_start = (0..archive.len())
+            .filter_map(|i| {
+                archive
+                    .by_index_raw(i)
+                    .ok()
+                    .map(|file| file.central_header_start())
+            })
+            .min()
+            .unwrap_or(0);
+        Ok(Self {
+            url: String::new(),
+            archive: Mutex::new(archive),
+            file_size,
+            requests: Arc::default(),
+            bytes: Arc::default(),
+            directory_start,
+            headers: OnceLock::new(),
+            resolved_url: None,
+            cancel: Arc::default(),
+        })
+    }
+
+    /// Create a new [`PartialZip`] from a stream which can't seek, like a zip piped to the standard input
+    ///
+    /// The end of central directory is at the end of the archive, so the whole stream is read first,
+    /// copied to a temporary file instead of memory, and removed when the [`PartialZip`] is dropped.
+    /// Its URL is empty, everything else works like for a remote archive.
+    /// # Errors
+    ///
+    /// Will return a [`PartialZipError`] enum depending on what error happened
+    pub fn from_reader_spilled(mut reader: impl Read) -> Result<Self, PartialZipError> {
+        Self::from_assembled(Assembled::Spilled(spill::SpilledStream::new(&mut reader)?))
+    }
+
+    /// Returns the compression methods this build can decompress, the `supported` flag of the files is based on it
+    ///
+    /// They are the ones enabled in the zip crate, plus LZMA decompressed by this crate and `PPMd` with the `ppmd` feature.
+    #[must_use]
+    pub fn supported_methods() -> &'static [PartialZipCompressionMethod] {
+        static METHODS: OnceLock<Vec<PartialZipCompressionMethod>> = OnceLock::new();
+        METHODS.get_or_init(|| {
+            let mut methods: Vec<PartialZipCompressionMethod> = zip::SUPPORTED_COMPRESSION_METHODS
+                .iter()
+                .map(|&method| method.into())
+                .collect();
+            methods.push(PartialZipCompressionMethod::Lzma);
+            if cfg!(feature = "ppmd") {
+                methods.push(PartialZipCompressionMethod::Ppmd);
+            }
+            methods.sort_unstable();
+            methods.dedup();
+            methods
+        })
+    }
+
+    /// Create a new [`PartialZip`] reading an archive the crate assembles from other readers
+    fn from_assembled(reader: Assembled) -> Result<Self, PartialZipError> {
+        Self::from_seeker(reader, |bufreader| {
+            ArchiveReader::Assembled(Box::new(bufreader))
+        })
+    }
+}
+
+impl<R: RangeReader + Send + 'static> PartialZip<R> {
+    /// Create a new [`PartialZip`] reading the archive through `reader` instead of curl
+    ///
+    /// Its URL is empty and the requests aren't counted by [`PartialZip::stats`], everything else works like for a remote archive.
+    /// The clones of the archive reading from other threads share `reader`, so it's locked for every read.
+    /// # Errors
+    ///
+    /// Will return a [`PartialZipError`] enum depending on what error happened
+    pub fn from_range_reader(reader: R) -> Result<Self, PartialZipError> {
+        Self::from_seeker(reader, |bufreader| {
+            ArchiveReader::Custom(Box::new(bufreader))
+        })
+    }
+
+    /// Create a new [`PartialZip`] parsing the archive read through `reader`, wrapped in a buffered [`RangeSeeker`] by `variant`
+    fn from_seeker<S: RangeReader>(
+        reader: S,
+        variant: impl FnOnce(BufReader<RangeSeeker<S>>) -> ArchiveReader<R>,
+    ) -> Result<Self, PartialZipError> {
+        let file_size = reader.len();
+        let lowest_read = Arc::new(AtomicU64::new(u64::MAX));
+        let seeker = RangeSeeker {
+            source: Arc::new(Mutex::new(reader)),
+            len: file_size,
+            pos: 0,
+            lowest_read: lowest_read.clone(),
+        };
+        let archive = ZipArchive::new(variant(BufReader::with_capacity(
+            ARCHIVE_BUFFER_SIZE,
+            seeker,
+        )))?;
+        Ok(Self {
+            url: String::new(),
+            archive: Mutex::new(archive),
+            file_size,
+            requests: Arc::default(),
+            bytes: Arc::default(),
+            directory_start: lowest_read.load(Ordering::Relaxed).min(file_size),
+            headers: OnceLock::new(),
+            resolved_url: None,
+            cancel: Arc::default(),
+        })
+    }
+
+    /// Lock the archive for a read operation
+    ///
+    /// A panic while holding the lock doesn't leave the archive in an inconsistent state, every read seeks first.
+    fn lock(&self) -> MutexGuard<'_, ZipArchive<ArchiveReader<R>>> {
+        self.archive.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns the url for the [`PartialZip`]
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    /// Returns the URL of the archive after following the redirects, see [`PartialReader::resolved_url`]
+    pub fn resolved_url(&self) -> Option<String> {
+        self.resolved_url.clone()
+    }
+
+    /// Returns the zip size for the entire archive of the [`PartialZip`]
+    pub const fn file_size(&self) -> u64 {
+        self.file_size
+    }
+
+    /// Returns how many HTTP requests have been done for the [`PartialZip`] so far
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    /// Returns how many requests have been done and how many bytes have been received for the [`PartialZip`] so far
+    pub fn stats(&self) -> ReadStats {
+        ReadStats {
+            requests: self.requests.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the number of files in the archive, without fetching anything
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns `true` if the archive doesn't contain any file
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if the archive contains a file named `filename`, without fetching anything
+    pub fn contains(&self, filename: &str) -> bool {
+        index_for_name(&self.lock(), filename).is_ok()
+    }
+
+    /// Returns the end of the archive holding the central directory, to open the archive again with [`PartialZip::new_with_directory`]
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened
+    pub fn export_directory(&self) -> Result<Vec<u8>, PartialZipError> {
+        // a clone of the reader with a copy of the cache, which likely has the end of the archive already
+        let mut reader = self.lock().clone().into_inner();
+        reader.seek(io::SeekFrom::Start(self.directory_start))?;
+        let mut directory = Vec::new();
+        reader.read_to_end(&mut directory)?;
+        Ok(directory)
+    }
+
+    /// Returns the comment of the archive, empty if there is none, without fetching anything
+    pub fn comment(&self) -> Vec<u8> {
+        self.lock().comment().to_vec()
+    }
+
+    /// Get a list of the filenames in the archive
+    pub fn list_names(&self) -> Vec<String> {
+        self.lock()
+            .file_names()
+            .map(std::borrow::ToOwned::to_owned)
+            .collect()
+    }
+
+    /// List the files and the directories right under the directory `prefix`, like `readdir`, without fetching anything
+    ///
+    /// The names are relative to `prefix`, sorted, and the directories end with `/`, even when the archive
+    /// only has the files under them. An empty `prefix` is the root of the archive.
+    pub fn list_dir(&self, prefix: &str) -> Vec<String> {
+        let prefix = if prefix.is_empty() || prefix.ends_with('/') {
+            prefix.to_string()
+        } else {
+            format!("{prefix}/")
+        };
+        let children: BTreeSet<String> = self
+            .lock()
+            .file_names()
+            .filter_map(|name| name.strip_prefix(&prefix))
+            .filter(|rest| !rest.is_empty())
+            .map(|rest| {
+                rest.find('/')
+                    .map_or(rest, |separator| &rest[..=separator])
+                    .to_string()
+            })
+            .collect();
+        children.into_iter().collect()
+    }
+
+    /// Get a list of the files in the archive with details (much slower than just listing names because it fetches much more data around with more requests)
+    pub fn list_detailed(&self) -> Vec<PartialZipFileDetailed> {
+        self.list_filtered(|_| true)
+    }
+
+    /// Get a list of the files whose name passes `predicate` with details, like [`PartialZip::list_detailed`]
+    ///
+    /// The local headers of the other files aren't read, which saves requests on large archives.
+    pub fn list_filtered(&self, predicate: impl Fn(&str) -> bool) -> Vec<PartialZipFileDetailed> {
+        let headers = match self.headers() {
+            Ok(headers) => headers,
+            Err(e) => {
+                log::warn!("list: error while reading the central directory - {e}");
+                return Vec::new();
+            }
+        };
+        self.list_matching(predicate, |_, file| Self::detailed(headers, file))
+    }
+
+    /// Get a list of the files in the archive with details like [`PartialZip::list_detailed`], sorted by `key`
+    ///
+    /// Files of the same size are sorted by name, so the order doesn't depend on the order of the central directory.
+    pub fn list_sorted(&self, key: SortKey) -> Vec<PartialZipFileDetailed> {
+        let mut files = self.list_detailed();
+        files.sort_by_cached_key(|file| {
+            let size = match key {
+                SortKey::Name => 0,
+                SortKey::CompressedSize => file.compressed_size,
+                SortKey::UncompressedSize => file.uncompressed_size,
+            };
+            (size, file.name.to_lowercase(), file.name.clone())
+        });
+        files
+    }
+
+    /// Iterate over the details of the files in the archive, like [`PartialZip::list_detailed`] but fetching them one at a time
+    ///
+    /// The local header of a file is only fetched when the iterator gets to it, so stopping early, for example with
+    /// [`Iterator::find`], saves the requests for the following files. The archive is locked only while getting each file,
+    /// and the errors are returned instead of skipping the file.
+    pub fn entries(
+        &self,
+    ) -> impl Iterator<Item = Result<PartialZipFileDetailed, PartialZipError>> + '_ {
+        (0..self.len()).map(move |i| {
+            let headers = self.headers()?;
+            let mut archive = self.lock();
+            let details = Self::detailed(headers, &archive.by_index_raw(i)?);
+            drop(archive);
+            details
+        })
+    }
+
+    /// Collect the details of `file`, with the fields of its header in `headers`
+    fn detailed(
+        headers: &BTreeMap<u64, CentralHeader>,
+        file: &ZipFile,
+    ) -> Result<PartialZipFileDetailed, PartialZipError> {
+        let header = header_at(headers, file.header_start())?;
+        let mut details = PartialZipFileDetailed::from_zip_file(file);
+        details.has_data_descriptor = header.flags & DATA_DESCRIPTOR_FLAG != 0;
+        details.version_needed = header.version_needed;
+        Ok(details)
+    }
+
+    /// Returns the headers of the central directory, which is fetched only the first time
+    ///
+    /// The archive must not be locked, the central directory is read with a clone of its reader.
+    fn headers(&self) -> Result<&BTreeMap<u64, CentralHeader>, PartialZipError> {
+        match self.headers.get() {
+            Some(headers) => Ok(headers),
+            None => self.headers_in(&self.export_directory()?),
+        }
+    }
+
+    /// Returns the headers of the central directory, parsed from `directory` if they aren't already
+    ///
+    /// The archive must not be locked, its offset is needed to key the headers by the absolute offset of their local header.
+    fn headers_in(
+        &self,
+        directory: &[u8],
+    ) -> Result<&BTreeMap<u64, CentralHeader>, PartialZipError> {
+        if let Some(headers) = self.headers.get() {
+            return Ok(headers);
+        }
+        let archive_offset = self.lock().offset();
+        let headers = central_headers(directory)?
+            .into_iter()
+            .map(|header| (header.offset + archive_offset, header))
+            .collect();
+        Ok(self.headers.get_or_init(|| headers))
+    }
+
+    /// Get the details of a single file in the archive, without walking all the others like [`PartialZip::list_detailed`]
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened,
+    /// [`PartialZipError::FileNotFound`] if there is no such file
+    pub fn stat(&self, filename: &str) -> Result<PartialZipFileDetailed, PartialZipError> {
+        let headers = self.headers()?;
+        let mut archive = self.lock();
+        let index = index_for_name(&archive, filename)?;
+        let details = Self::detailed(headers, &archive.by_index_raw(index)?);
+        drop(archive);
+        details
+    }
+
+    /// Read the target path of a symlink, stored as the content of its entry
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened,
+    /// [`PartialZipError::FileNotFound`] if there is no such file, an [`io::ErrorKind::InvalidInput`] error if it isn't a symlink
+    pub fn read_link(&self, filename: &str) -> Result<String, PartialZipError> {
+        if !self.stat(filename)?.is_symlink {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("{filename} is not a symlink"),
+            )
+            .into());
+        }
+        String::from_utf8(self.download(filename)?)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e).into())
+    }
+
+    /// Get the range of the archive holding the compressed content of a file, which starts after its local header
+    ///
+    /// The end is exclusive, so the content can be fetched with a separate range request of `start..=end - 1`.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened,
+    /// [`PartialZipError::FileNotFound`] if there is no such file
+    pub fn data_offset(&self, filename: &str) -> Result<std::ops::Range<u64>, PartialZipError> {
+        let mut archive = self.lock();
+        let index = index_for_name(&archive, filename)?;
+        let file = archive.by_index_raw(index)?;
+        let range = file.data_start()..file.data_start() + file.compressed_size();
+        drop(file);
+        drop(archive);
+        Ok(range)
+    }
+
+    /// Get the ranges of the archive a download of a file would read, without fetching the file
+    ///
+    /// They are computed from the central directory fetched only the first time it is needed,
+    /// assuming the local header has the same extra fields as the central directory, which is almost always the case.
+    /// The zip crate reads the local header to find the file, but never its content.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened,
+    /// [`PartialZipError::FileNotFound`] if there is no such file
+    pub fn download_plan(&self, filename: &str) -> Result<DownloadPlan, PartialZipError> {
+        let headers = self.headers()?;
+        let mut archive = self.lock();
+        let index = index_for_name(&archive, filename)?;
+        let header_start = archive.by_index_raw(index)?.header_start();
+        drop(archive);
+        let header = header_at(headers, header_start)?;
+        let name_len = header.name.len().to_u64().unwrap_or_default();
+        let data_start = header_start + 30 + name_len + header.extra_len;
+        let mut ranges = vec![(header_start, data_start - 1)];
+        if header.compressed_size > 0 {
+            ranges.push((data_start, data_start + header.compressed_size - 1));
+        }
+        Ok(DownloadPlan {
+            total_bytes: ranges.iter().map(|(start, end)| end - start + 1).sum(),
+            ranges,
+        })
+    }
+
+    /// Returns a digest of the names, uncompressed sizes and CRC32 of all the files, to tell cheaply if an archive changed
+    ///
+    /// Only the central directory is read, fetched only the first time it is needed.
+    /// The order of the files doesn't matter, so two archives with the same content have the same fingerprint.
+    /// The names are hashed as they are stored, and all the files with the same name are part of it.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened
+    pub fn directory_fingerprint(&self) -> Result<u64, PartialZipError> {
+        let mut headers: Vec<_> = self.headers()?.values().collect();
+        headers
+            .sort_unstable_by_key(|header| (&header.name, header.uncompressed_size, header.crc32));
+        Ok(headers.into_iter().fold(FNV_OFFSET_BASIS, |hash, header| {
+            // the length keeps the name apart from the following fields
+            let hash = fnv1a(
+                hash,
+                &header.name.len().to_u64().unwrap_or_default().to_le_bytes(),
+            );
+            let hash = fnv1a(hash, &header.name);
+            let hash = fnv1a(hash, &header.uncompressed_size.to_le_bytes());
+            fnv1a(hash, &header.crc32.to_le_bytes())
+        }))
+    }
+
+    /// Returns the totals of the sizes of the files and how many are compressed with each method
+    ///
+    /// Only the central directory is read, fetched only the first time it is needed.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened
+    pub fn summary(&self) -> Result<ArchiveSummary, PartialZipError> {
+        let headers = self.headers()?;
+        let mut methods = BTreeMap::new();
+        for header in headers.values() {
+            *methods
+                .entry(PartialZipCompressionMethod::from_id(
+                    header.compression_method,
+                ))
+                .or_default() += 1;
+        }
+        Ok(ArchiveSummary {
+            files: headers.len(),
+            // the sizes come from the archive, they can be absurd
+            compressed_size: headers.values().fold(0, |total, header| {
+                total.saturating_add(header.compressed_size)
+            }),
+            uncompressed_size: headers.values().fold(0, |total, header| {
+                total.saturating_add(header.uncompressed_size)
+            }),
+            methods,
+        })
+    }
+
+    /// Get a list of the files in the archive with all their attributes, like sizes, CRC32 and permissions (as slow as [`PartialZip::list_detailed`])
+    pub fn list_full(&self) -> Vec<PartialZipFileFull> {
+        self.list_with(PartialZipFileFull::from_zip_file)
+    }
+
+    /// Get a list of the files in the archive with their comment and extra fields (as slow as [`PartialZip::list_detailed`])
+    pub fn list_with_extra(&self) -> Vec<PartialZipFileExtra> {
+        self.list_with(PartialZipFileExtra::from_zip_file)
+    }
+
+    /// Walk all the files in the archive, collecting the result of `f` for each of them
+    fn list_with<T>(&self, f: impl Fn(&ZipFile) -> T) -> Vec<T> {
+        self.list_matching(|_| true, |_, file| Ok(f(file)))
+    }
+
+    /// Walk the files in the archive whose name passes `predicate`, collecting the result of `f` with the index of each of them
+    ///
+    /// The files for which `f` fails are skipped with a warning, like the ones which can't be read.
+    fn list_matching<T>(
+        &self,
+        predicate: impl Fn(&str) -> bool,
+        f: impl Fn(usize, &ZipFile) -> Result<T, PartialZipError>,
+    ) -> Vec<T> {
+        let mut file_list = Vec::new();
+        let mut archive = self.lock();
+        for i in 0..archive.len() {
+            if !archive.name_for_index(i).is_some_and(&predicate) {
+                continue;
+            }
+            // only the metadata is needed, and by_index refuses the files compressed with a method the zip crate
+            // doesn't support, so they would be missing from the listing instead of showing as not supported
+            match archive
+                .by_index_raw(i)
+                .map_err(PartialZipError::from)
+                .and_then(|file| f(i, &file))
+            {
+                Ok(file) => file_list.push(file),
+                Err(e) => {
+                    // We are unable to get a file, let's try to continue,
+                    // and at least return the files we can
+                    log::warn!("list: error while matching file by index: {i} - {e}");
+                }
+            }
+        }
+        drop(archive);
+        file_list
+    }
+
+    /// Download a single file from the archive
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened
+    pub fn download(&self, filename: &str) -> Result<Vec<u8>, PartialZipError> {
+        let mut content: Vec<u8> = Vec::new();
+        self.download_to_write(filename, &mut content)?;
+        Ok(content)
+    }
+
+    /// Download the file at `index` in the archive, its position in [`PartialZip::list_names`]
+    ///
+    /// Useful when the names are duplicated or garbled and can't select the file.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened,
+    /// [`PartialZipError::FileNotFound`] if there are not that many files
+    pub fn download_by_index(&self, index: usize) -> Result<Vec<u8>, PartialZipError> {
+        let mut content: Vec<u8> = Vec::new();
+        let mut archive = self.lock();
+        if index >= archive.len() {
+            return Err(PartialZipError::FileNotFound {
+                name: format!("#{index}"),
+            });
+        }
+        let copied = copy_index(&mut archive, index, &mut content);
+        drop(archive);
+        copied?;
+        Ok(content)
+    }
+
+    /// Download a single file, looking it up with `/` and `\` as the same separator and ignoring the case if there is no other match
+    ///
+    /// Archives created on Windows sometimes store `Dir\File.txt` where `Dir/file.txt` is expected.
+    /// The exact name is preferred, then the one with the same case after normalizing the separators.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened,
+    /// [`PartialZipError::AmbiguousFilename`] if several files match the same way
+    pub fn download_normalized(&self, filename: &str) -> Result<Vec<u8>, PartialZipError> {
+        let name = self.find_normalized(filename)?;
+        self.download(&name)
+    }
+
+    /// Find the name of the file matching `filename` like [`PartialZip::download_normalized`]
+    fn find_normalized(&self, filename: &str) -> Result<String, PartialZipError> {
+        if self.contains(filename) {
+            return Ok(filename.to_string());
+        }
+        let normalize = |name: &str| name.replace('\\', "/");
+        let wanted = normalize(filename);
+        let names = self.list_names();
+        for same in [
+            |a: &str, b: &str| a == b,
+            |a: &str, b: &str| a.to_lowercase() == b.to_lowercase(),
+        ] {
+            let mut candidates: Vec<String> = names
+                .iter()
+                .filter(|name| same(&normalize(name), &wanted))
+                .cloned()
+                .collect();
+            match candidates.len() {
+                0 => {}
+                1 => return Ok(candidates.remove(0)),
+                _ => {
+                    return Err(PartialZipError::AmbiguousFilename {
+                        name: filename.to_string(),
+                        candidates,
+                    })
+                }
+            }
+        }
+        Err(PartialZipError::FileNotFound {
+            name: filename.to_string(),
+        })
+    }
+
+    /// Download a single file encrypted with a password (`ZipCrypto` or `WinZip` AES) from the archive
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened,
+    /// [`PartialZipError::InvalidPassword`] if the password is wrong
+    pub fn download_encrypted(
+        &self,
+        filename: &str,
+        password: &[u8],
+    ) -> Result<Vec<u8>, PartialZipError> {
+        let mut content: Vec<u8> = Vec::new();
+        let mut archive = self.lock();
+        let index = index_for_name(&archive, filename)?;
+        // the encryption header is at the start of the entry data, fetched like the rest of it
+        let mut file = archive
+            .by_index_decrypt(index, password)
+            .map_err(|e| match e {
+                ZipError::InvalidPassword => PartialZipError::InvalidPassword,
+                e => e.into(),
+            })?;
+        io::copy(&mut file, &mut content)?;
+        drop(file);
+        drop(archive);
+        Ok(content)
+    }
+
+    /// Download the content of a single file as stored in the archive, without decompressing it, with its compression method
+    ///
+    /// The content is still encrypted for the encrypted files.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened
+    pub fn download_raw(
+        &self,
+        filename: &str,
+    ) -> Result<(Vec<u8>, PartialZipCompressionMethod), PartialZipError> {
+        let mut content: Vec<u8> = Vec::new();
+        let mut archive = self.lock();
+        let index = index_for_name(&archive, filename)?;
+        let mut file = archive.by_index_raw(index)?;
+        let method = file.compression().into();
+        io::copy(&mut file, &mut content)?;
+        drop(file);
+        drop(archive);
+        Ok((content, method))
+    }
+
+    /// Download only the bytes from `offset` to `offset + len` of a single file from the archive
+    ///
+    /// The range is truncated at the end of the file. The content of stored files is fetched directly,
+    /// the compressed ones are decompressed up to the end of the range, skipping what is before `offset`.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened
+    pub fn download_range(
+        &self,
+        filename: &str,
+        offset: u64,
+        len: u64,
+    ) -> Result<Vec<u8>, PartialZipError> {
+        let index = index_for_name(&self.lock(), filename)?;
+        if let Some((mut reader, data_start, size)) = self.stored_content(index)? {
+            let len = size.saturating_sub(offset).min(len);
+            let mut content = vec![
+                0;
+                len.to_usize()
+                    .ok_or(PartialZipError::ConvError(conv::PosOverflow(len)))?
+            ];
+            reader.read_exact_at(data_start + offset, &mut content)?;
+            return Ok(content);
+        }
+        let mut content: Vec<u8> = Vec::new();
+        let mut archive = self.lock();
+        let mut file = open_index(&mut archive, index)?;
+        io::copy(&mut file.by_ref().take(offset), &mut io::sink())?;
+        file.take(len).read_to_end(&mut content)?;
+        drop(archive);
+        Ok(content)
+    }
+
+    /// Returns a reader of the archive, the offset of the content and the size of the file at `index`, if it's stored without encryption
+    ///
+    /// The offset of the content comes from the open archive, which reads the local header only once.
+    fn stored_content(
+        &self,
+        index: usize,
+    ) -> Result<Option<(ArchiveReader<R>, u64, u64)>, PartialZipError> {
+        let mut archive = self.lock();
+        let (stored, data_start, size) = unbuffered_reads(|| {
+            let file = archive.by_index_raw(index)?;
+            Ok::<_, ZipError>((
+                file.compression() == zip::CompressionMethod::Stored,
+                file.data_start(),
+                file.size(),
+            ))
+        })?;
+        if !stored {
+            return Ok(None);
+        }
+        // rejects the encrypted files, whose raw content isn't the file content
+        unbuffered_reads(|| archive.by_index(index).map(drop))?;
+        // a clone of the reader to read directly at the offsets, with its own copy of the cache
+        let reader = archive.clone().into_inner();
+        drop(archive);
+        Ok(Some((reader, data_start, size)))
+    }
+
+    /// Download only the first `n` bytes of a single file from the archive, for example to sniff its type from its magic bytes
+    ///
+    /// Like with [`PartialZip::download_range`], a stored file is fetched only up to `n` bytes,
+    /// and a compressed one is decompressed from its start but only up to `n` bytes, not until its end.
+    /// It's shorter than `n` bytes if the file is.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened
+    pub fn peek(&self, filename: &str, n: usize) -> Result<Vec<u8>, PartialZipError> {
+        self.download_range(filename, 0, n.to_u64().unwrap_or(u64::MAX))
+    }
+
+    /// Complete the download of a single file to `dest`, keeping the bytes already written there by an interrupted download
+    ///
+    /// Only the stored files can be truly resumed, since their content maps directly to the archive: the missing bytes are fetched and appended.
+    /// A compressed file has to be fetched and decompressed again from the start, only the writes of the bytes already there are skipped.
+    /// The bytes already written are never checked against the archive, a file longer than the one in the archive is written again from scratch.
+    /// The content is written directly to `dest`, so an error leaves it partially written, ready for another attempt.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened,
+    /// [`PartialZipError::SizeMismatch`] if `dest` doesn't have the size of the file once completed
+    pub fn resume_download(
+        &self,
+        filename: &str,
+        dest: &Path,
+    ) -> Result<ResumeStatus, PartialZipError> {
+        let mut archive = self.lock();
+        let index = index_for_name(&archive, filename)?;
+        let (stored, data_start, size) = {
+            let file = archive.by_index_raw(index)?;
+            (
+                file.compression() == zip::CompressionMethod::Stored,
+                file.data_start(),
+                file.size(),
+            )
+        };
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dest)?;
+        let mut written = file.metadata()?.len();
+        if written > size {
+            // it's not a part of this file, start over
+            file.set_len(0)?;
+            written = 0;
+        }
+        let status = if stored {
+            // rejects the encrypted files, whose raw content isn't the file content
+            drop(archive.by_index(index)?);
+            let mut reader = archive.clone().into_inner();
+            drop(archive);
+            reader.seek(io::SeekFrom::Start(data_start + written))?;
+            io::copy(&mut reader.take(size - written), &mut file)?;
+            ResumeStatus::Resumed(written)
+        } else {
+            let mut entry = open_index(&mut archive, index)?;
+            io::copy(&mut entry.by_ref().take(written), &mut io::sink())?;
+            io::copy(&mut entry, &mut file)?;
+            drop(entry);
+            drop(archive);
+            ResumeStatus::Restarted(written)
+        };
+        file.sync_all()?;
+        check_size(size, file.metadata()?.len())?;
+        Ok(status)
+    }
+
+    /// Download a single file from the archive and verify its CRC32 against the one stored in the archive
+    ///
+    /// The checksum is computed while the content is streamed, so the file is not buffered twice.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened,
+    /// [`PartialZipError::CrcMismatch`] if the checksum doesn't match,
+    /// [`PartialZipError::SizeMismatch`] if the content ends before the uncompressed size stored in the archive
+    pub fn download_verified(&self, filename: &str) -> Result<Vec<u8>, PartialZipError> {
+        let mut archive = self.lock();
+        let index = index_for_name(&archive, filename)?;
+        let content = read_verified(&mut archive, index);
+        drop(archive);
+        content
+    }
+
+    /// Download a single file from the archive like [`PartialZip::download_verified`], returning its details too
+    ///
+    /// The details are read under the same lock as the content, so they always describe the returned bytes.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened,
+    /// [`PartialZipError::CrcMismatch`] if the checksum doesn't match
+    pub fn download_with_info(
+        &self,
+        filename: &str,
+    ) -> Result<(Vec<u8>, PartialZipFileDetailed), PartialZipError> {
+        let headers = self.headers()?;
+        let mut archive = self.lock();
+        let index = index_for_name(&archive, filename)?;
+        let details = Self::detailed(headers, &archive.by_index_raw(index)?)?;
+        let content = read_verified(&mut archive, index)?;
+        drop(archive);
+        Ok((content, details))
+    }
+
+    /// Download all the files of the archive to `out_dir`, recreating the directory tree.
+    ///
+    /// All the entry paths are validated before writing anything, existing files are overwritten.
+    /// Returns the paths of the files written.
+    /// The entries are streamed to their files through a buffer of [`DEFAULT_ENTRY_BUFFER`] bytes,
+    /// see [`PartialZip::download_all_with_buffer`].
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened,
+    /// [`PartialZipError::UnsafePath`] if an entry would be written outside of `out_dir`
+    pub fn download_all(&self, out_dir: &Path) -> Result<Vec<PathBuf>, PartialZipError> {
+        self.download_all_with_buffer(out_dir, DEFAULT_ENTRY_BUFFER)
+    }
+
+    /// Download all the files of the archive to `out_dir` like [`PartialZip::download_all`],
+    /// streaming the decompressed content of each entry through a buffer of `max_entry_buffer` bytes.
+    ///
+    /// The memory used doesn't depend on the size of the entries: besides the buffer, there are only the ones
+    /// of the decompressor and of the reader, bounded by its cache size (see [`PartialReader::with_cache_size`]).
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened,
+    /// [`PartialZipError::UnsafePath`] if an entry would be written outside of `out_dir`
+    pub fn download_all_with_buffer(
+        &self,
+        out_dir: &Path,
+        max_entry_buffer: usize,
+    ) -> Result<Vec<PathBuf>, PartialZipError> {
+        self.extract(out_dir, max_entry_buffer, Preserve::Nothing, |_| true)
+    }
+
+    /// Download all the files of the archive to `out_dir` like [`PartialZip::download_all`],
+    /// then restore the attributes of the entries on the files written as asked by `preserve`.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened,
+    /// [`PartialZipError::UnsafePath`] if an entry would be written outside of `out_dir`
+    pub fn download_all_preserving(
+        &self,
+        out_dir: &Path,
+        preserve: Preserve,
+    ) -> Result<Vec<PathBuf>, PartialZipError> {
+        self.extract(out_dir, DEFAULT_ENTRY_BUFFER, preserve, |_| true)
+    }
+
+    /// Set the modification time of the extracted `path` to the one of `filename`, and on Unix its permissions,
+    /// as asked by `preserve`.
+    ///
+    /// The setuid and setgid bits are only kept with [`Preserve::AttributesWithSetid`],
+    /// and a mode which wouldn't let the owner read the file is replaced by the default one.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened
+    pub fn restore_attributes(
+        &self,
+        filename: &str,
+        path: &Path,
+        preserve: Preserve,
+    ) -> Result<(), PartialZipError> {
+        let mut archive = self.lock();
+        let index = index_for_name(&archive, filename)?;
+        apply_attributes(&archive.by_index_raw(index)?, path, preserve)?;
+        drop(archive);
+        Ok(())
+    }
+
+    /// Download a single file under `out_dir` at the relative path of the entry, creating the intermediate directories.
+    ///
+    /// An existing file is overwritten. Returns the path written.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened,
+    /// [`PartialZipError::UnsafePath`] if the entry would be written outside of `out_dir`
+    pub fn download_to_dir(
+        &self,
+        filename: &str,
+        out_dir: &Path,
+    ) -> Result<PathBuf, PartialZipError> {
+        let mut archive = self.lock();
+        let path = download_one(&mut archive, filename, out_dir)?;
+        drop(archive);
+        Ok(path)
+    }
+
+    /// Download all the files matching the glob `pattern` to `out_dir`, recreating the directory tree.
+    ///
+    /// Directories are skipped, existing files are overwritten. Returns the paths of the files written.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened,
+    /// [`PartialZipError::FileNotFound`] if no file matches
+    pub fn download_matching(
+        &self,
+        pattern: &str,
+        out_dir: &Path,
+    ) -> Result<Vec<PathBuf>, PartialZipError> {
+        let pattern = glob::Pattern::new(pattern)?;
+        let written = self.extract(out_dir, DEFAULT_ENTRY_BUFFER, Preserve::Nothing, |name| {
+            !name.ends_with('/') && pattern.matches(name)
+        })?;
+        if written.is_empty() {
+            return Err(PartialZipError::FileNotFound {
+                name: pattern.to_string(),
+            });
+        }
+        Ok(written)
+    }
+
+    /// Extract the entries whose name passes `filter` to `out_dir` through a buffer of `buffer_len` bytes,
+    /// returning the paths of the files written.
+    ///
+    /// All the entry paths are validated before writing anything, the attributes of the files are restored as asked by `preserve`.
+    fn extract(
+        &self,
+        out_dir: &Path,
+        buffer_len: usize,
+        preserve: Preserve,
+        filter: impl Fn(&str) -> bool,
+    ) -> Result<Vec<PathBuf>, PartialZipError> {
+        let mut archive = self.lock();
+        let entries = archive
+            .file_names()
+            .enumerate()
+            .filter(|(_, name)| filter(name))
+            .map(|(i, name)| {
+                utils::entry_path(name)
+                    .map(|path| (i, path))
+                    .ok_or_else(|| PartialZipError::UnsafePath(name.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut written = Vec::new();
+        for (i, path) in entries {
+            let path = out_dir.join(path);
+            if archive.by_index_raw(i)?.is_dir() {
+                fs::create_dir_all(&path)?;
+                continue;
+            }
+            extract_index(&mut archive, i, &path, buffer_len)?;
+            apply_attributes(&archive.by_index_raw(i)?, &path, preserve)?;
+            written.push(path);
+        }
+        drop(archive);
+        Ok(written)
+    }
+
+    /// Repack all the files of the archive into a tar archive written to `writer`, like `download_all` without the disk
+    ///
+    /// The decompressed content of each entry is streamed into the tar archive, so only one entry is read at a time
+    /// and none of them is held in memory. The directories and the symlinks are kept, with the modes and dates of the entries.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened,
+    /// [`PartialZipError::UnsafePath`] if an entry has an absolute path or goes up with `..`
+    #[cfg(feature = "tar")]
+    pub fn repack_to_tar(&self, writer: &mut dyn io::Write) -> Result<(), PartialZipError> {
+        let mut archive = self.lock();
+        let entries = archive
+            .file_names()
+            .enumerate()
+            .map(|(i, name)| {
+                utils::entry_path(name)
+                    .map(|path| (i, path))
+                    .ok_or_else(|| PartialZipError::UnsafePath(name.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut builder = tar::Builder::new(writer);
+        for (i, path) in entries {
+            let (mut header, is_dir) = {
+                let file = archive.by_index_raw(i)?;
+                (
+                    tar_header(&file),
+                    file.is_dir() || unix_file_type(&file) == Some(S_IFDIR),
+                )
+            };
+            if is_dir {
+                header.set_entry_type(tar::EntryType::Directory);
+                header.set_size(0);
+                builder.append_data(&mut header, &path, io::empty())?;
+            } else if header.entry_type() == tar::EntryType::Symlink {
+                let mut target = String::new();
+                open_index(&mut archive, i)?.read_to_string(&mut target)?;
+                builder.append_link(&mut header, &path, target)?;
+            } else {
+                builder.append_data(&mut header, &path, open_index(&mut archive, i)?)?;
+            }
+        }
+        builder.finish()?;
+        drop(archive);
+        Ok(())
+    }
+
+    /// Download the files in `filenames` to `out_dir` with `concurrency` threads, recreating the directory tree.
+    ///
+    /// Every thread has its own connection, while the central directory is parsed only once and shared.
+    /// Existing files are overwritten. The results are in the same order as `filenames`.
+    pub fn download_many(
+        &self,
+        filenames: &[&str],
+        out_dir: &Path,
+        concurrency: usize,
+    ) -> Vec<Result<PathBuf, PartialZipError>> {
+        self.in_parallel(filenames.len(), concurrency, |archive, i| {
+            download_one(archive, filenames[i], out_dir)
+        })
+    }
+
+    /// Decompress every file of the archive with `concurrency` threads, checking its CRC32, and returns the result of each one.
+    ///
+    /// Unlike [`PartialZip::verify_archive`], the content is downloaded, so it catches the corrupted data,
+    /// but nothing is kept: the decompressed bytes are discarded as they are checked.
+    /// Every thread has its own connection, the results are in the order of the archive.
+    pub fn verify_all(&self, concurrency: usize) -> Vec<(String, Result<(), PartialZipError>)> {
+        let names = self.list_names();
+        let results = self.in_parallel(names.len(), concurrency, |archive, i| {
+            copy_verified(archive, i, &mut io::sink())
+        });
+        names.into_iter().zip(results).collect()
+    }
+
+    /// Run `work` on the indexes below `count` with `concurrency` threads, and returns the results in the order of the indexes
+    ///
+    /// Every thread has its own copy of the archive, with a new connection, while the central directory is parsed only once and shared.
+    fn in_parallel<T: Send>(
+        &self,
+        count: usize,
+        concurrency: usize,
+        work: impl Fn(&mut ZipArchive<ArchiveReader<R>>, usize) -> T + Sync,
+    ) -> Vec<T> {
+        let next = AtomicUsize::new(0);
+        let workers = concurrency.clamp(1, count.max(1));
+        let mut results: Vec<(usize, T)> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers)
+                .map(|_| {
+                    // cloning the archive gives it a new connection, sharing the parsed central directory
+                    let mut archive = self.lock().clone();
+                    let (next, work) = (&next, &work);
+                    scope.spawn(move || {
+                        let mut done = Vec::new();
+                        loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            if i >= count {
+                                break;
+                            }
+                            done.push((i, work(&mut archive, i)));
+                        }
+                        done
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|e| std::panic::resume_unwind(e))
+                })
+                .collect()
+        });
+        results.sort_by_key(|(i, _)| *i);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+
+    /// Check the structure of the archive without decompressing anything
+    ///
+    /// The end of central directory record must be well formed and count the files of the central directory,
+    /// and the local header of every file must start with its signature, read with a small range request per file.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError::InvalidArchive`] describing the first inconsistency,
+    /// or another [`PartialZipError`] if the archive can't be read
+    pub fn verify_archive(&self) -> Result<(), PartialZipError> {
+        let directory = self.export_directory()?;
+        let entries = end_of_central_directory_entries(&directory).ok_or_else(|| {
+            PartialZipError::InvalidArchive("no valid end of central directory record".to_string())
+        })?;
+        let archive = self.lock();
+        let mut reader = archive.clone().into_inner();
+        drop(archive);
+        let headers = self.headers_in(&directory)?;
+        // the zip64 archives have the real count in the zip64 record
+        if entries != u16::MAX && usize::from(entries) != headers.len() {
+            return Err(PartialZipError::InvalidArchive(format!(
+                "{} headers in the central directory for {entries} files in the end of central directory record",
+                headers.len()
+            )));
+        }
+        for (header_start, header) in headers {
+            // only the signature, not a whole buffer of the archive reader
+            let mut signature = [0; 4];
+            reader.read_exact_at(*header_start, &mut signature)?;
+            if signature != LOCAL_HEADER_SIGNATURE {
+                return Err(PartialZipError::InvalidArchive(format!(
+                    "no local header for {} at offset {:#x}",
+                    String::from_utf8_lossy(&header.name),
+                    header.offset
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Download several files from the archive, fetching them with as few range requests as possible
+    ///
+    /// The region of each file goes from its local header to the next entry, the regions next to each other
+    /// are fetched with a single request and the files are decompressed from memory. Returns the content by filename.
+    /// The offsets of the files are found by the zip crate, which checks the start of their local header,
+    /// and the ones of the next entries come from the central directory fetched only the first time it is needed.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened,
+    /// [`PartialZipError::FileNotFound`] naming the first of the files missing
+    pub fn download_batch(
+        &self,
+        filenames: &[&str],
+    ) -> Result<HashMap<String, Vec<u8>>, PartialZipError> {
+        let archive = self.lock();
+        let indexes = filenames
+            .iter()
+            .map(|filename| index_for_name(&archive, filename))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut reader = archive.clone().into_inner();
+        drop(archive);
+        if let ArchiveReader::Remote(bufreader) = &mut reader {
+            let directory = self.export_directory()?;
+            let mut starts: Vec<u64> = self.headers_in(&directory)?.keys().copied().collect();
+            starts.push(self.directory_start);
+            starts.sort_unstable();
+            let mut archive = self.lock();
+            // only the start of the local headers is read, not a whole buffer of the content after them
+            let offsets = unbuffered_reads(|| {
+                indexes
+                    .iter()
+                    .map(|i| archive.by_index_raw(*i).map(|file| file.header_start()))
+                    .collect::<Result<Vec<_>, _>>()
+            })?;
+            drop(archive);
+            let mut ranges: Vec<(u64, u64)> = offsets
+                .into_iter()
+                .map(|start| {
+                    let end = starts.iter().find(|s| **s > start).copied();
+                    (start, end.unwrap_or(self.directory_start))
+                })
+                .collect();
+            ranges.sort_unstable();
+            ranges.dedup();
+            let mut merged: Vec<(u64, u64)> = Vec::new();
+            for (start, end) in ranges {
+                match merged.last_mut() {
+                    Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                    _ => merged.push((start, end)),
+                }
+            }
+            let partial = bufreader.get_mut();
+            for (start, end) in merged.into_iter().filter(|(start, end)| end > start) {
+                partial.preload_range(start, end - 1)?;
+            }
+            // the archive parsed again from the directory we already have
+            partial.preload(self.directory_start, directory.into());
+        }
+        let mut archive = ZipArchive::new(reader)?;
+        let mut contents = HashMap::new();
+        for (filename, index) in filenames.iter().zip(indexes) {
+            let expected = archive.by_index_raw(index)?.size();
+            let mut content = Vec::new();
+            let actual = io::copy(&mut open_index(&mut archive, index)?, &mut content)?;
+            check_size(expected, actual)?;
+            contents.insert((*filename).to_string(), content);
+        }
+        Ok(contents)
+    }
+
+    /// Open a single file from the archive as a streaming reader over its decompressed content
+    ///
+    /// The file is decompressed ahead of the reads in a thread with its own connection to the archive,
+    /// buffering only a few chunks so the memory usage stays constant whatever the size of the file.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened, the errors while decompressing are returned by the reads
+    pub fn open_entry(&self, filename: &str) -> Result<impl io::Read + '_, PartialZipError> {
+        let index = index_for_name(&self.lock(), filename)?;
+        // the clone has its own connection, sharing the parsed central directory
+        let mut archive = self.lock().clone();
+        let (sender, receiver) = mpsc::sync_channel(ENTRY_CHUNKS_AHEAD);
+        thread::spawn(move || {
+            let result = open_index(&mut archive, index)
+                .map_err(io::Error::other)
+                .and_then(|mut file| {
+                    let mut chunk = vec![0; ENTRY_CHUNK_SIZE];
+                    loop {
+                        let n = match file.read(&mut chunk) {
+                            Ok(0) => return Ok(()),
+                            Ok(n) => n,
+                            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                            Err(e) => return Err(e),
+                        };
+                        if sender.send(Ok(chunk[..n].to_vec())).is_err() {
+                            // the reader has been dropped, stop decompressing
+                            return Ok(());
+                        }
+                    }
+                });
+            if let Err(e) = result {
+                sender.send(Err(e)).ok();
+            }
+        });
+        Ok(EntryReader {
+            receiver,
+            chunk: io::Cursor::default(),
+        })
+    }
+
+    /// Download a single file from the archive and writes it to a [`std::io::Write`]
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened,
+    /// [`PartialZipError::SizeMismatch`] if the content ends before the uncompressed size stored in the archive
+    pub fn download_to_write(
+        &self,
+        filename: &str,
+        writer: &mut dyn std::io::Write,
+    ) -> Result<(), PartialZipError> {
+        let mut archive = self.lock();
+        let index = index_for_name(&archive, filename)?;
+        let copied = copy_index(&mut archive, index, writer);
+        drop(archive);
+        copied
+    }
+
+    /// Hash the content of a file of the archive with `algorithm`, returning the digest in lowercase hexadecimal
+    ///
+    /// The content is streamed through the hasher, like [`PartialZip::download_to_write`], it's never kept whole in memory.
+    /// # Errors
+    ///
+    /// Will return a [`PartialZipError`] enum depending on what error happened
+    #[cfg(feature = "checksums")]
+    pub fn hash_entry(
+        &self,
+        filename: &str,
+        algorithm: HashAlgorithm,
+    ) -> Result<String, PartialZipError> {
+        match algorithm {
+            HashAlgorithm::Md5 => self.hash_with::<md5::Md5>(filename),
+            HashAlgorithm::Sha1 => self.hash_with::<sha1::Sha1>(filename),
+            HashAlgorithm::Sha256 => self.hash_with::<sha2::Sha256>(filename),
+        }
+    }
+
+    /// Hash the content of a file of the archive with the hasher `D`, see [`PartialZip::hash_entry`]
+    #[cfg(feature = "checksums")]
+    fn hash_with<D: Digest + io::Write>(&self, filename: &str) -> Result<String, PartialZipError> {
+        use std::fmt::Write as _;
+
+        let mut hasher = D::new();
+        self.download_to_write(filename, &mut hasher)?;
+        let digest = hasher.finalize();
+        Ok(digest
+            .iter()
+            .fold(String::with_capacity(digest.len() * 2), |mut hex, byte| {
+                // writing to a String can't fail
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            }))
+    }
+
+    /// Download a single file from the archive to a [`std::io::Write`], until `cancel` is set
+    ///
+    /// The token is checked between the writes and by curl during the requests, so even a stalled request
+    /// is aborted as soon as it's set, from another thread.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened,
+    /// [`PartialZipError::Cancelled`] if the download was cancelled
+    pub fn download_to_write_cancellable(
+        &self,
+        filename: &str,
+        writer: &mut dyn std::io::Write,
+        cancel: &Arc<AtomicBool>,
+    ) -> Result<(), PartialZipError> {
+        let mut archive = self.lock();
+        let index = index_for_name(&archive, filename)?;
+        let mut writer = CancellableWriter {
+            inner: writer,
+            cancel,
+        };
+        let token = || self.cancel.lock().unwrap_or_else(PoisonError::into_inner);
+        *token() = Some(cancel.clone());
+        let copied = copy_index(&mut archive, index, &mut writer);
+        *token() = None;
+        drop(archive);
+        // whatever error the cancellation caused on the way
+        if copied.is_err() && cancel.load(Ordering::Relaxed) {
+            return Err(PartialZipError::Cancelled);
+        }
+        copied
+    }
+
+    /// Download a single file from the archive to `dest`
+    ///
+    /// The content is written to a temporary file next to `dest`, renamed to `dest` only once the download succeeded,
+    /// so an interrupted download never leaves a truncated file at `dest`.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened
+    pub fn download_to_file(&self, filename: &str, dest: &Path) -> Result<(), PartialZipError> {
+        write_atomically(dest, true, |file| self.download_to_write(filename, file))
+    }
+
+    /// Download a single file from the archive to `dest` showing a progress bar, see [`PartialZip::download_to_file`]
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened
+    #[cfg(feature = "progressbar")]
+    pub fn download_to_file_with_progressbar(
+        &self,
+        filename: &str,
+        dest: &Path,
+    ) -> Result<(), PartialZipError> {
+        write_atomically(dest, true, |file| {
+            self.download_to_write_with_progressbar(filename, file)
+        })
+    }
+
+    /// Download a single file from the archive showing a progress bar
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened
+    #[cfg(feature = "progressbar")]
+    pub fn download_with_progressbar(&self, filename: &str) -> Result<Vec<u8>, PartialZipError> {
+        let mut content: Vec<u8> = Vec::new();
+        self.download_to_write_with_progressbar(filename, &mut content)?;
+        Ok(content)
+    }
+
+    /// Download a single file from the archive showing a progress bar to a [`std::io::Write`]
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened
+    #[cfg(feature = "progressbar")]
+    pub fn download_to_write_with_progressbar(
+        &self,
+        filename: &str,
+        writer: &mut dyn std::io::Write,
+    ) -> Result<(), PartialZipError> {
+        use indicatif::{HumanBytes, HumanDuration, ProgressBar, ProgressStyle};
+
+        let pb = ProgressBar::new(0).with_style(
+            ProgressStyle::with_template("{wide_bar} {bytes}/{total_bytes} {msg}")
+                .unwrap_or_else(|_| ProgressStyle::default_bar()),
+        );
+        self.download_to_write_with_progress(filename, writer, &mut |progress| {
+            pb.set_length(progress.total);
+            pb.set_position(progress.done);
+            let eta = progress
+                .eta
+                .map_or_else(String::new, |eta| format!(", ETA {}", HumanDuration(eta)));
+            pb.set_message(format!("{}/s{eta}", HumanBytes(progress.bytes_per_sec)));
+        })?;
+        pb.finish();
+        Ok(())
+    }
+
+    /// Download a single file from the archive to a [`std::io::Write`], calling `callback` with the [`Progress`] as the download proceeds
+    ///
+    /// The speed and the estimated time left are computed over the last seconds of the download.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened,
+    /// [`PartialZipError::SizeMismatch`] if the content ends before the uncompressed size stored in the archive
+    pub fn download_to_write_with_progress(
+        &self,
+        filename: &str,
+        writer: &mut dyn std::io::Write,
+        callback: &mut dyn FnMut(Progress),
+    ) -> Result<(), PartialZipError> {
+        let mut archive = self.lock();
+        let index = index_for_name(&archive, filename)?;
+        let total = archive.by_index_raw(index)?.size();
+        let mut writer = ProgressWriter {
+            inner: writer,
+            done: 0,
+            total,
+            samples: VecDeque::from([(Instant::now(), 0)]),
+            callback,
+        };
+        let actual = io::copy(&mut open_index(&mut archive, index)?, &mut writer)?;
+        drop(archive);
+        check_size(total, actual)
+    }
+}
+
+/// Size of the chunks of an entry sent by the thread decompressing it
+const ENTRY_CHUNK_SIZE: usize = 0x0001_0000;
+/// Number of decompressed chunks buffered ahead of the reads of an entry
+const ENTRY_CHUNKS_AHEAD: usize = 4;
+
+/// Reader over the decompressed content of an entry, fed by the thread decompressing it
+struct EntryReader {
+    receiver: mpsc::Receiver<io::Result<Vec<u8>>>,
+    /// The chunk being read
+    chunk: io::Cursor<Vec<u8>>,
+}
+
+impl io::Read for EntryReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = self.chunk.read(buf)?;
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+            match self.receiver.recv() {
+                Ok(chunk) => self.chunk = io::Cursor::new(chunk?),
+                // the thread is done, and all the chunks have been read
+                Err(_) => return Ok(0),
+            }
+        }
+    }
+}
+
+/// Progress of a download, reported by [`PartialZip::download_to_write_with_progress`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// Bytes written so far
+    pub done: u64,
+    /// Size of the file
+    pub total: u64,
+    /// Speed over the last seconds, in bytes per second
+    pub bytes_per_sec: u64,
+    /// Estimated time left at this speed, `None` while nothing is flowing
+    pub eta: Option<Duration>,
+}
+
+/// Duration over which the speed of a download is computed
+const PROGRESS_WINDOW: Duration = Duration::from_secs(5);
+/// Minimum interval between the timing samples of a download
+const PROGRESS_SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Writer reporting the progress of the data flowing through it
+struct ProgressWriter<'a, W: ?Sized> {
+    inner: &'a mut W,
+    /// Bytes written so far
+    done: u64,
+    /// Size of the whole content
+    total: u64,
+    /// When the bytes written were `done`, the oldest one starts the window the speed is computed over
+    samples: VecDeque<(Instant, u64)>,
+    callback: &'a mut dyn FnMut(Progress),
+}
+
+impl<W: ?Sized> ProgressWriter<'_, W> {
+    /// Record the bytes written now and compute the progress
+    fn progress(&mut self) -> Progress {
+        let now = Instant::now();
+        if self.samples.back().map_or(true, |(at, _)| {
+            now.duration_since(*at) >= PROGRESS_SAMPLE_INTERVAL
+        }) {
+            self.samples.push_back((now, self.done));
+        }
+        // keep the last sample older than the window as its start
+        while self
+            .samples
+            .get(1)
+            .is_some_and(|(at, _)| now.duration_since(*at) >= PROGRESS_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+        let (start, start_done) = self.samples.front().copied().unwrap_or((now, 0));
+        let nanos = now.duration_since(start).as_nanos();
+        let bytes_per_sec = (u128::from(self.done.saturating_sub(start_done)) * 1_000_000_000)
+            .checked_div(nanos)
+            .map_or(0, |speed| speed.to_u64().unwrap_or(u64::MAX));
+        let left = self.total.saturating_sub(self.done);
+        // nothing left is done now, even without a speed
+        let eta = if left == 0 {
+            Some(Duration::ZERO)
+        } else {
+            (u128::from(left) * 1_000_000_000)
+                .checked_div(u128::from(bytes_per_sec))
+                .and_then(|nanos| nanos.to_u64())
+                .map(Duration::from_nanos)
+        };
+        Progress {
+            done: self.done,
+            total: self.total,
+            bytes_per_sec,
+            eta,
+        }
+    }
+}
+
+impl<W: io::Write + ?Sized> io::Write for ProgressWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.done += n.to_u64().unwrap_or_default();
+        let progress = self.progress();
+        (self.callback)(progress);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writer failing with [`PartialZipError::Cancelled`] once its token is set
+struct CancellableWriter<'a, W: ?Sized> {
+    inner: &'a mut W,
+    cancel: &'a AtomicBool,
+}
+
+impl<W: io::Write + ?Sized> io::Write for CancellableWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.cancel.load(Ordering::Relaxed) {
+            return Err(io::Error::other(PartialZipError::Cancelled));
+        }
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writer computing the CRC32 of the data flowing through it
+struct Crc32Writer<W> {
+    inner: W,
+    hasher: crc32fast::Hasher,
+    /// Number of bytes written so far
+    written: u64,
+}
+
+impl<W: io::Write> Crc32Writer<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: crc32fast::Hasher::new(),
+            written: 0,
+        }
+    }
+
+    /// Returns the CRC32 and the number of bytes written
+    fn finalize(self) -> (u32, u64) {
+        (self.hasher.finalize(), self.written)
+    }
+}
+
+impl<W: io::Write> io::Write for Crc32Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Capacity of the buffer in front of the [`PartialReader`] of a [`PartialZip`]
+const ARCHIVE_BUFFER_SIZE: usize = 0x0010_0000;
+
+/// Reader used by the archive of a [`PartialZip`].
+///
+/// Cloning a remote one opens a new connection to the same archive, with a copy of its block cache,
+/// so the whole archive can be cloned to read it from another thread.
+#[derive(Debug)]
+enum ArchiveReader<R> {
+    /// Buffered [`PartialReader`] fetching the archive
+    Remote(Box<BufReader<PartialReader>>),
+    /// Archive already in memory, shared by the clones
+    Memory(io::Cursor<Arc<[u8]>>),
+    /// Buffered archive assembled by the crate, shared by the clones
+    Assembled(Box<BufReader<RangeSeeker<Assembled>>>),
+    /// Buffered [`RangeReader`] supplied by the caller, shared by the clones
+    Custom(Box<BufReader<RangeSeeker<R>>>),
+}
+
+/// Archives the crate reads from other sources than a single [`PartialReader`], opened by the constructors of [`PartialZip`]
+enum Assembled {
+    /// Volumes of a split archive
+    Split(split::SplitVolumes),
+    /// Copy of a stream which can't seek
+    Spilled(spill::SpilledStream),
+    /// Archive of an `s3://` URL
+    #[cfg(feature = "s3")]
+    S3(crate::s3::S3RangeReader),
+}
+
+impl RangeReader for Assembled {
+    fn read_range(&mut self, start: u64, end: u64) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Split(volumes) => volumes.read_range(start, end),
+            Self::Spilled(spilled) => spilled.read_range(start, end),
+            #[cfg(feature = "s3")]
+            Self::S3(s3) => s3.read_range(start, end),
+        }
+    }
+
+    fn len(&self) -> u64 {
+        match self {
+            Self::Split(volumes) => volumes.len(),
+            Self::Spilled(spilled) => spilled.len(),
+            #[cfg(feature = "s3")]
+            Self::S3(s3) => s3.len(),
+        }
+    }
+}
+
+/// Clone a buffered [`RangeSeeker`] at the position of what it returned, with an empty buffer
+fn clone_seeker<S: RangeReader>(
+    bufreader: &BufReader<RangeSeeker<S>>,
+) -> BufReader<RangeSeeker<S>> {
+    let mut reader = bufreader.get_ref().clone();
+    reader.pos = reader.pos.saturating_sub(bufreader.buffer().len() as u64);
+    BufReader::with_capacity(ARCHIVE_BUFFER_SIZE, reader)
+}
+
+impl<R: RangeReader> Clone for ArchiveReader<R> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Remote(bufreader) => {
+                let reader = bufreader.get_ref();
+                // the inner reader is ahead of what we returned by the buffered content
+                let pos = reader
+                    .position()
+                    .unwrap_or_default()
+                    .saturating_sub(bufreader.buffer().len() as u64);
+                Self::Remote(Box::new(BufReader::with_capacity(
+                    ARCHIVE_BUFFER_SIZE,
+                    reader.clone_at(pos),
+                )))
+            }
+            Self::Memory(cursor) => Self::Memory(cursor.clone()),
+            Self::Assembled(bufreader) => Self::Assembled(Box::new(clone_seeker(bufreader))),
+            Self::Custom(bufreader) => Self::Custom(Box::new(clone_seeker(bufreader))),
+        }
+    }
+}
+
+thread_local! {
+    /// Set while the archive must be read without filling its buffer, see [`unbuffered_reads`]
+    static UNBUFFERED_READS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `read` with the reads of the archives of this thread fetching only the requested bytes, not a whole buffer
+///
+/// The zip crate reads the local headers through the buffer, it would fetch the content after them too
+/// when only their offsets are needed.
+fn unbuffered_reads<T>(read: impl FnOnce() -> T) -> T {
+    UNBUFFERED_READS.set(true);
+    let result = read();
+    UNBUFFERED_READS.set(false);
+    result
+}
+
+impl<R: RangeReader> ArchiveReader<R> {
+    /// Read exactly `buf.len()` bytes at `offset`, bypassing the buffer so only them are fetched
+    ///
+    /// The reader must not be used through the buffer afterwards, its position is lost.
+    fn read_exact_at(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+        match self {
+            Self::Remote(bufreader) => {
+                let reader = bufreader.get_mut();
+                reader.seek(io::SeekFrom::Start(offset))?;
+                reader.read_exact(buf)
+            }
+            Self::Memory(cursor) => {
+                cursor.seek(io::SeekFrom::Start(offset))?;
+                cursor.read_exact(buf)
+            }
+            Self::Assembled(bufreader) => {
+                let reader = bufreader.get_mut();
+                reader.seek(io::SeekFrom::Start(offset))?;
+                reader.read_exact(buf)
+            }
+            Self::Custom(bufreader) => {
+                let reader = bufreader.get_mut();
+                reader.seek(io::SeekFrom::Start(offset))?;
+                reader.read_exact(buf)
+            }
+        }
+    }
+}
+
+impl<R: RangeReader> io::Read for ArchiveReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            // the inner reader is at the position when nothing is buffered
+            Self::Remote(bufreader) if bufreader.buffer().is_empty() && UNBUFFERED_READS.get() => {
+                bufreader.get_mut().read(buf)
+            }
+            Self::Assembled(bufreader)
+                if bufreader.buffer().is_empty() && UNBUFFERED_READS.get() =>
+            {
+                bufreader.get_mut().read(buf)
+            }
+            Self::Custom(bufreader) if bufreader.buffer().is_empty() && UNBUFFERED_READS.get() => {
+                bufreader.get_mut().read(buf)
+            }
+            Self::Remote(bufreader) => bufreader.read(buf),
+            Self::Memory(cursor) => cursor.read(buf),
+            Self::Assembled(bufreader) => bufreader.read(buf),
+            Self::Custom(bufreader) => bufreader.read(buf),
+        }
+    }
+}
+
+impl<R: RangeReader> io::Seek for ArchiveReader<R> {
+    fn seek(&mut self, style: io::SeekFrom) -> io::Result<u64> {
+        match self {
+            Self::Remote(bufreader) => bufreader.seek(style),
+            Self::Memory(cursor) => cursor.seek(style),
+            Self::Assembled(bufreader) => bufreader.seek(style),
+            Self::Custom(bufreader) => bufreader.seek(style),
+        }
+    }
+}
+
+/// Source of the bytes of an archive, to read it with something else than curl
+///
+/// [`PartialReader`] is the implementation used by default, another one can be used with [`PartialZip::from_range_reader`],
+/// for example to fetch the archive with another HTTP client or an object storage SDK, or to test without a server.
+pub trait RangeReader {
+    /// Read the bytes from `start` to `end` (included)
+    ///
+    /// # Errors
+    /// Will return an [`io::Error`] if the bytes can't be read
+    fn read_range(&mut self, start: u64, end: u64) -> io::Result<Vec<u8>>;
+
+    /// Returns the size of the archive
+    fn len(&self) -> u64;
+
+    /// Returns `true` if the archive is empty
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Reader of an archive through a [`RangeReader`], shared by the clones each with its own position
+struct RangeSeeker<S> {
+    source: Arc<Mutex<S>>,
+    len: u64,
+    pos: u64,
+    /// Lowest offset read so far, shared with the clones of the reader
+    lowest_read: Arc<AtomicU64>,
+}
+
+impl<S> Clone for RangeSeeker<S> {
+    fn clone(&self) -> Self {
+        Self {
+            source: self.source.clone(),
+            len: self.len,
+            pos: self.pos,
+            lowest_read: self.lowest_read.clone(),
+        }
+    }
+}
+
+impl<S> std::fmt::Debug for RangeSeeker<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RangeSeeker")
+            .field("len", &self.len)
+            .field("pos", &self.pos)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: RangeReader> io::Read for RangeSeeker<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.len || buf.is_empty() {
+            return Ok(0);
+        }
+        self.lowest_read.fetch_min(self.pos, Ordering::Relaxed);
+        let end = std::cmp::min(
+            self.pos
+                .saturating_add(buf.len().to_u64().unwrap_or(u64::MAX)),
+            self.len,
+        ) - 1;
+        let content = self
+            .source
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .read_range(self.pos, end)?;
+        if content.is_empty() {
+            return Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                format!("empty range {}-{end}", self.pos),
+            ));
+        }
+        let n = io::Read::read(&mut content.as_slice(), buf)?;
+        self.pos += n.to_u64().unwrap_or_default();
+        Ok(n)
+    }
+}
+
+impl<S> io::Seek for RangeSeeker<S> {
+    fn seek(&mut self, style: io::SeekFrom) -> io::Result<u64> {
+        let (base_pos, offset) = match style {
+            io::SeekFrom::Start(n) => {
+                self.pos = n;
+                return Ok(n);
+            }
+            io::SeekFrom::End(n) => (self.len, n),
+            io::SeekFrom::Current(n) => (self.pos, n),
+        };
+        self.pos = base_pos.checked_add_signed(offset).ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        Ok(self.pos)
+    }
+}
+
+impl RangeReader for PartialReader {
+    fn read_range(&mut self, start: u64, end: u64) -> io::Result<Vec<u8>> {
+        self.probe().map_err(io::Error::other)?;
+        if let Some(file) = &mut self.local {
+            let mut content = vec![0; (end + 1 - start).to_usize().unwrap_or_default()];
+            file.seek(io::SeekFrom::Start(start))?;
+            file.read_exact(&mut content)?;
+            return Ok(content);
+        }
+        self.fetch(start, end)
+    }
+
+    /// Returns the size of the archive, known once the archive is probed
+    fn len(&self) -> u64 {
+        self.file_size
+    }
+}
+
+/// Reader for the partialzip doing only the partial read instead of downloading everything
+///
+/// The archive is probed (to know its size and check for the range support) lazily,
+/// on the first read or seek, so options set with the `with_*` methods apply to every request.
+#[derive(Debug)]
+pub struct PartialReader {
+    /// URL at which we read the file
+    url: String,
+    file_size: u64,
+    easy: Easy,
+    pos: u64,
+    /// Options applied to the curl handle
+    options: ReaderOptions,
+    /// Require the range support when probing
+    check_range: bool,
+    /// Did we already probe the archive
+    probed: bool,
+    /// Local file read directly for `file://` URLs, skipping curl
+    local: Option<File>,
+    /// Cache of the blocks already fetched
+    cache: BlockCache,
+    /// Size of the end of the archive fetched at once when a read lands there
+    tail_prefetch: u64,
+    /// End of the last read, a read starting there continues a sequential one
+    read_end: u64,
+    /// Number of blocks of the last fetch, the next one fetches twice as many if the reads are sequential
+    sequential_blocks: u64,
+    /// Number of HTTP requests done, shared with the clones of the reader
+    requests: Arc<AtomicU64>,
+    /// Number of bytes received, shared with the clones of the reader
+    bytes: Arc<AtomicU64>,
+    /// `Content-Type` announced by the server when probing
+    content_type: Option<String>,
+    /// URL of the archive after following the redirects when probing
+    resolved_url: Option<String>,
+    /// `ETag` or `Last-Modified` of the archive when probing, sent as `If-Range` with the range requests
+    validator: Option<String>,
+    /// Size given with [`PartialReader::with_file_size`] instead of probed, until it's checked against the first range response
+    given_size: Option<u64>,
+    /// Maximum number of retries of a failed range request
+    retries: u32,
+    /// Delay before the first retry, doubling at every attempt
+    retry_delay: Duration,
+    /// Cookies set by the server while probing, in the Netscape format, loaded in the handles of the clones
+    cookies: Vec<String>,
+    /// Is the cookie engine of the curl handle enabled
+    cookie_engine: bool,
+    /// Regions of the archive already fetched or supplied by the caller with their offset, served without fetching them
+    preloaded: Vec<(u64, Arc<[u8]>)>,
+    /// Lowest offset read so far, shared with the clones of the reader
+    lowest_read: Arc<AtomicU64>,
+    /// Token aborting the requests when set, shared with the [`PartialZip`] of the archive
+    cancel: Arc<Mutex<Option<Arc<AtomicBool>>>>,
+    /// Client lending the curl handle, which gets it back when the reader is dropped
+    client: Option<PartialZipClient>,
+}
+
+impl Drop for PartialReader {
+    fn drop(&mut self) {
+        if let Some(client) = &self.client {
+            client.give_back(std::mem::replace(&mut self.easy, Easy::new()));
+        }
+    }
+}
+
+/// Pool of curl handles shared by the readers of many archives, keeping their connections open between the archives
+///
+/// A handle keeps its connections alive once its reader is dropped, so the next archive opened with
+/// [`PartialZip::from_client`] on the same host skips the TCP and TLS handshakes.
+/// Cloning the client shares the same pool.
+#[derive(Debug, Clone, Default)]
+pub struct PartialZipClient {
+    /// Idle handles, the last one given back is lent first as it's the most likely to have a live connection
+    handles: Arc<Mutex<Vec<Easy>>>,
+    /// Number of new connections opened by the handles of the pool
+    connections: Arc<AtomicU64>,
+}
+
+impl PartialZipClient {
+    /// Create a new empty [`PartialZipClient`], the handles are created when needed
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a [`PartialReader`] of the archive at `url` using a handle of the pool
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] enum depending on what happened
+    pub fn reader(&self, url: &dyn ToString) -> Result<PartialReader, PartialZipError> {
+        Ok(PartialReader::new(url)?.with_client(self))
+    }
+
+    /// Returns how many connections the handles of the pool opened, the reused ones aren't counted
+    #[must_use]
+    pub fn connections(&self) -> u64 {
+        self.connections.load(Ordering::Relaxed)
+    }
+
+    /// Lend an idle handle, or a new one if they are all in use
+    fn take(&self) -> Easy {
+        self.handles
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop()
+            .unwrap_or_else(Easy::new)
+    }
+
+    /// Get back a handle, its options are reset but its connections are kept
+    fn give_back(&self, mut easy: Easy) {
+        // the cookies of an archive must not leak to the next one
+        let _ = easy.cookie_list("ALL");
+        easy.reset();
+        self.handles
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(easy);
+    }
+}
+
+/// Credentials used to authenticate against the server
+#[derive(Clone)]
+struct Credentials {
+    username: String,
+    password: String,
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // never leak the password in logs
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Statistics of the requests done to the server, local files don't need any
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReadStats {
+    /// Number of requests done, including the ones probing the archive
+    pub requests: u64,
+    /// Number of bytes of content received
+    pub bytes: u64,
+}
+
+/// Client certificate presented to the servers requiring a mutual TLS authentication
+#[derive(Clone)]
+struct ClientCert {
+    /// Path of the certificate
+    cert: PathBuf,
+    /// Path of the private key
+    key: PathBuf,
+    /// Password of the private key
+    key_password: Option<String>,
+}
+
+impl std::fmt::Debug for ClientCert {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // never leak the password in logs
+        f.debug_struct("ClientCert")
+            .field("cert", &self.cert)
+            .field("key", &self.key)
+            .finish_non_exhaustive()
+    }
+}
+
+/// How the data connection of a FTP transfer is established
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FtpMode {
+    /// Passive mode, trying `EPSV` before `PASV`
+    #[default]
+    Passive,
+    /// Passive mode with `PASV` only, for the servers which don't support `EPSV`
+    PassiveNoEpsv,
+    /// Active mode, the server connects back to us
+    Active,
+}
+
+/// Version of the HTTP protocol used for the requests
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HttpVersion {
+    /// Let curl choose, HTTP/2 over TLS when both sides support it and HTTP/1.1 otherwise
+    #[default]
+    Auto,
+    /// HTTP/1.1 only
+    Http11,
+    /// HTTP/2 when the server supports it, negotiated over TLS or with an upgrade from HTTP/1.1 for plain HTTP
+    Http2,
+    /// HTTP/2 directly, for the plain HTTP servers known to support it
+    Http2PriorKnowledge,
+}
+
+/// Redirects followed by a [`PartialReader`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RedirectPolicy {
+    /// Follow all the redirects, like `curl -L`
+    #[default]
+    All,
+    /// Follow only the redirects to the same scheme, host and port as the URL of the archive,
+    /// so the credentials and the custom headers are never sent to another server
+    SameOrigin,
+}
+
+/// Maximum number of redirects followed by the reader itself, when [`PartialReader::with_max_redirections`] isn't set
+const DEFAULT_MAX_REDIRECTIONS: u32 = 30;
+
+/// Options applied to the curl handle of a [`PartialReader`]
+#[derive(Debug, Clone, Default)]
+struct ReaderOptions {
+    /// Credentials for the authentication
+    credentials: Option<Credentials>,
+    /// Keep sending the credentials after a redirect to another host
+    unrestricted_auth: bool,
+    /// Custom headers sent with every request
+    headers: Vec<(String, String)>,
+    /// Maximum time to establish the connection
+    connect_timeout: Option<Duration>,
+    /// Maximum time for a whole request
+    timeout: Option<Duration>,
+    /// Proxy the requests go through
+    proxy: Option<String>,
+    /// Credentials for the proxy
+    proxy_credentials: Option<Credentials>,
+    /// Mode of the FTP data connections
+    ftp_mode: FtpMode,
+    /// Maximum download speed in bytes per second
+    max_recv_speed: Option<u64>,
+    /// Minimum speed in bytes per second, and for how long it can be missed before the request is aborted
+    low_speed: Option<(u32, Duration)>,
+    /// User-Agent sent instead of the curl default one
+    user_agent: Option<String>,
+    /// Cookies sent with every request, as `name=value; name2=value2`
+    cookies: Option<String>,
+    /// Client certificate for the mutual TLS authentication
+    client_cert: Option<ClientCert>,
+    /// Skip the verification of the TLS certificate of the server
+    accept_invalid_certs: bool,
+    /// Unix domain socket the connections go through instead of TCP
+    unix_socket: Option<PathBuf>,
+    /// Maximum number of redirects followed
+    max_redirections: Option<u32>,
+    /// Refuse the redirects from HTTPS to plain HTTP
+    refuse_downgrades: bool,
+    /// Addresses of the hosts overriding the DNS, as `host:port:address`
+    resolve: Vec<String>,
+    /// Version of the HTTP protocol
+    http_version: HttpVersion,
+    /// Redirects followed
+    redirect_policy: RedirectPolicy,
+    /// Builds the value of the `Range` header of the HTTP requests instead of curl
+    range_formatter: Option<fn(u64, u64) -> String>,
+}
+
+impl ReaderOptions {
+    /// Apply the options to a curl handle
+    fn apply(&self, easy: &mut Easy) -> Result<(), PartialZipError> {
+        // the ranges are offsets in the encoded content, the server must not compress it
+        easy.accept_encoding("identity")?;
+        if let Some(credentials) = &self.credentials {
+            easy.username(&credentials.username)?;
+            easy.password(&credentials.password)?;
+            // curl sends them only to the host of the URL otherwise
+            easy.unrestricted_auth(self.unrestricted_auth)?;
+        }
+        if !self.headers.is_empty() {
+            let mut list = List::new();
+            for (name, value) in &self.headers {
+                list.append(&format!("{name}: {value}"))?;
+            }
+            easy.http_headers(list)?;
+        }
+        if let Some(connect_timeout) = self.connect_timeout {
+            easy.connect_timeout(connect_timeout)?;
+        }
+        if let Some(timeout) = self.timeout {
+            easy.timeout(timeout)?;
+        }
+        if let Some(proxy) = &self.proxy {
+            // the scheme of the proxy URL selects its type, for example `socks5://`
+            easy.proxy(proxy)?;
+        }
+        if let Some(credentials) = &self.proxy_credentials {
+            easy.proxy_username(&credentials.username)?;
+            easy.proxy_password(&credentials.password)?;
+        }
+        if let Some(speed) = self.max_recv_speed {
+            easy.max_recv_speed(speed)?;
+        }
+        if let Some((limit, time)) = self.low_speed {
+            easy.low_speed_limit(limit)?;
+            easy.low_speed_time(time)?;
+        }
+        if let Some(user_agent) = &self.user_agent {
+            easy.useragent(user_agent)?;
+        }
+        if let Some(cookies) = &self.cookies {
+            easy.cookie(cookies)?;
+        }
+        if let Some(client_cert) = &self.client_cert {
+            // curl only says the option is unknown
+            if curl::Version::get().ssl_version().is_none() {
+                return Err(PartialZipError::TlsUnavailable("a client certificate"));
+            }
+            easy.ssl_cert(&client_cert.cert)?;
+            easy.ssl_key(&client_cert.key)?;
+            if let Some(password) = &client_cert.key_password {
+                easy.key_password(password)?;
+            }
+        }
+        if self.accept_invalid_certs {
+            easy.ssl_verify_peer(false)?;
+            easy.ssl_verify_host(false)?;
+        }
+        if let Some(unix_socket) = &self.unix_socket {
+            easy.unix_socket_path(Some(unix_socket))?;
+        }
+        if let Some(max) = self.max_redirections {
+            easy.max_redirections(max)?;
+        }
+        if !self.resolve.is_empty() {
+            let mut list = List::new();
+            for entry in &self.resolve {
+                list.append(entry)?;
+            }
+            easy.resolve(list)?;
+        }
+        match self.http_version {
+            HttpVersion::Auto => {}
+            HttpVersion::Http11 => easy.http_version(curl::easy::HttpVersion::V11)?,
+            HttpVersion::Http2 => easy.http_version(curl::easy::HttpVersion::V2)?,
+            HttpVersion::Http2PriorKnowledge => {
+                easy.http_version(curl::easy::HttpVersion::V2PriorKnowledge)?;
+            }
+        }
+        match self.ftp_mode {
+            FtpMode::Passive => {}
+            FtpMode::PassiveNoEpsv => setopt_long(easy, curl_sys::CURLOPT_FTP_USE_EPSV, 0)?,
+            // "-" lets curl pick the address of the control connection
+            FtpMode::Active => setopt_str(easy, curl_sys::CURLOPT_FTPPORT, c"-")?,
+        }
+        Ok(())
+    }
+}
+
+/// Set a long option of the curl handle, for the ones the `curl` crate doesn't expose
+fn setopt_long(
+    easy: &Easy,
+    option: curl_sys::CURLoption,
+    value: std::os::raw::c_long,
+) -> Result<(), curl::Error> {
+    // SAFETY: the handle is alive as long as `easy`, and `option` takes a long
+    let code = unsafe { curl_sys::curl_easy_setopt(easy.raw(), option, value) };
+    if code == curl_sys::CURLE_OK {
+        Ok(())
+    } else {
+        Err(curl::Error::new(code))
+    }
+}
+
+/// Protocols the redirects can go to, set by name since `CURLOPT_REDIR_PROTOCOLS` is deprecated and not in `curl-sys`
+const CURLOPT_REDIR_PROTOCOLS_STR: curl_sys::CURLoption = curl_sys::CURLOPTTYPE_OBJECTPOINT + 319;
+
+/// Set a string option of the curl handle, for the ones the `curl` crate doesn't expose
+fn setopt_str(easy: &Easy, option: curl_sys::CURLoption, value: &CStr) -> Result<(), curl::Error> {
+    // SAFETY: the handle is alive as long as `easy`, `option` takes a string and curl copies it
+    let code = unsafe { curl_sys::curl_easy_setopt(easy.raw(), option, value.as_ptr()) };
+    if code == curl_sys::CURLE_OK {
+        Ok(())
+    } else {
+        Err(curl::Error::new(code))
+    }
+}
+
+/// Map the errors of a server refusing a range request to [`PartialZipError::RangeNotSupported`]
+fn range_error(e: curl::Error) -> PartialZipError {
+    // FTP servers without the `REST` command
+    if e.code() == curl_sys::CURLE_FTP_COULDNT_USE_REST || e.is_range_error() {
+        PartialZipError::RangeNotSupported
+    } else {
+        e.into()
+    }
+}
+
+const HTTP_PARTIAL_CONTENT: u32 = 206;
+
+/// Count a request done by `easy`, and the connection it opened for the `client` lending it
+fn count_request(easy: &Easy, requests: &AtomicU64, client: Option<&PartialZipClient>) {
+    requests.fetch_add(1, Ordering::Relaxed);
+    if let Some(client) = client {
+        let opened = easy.num_connects().map_or(0, u64::from);
+        client.connections.fetch_add(opened, Ordering::Relaxed);
+    }
+}
+
+/// Check the status of the last HTTP request done by `easy` is in `expected`
+fn check_status(easy: &Easy, expected: std::ops::Range<u32>) -> Result<(), PartialZipError> {
+    let code = easy.response_code()?;
+    let effective_url = easy.effective_url()?.unwrap_or_default();
+    // other protocols, like ftp, have their own response codes
+    if effective_url.starts_with("http") && !expected.contains(&code) {
+        log::warn!("HTTP status {code} from {effective_url}");
+        return Err(PartialZipError::HttpStatus(code));
+    }
+    Ok(())
+}
+
+/// Is the error a network failure or a server error, which could go away retrying
+fn is_transient(e: &PartialZipError) -> bool {
+    match e {
+        PartialZipError::CURLError(e) => {
+            e.is_couldnt_connect()
+                || e.is_operation_timedout()
+                || e.is_send_error()
+                || e.is_recv_error()
+                || e.is_got_nothing()
+                || e.is_partial_file()
+        }
+        PartialZipError::HttpStatus(code) => *code >= 500,
+        _ => false,
+    }
+}
+
+/// Default size of the block cache of a [`PartialReader`]
+const DEFAULT_CACHE_SIZE: u64 = 0x0040_0000;
+
+/// Default size of the end of the archive fetched at once, usually covering the whole central directory
+const DEFAULT_TAIL_PREFETCH: u64 = 0x0002_0000;
+
+impl PartialReader {
+    /// Creates a new [`PartialReader`]
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] enum depending on what happened
+    pub fn new(url: &dyn ToString) -> Result<Self, PartialZipError> {
+        Self::new_check_range(url, false)
+    }
+    /// Creates a new [`PartialReader`]
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] enum depending on what happened
+    pub fn new_check_range(url: &dyn ToString, check_range: bool) -> Result<Self, PartialZipError> {
+        Self::new_with_options(url, check_range, ReaderOptions::default())
+    }
+
+    /// Creates a new [`PartialReader`] authenticating with the given username and password
+    ///
+    /// The credentials are sent with every request to the host of the URL, including the ones following a redirect to it,
+    /// but not to another host unless [`PartialReader::with_unrestricted_auth`] is set.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] enum depending on what happened
+    pub fn new_with_auth(
+        url: &dyn ToString,
+        username: &str,
+        password: &str,
+        check_range: bool,
+    ) -> Result<Self, PartialZipError> {
+        let options = ReaderOptions {
+            credentials: Some(Credentials {
+                username: username.to_string(),
+                password: password.to_string(),
+            }),
+            ..Default::default()
+        };
+        Self::new_with_options(url, check_range, options)
+    }
+
+    /// Creates a new [`PartialReader`] with a timeout to `connect` and a timeout for each `transfer`
+    ///
+    /// A request exceeding the timeouts fails with [`PartialZipError::CURLError`].
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] enum depending on what happened
+    pub fn new_with_timeout(
+        url: &dyn ToString,
+        connect: Duration,
+        transfer: Duration,
+        check_range: bool,
+    ) -> Result<Self, PartialZipError> {
+        let options = ReaderOptions {
+            connect_timeout: Some(connect),
+            timeout: Some(transfer),
+            ..Default::default()
+        };
+        Self::new_with_options(url, check_range, options)
+    }
+
+    /// Creates a new [`PartialReader`] sending the requests through the proxy at `proxy_url`
+    ///
+    /// The type of the proxy is selected by the scheme of its URL, for example `http://` or `socks5://`.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] enum depending on what happened
+    pub fn new_with_proxy(
+        url: &dyn ToString,
+        proxy_url: &str,
+        check_range: bool,
+    ) -> Result<Self, PartialZipError> {
+        let options = ReaderOptions {
+            proxy: Some(proxy_url.to_string()),
+            ..Default::default()
+        };
+        Self::new_with_options(url, check_range, options)
+    }
+
+    /// Creates a new [`PartialReader`] with the curl handle configured by `options`
+    ///
+    /// Without an explicit proxy, curl uses the one in its environment variables, like `http_proxy` or `HTTPS_PROXY`.
+    fn new_with_options(
+        url: &dyn ToString,
+        check_range: bool,
+        options: ReaderOptions,
+    ) -> Result<Self, PartialZipError> {
+        let url = &url.to_string();
+        // curl doesn't speak S3, those URLs are only opened by `PartialZip::new`
+        if !utils::url_is_valid(url) || utils::is_s3_url(url) {
+            return Err(PartialZipError::InvalidUrl);
+        }
+
+        let local = utils::local_path(url).map(File::open).transpose()?;
+
+        Ok(Self {
+            url: url.clone(),
+            file_size: 0,
+            easy: Easy::new(),
+            pos: 0,
+            options,
+            check_range,
+            probed: false,
+            local,
+            cache: BlockCache::new(DEFAULT_CACHE_SIZE),
+            tail_prefetch: DEFAULT_TAIL_PREFETCH,
+            read_end: 0,
+            sequential_blocks: 0,
+            requests: Arc::default(),
+            bytes: Arc::default(),
+            content_type: None,
+            resolved_url: None,
+            validator: None,
+            given_size: None,
+            retries: 0,
+            retry_delay: Duration::ZERO,
+            cookies: Vec::new(),
+            cookie_engine: false,
+            preloaded: Vec::new(),
+            lowest_read: Arc::new(AtomicU64::new(u64::MAX)),
+            cancel: Arc::default(),
+            client: None,
+        })
+    }
+
+    /// Use a curl handle of `client` instead of a new one, reusing its connections to the same host
+    ///
+    /// The handle is given back to the client when the reader is dropped, see [`PartialZipClient`].
+    #[must_use]
+    pub fn with_client(mut self, client: &PartialZipClient) -> Self {
+        self.easy = client.take();
+        self.client = Some(client.clone());
+        self
+    }
+
+    /// Send the given headers with every request, for example `("Authorization", "Bearer token")`
+    #[must_use]
+    pub fn with_headers(mut self, headers: Vec<(String, String)>) -> Self {
+        self.options.headers = headers;
+        self
+    }
+
+    /// Send `user_agent` as the User-Agent of every request, instead of the curl default one
+    #[must_use]
+    pub fn with_user_agent(mut self, user_agent: &str) -> Self {
+        self.options.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    /// Send the given cookies with every request, formatted as `name=value; name2=value2`
+    ///
+    /// They are sent along with the cookies set by the server, for example on a redirect of [`PartialReader::probe`].
+    #[must_use]
+    pub fn with_cookies(mut self, cookies: &str) -> Self {
+        self.options.cookies = Some(cookies.to_string());
+        self
+    }
+
+    /// Present the certificate `cert` with its private `key` to the servers requiring a mutual TLS authentication
+    ///
+    /// A failed handshake is returned as a [`PartialZipError::CURLError`], and the requests fail with
+    /// [`PartialZipError::TlsUnavailable`] if curl was built without TLS support.
+    #[must_use]
+    pub fn with_client_cert(mut self, cert: &Path, key: &Path, key_password: Option<&str>) -> Self {
+        self.options.client_cert = Some(ClientCert {
+            cert: cert.to_path_buf(),
+            key: key.to_path_buf(),
+            key_password: key_password.map(str::to_owned),
+        });
+        self
+    }
+
+    /// Accept any TLS certificate from the server, like `curl -k`
+    ///
+    /// This is dangerous: the connection is still encrypted but anybody can impersonate the server,
+    /// use it only for testing environments with self-signed certificates.
+    #[must_use]
+    pub const fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.options.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Connect to the Unix domain socket at `path` instead of the host of the URL, like `curl --unix-socket`
+    ///
+    /// The URL still selects the protocol and the path of the archive, for example `http://localhost/archive.zip`.
+    /// The proxy options are ignored for these connections.
+    #[must_use]
+    pub fn with_unix_socket(mut self, path: &Path) -> Self {
+        self.options.unix_socket = Some(path.to_path_buf());
+        self
+    }
+
+    /// Follow at most `max` redirects, failing with a [`PartialZipError::CURLError`] after them
+    #[must_use]
+    pub const fn with_max_redirections(mut self, max: u32) -> Self {
+        self.options.max_redirections = Some(max);
+        self
+    }
+
+    /// Refuse to follow the redirects downgrading from HTTPS to plain HTTP
+    ///
+    /// The redirects keeping the scheme of the URL are followed, so a plain HTTP archive can still be redirected
+    /// to plain HTTP. A refused redirect fails with a [`PartialZipError::CURLError`], it needs libcurl 7.85 or newer.
+    #[must_use]
+    pub const fn with_refuse_downgrades(mut self, refuse: bool) -> Self {
+        self.options.refuse_downgrades = refuse;
+        self
+    }
+
+    /// Keep sending the credentials of [`PartialReader::new_with_auth`] after a redirect to another host, off by default
+    ///
+    /// It's for the servers redirecting to a mirror or a CDN needing the same credentials: every host the server
+    /// redirects to gets them, so only for trusted servers, and with [`RedirectPolicy::SameOrigin`] there's no such redirect.
+    #[must_use]
+    pub const fn with_unrestricted_auth(mut self, unrestricted: bool) -> Self {
+        self.options.unrestricted_auth = unrestricted;
+        self
+    }
+
+    /// Follow the redirects allowed by `policy`, all of them by default
+    ///
+    /// With [`RedirectPolicy::SameOrigin`] the redirects are followed by the reader instead of curl, while probing,
+    /// checking every target before sending anything to it, and the range requests go to the final URL.
+    /// A redirect to another origin fails with [`PartialZipError::RedirectRefused`].
+    #[must_use]
+    pub const fn with_redirect_policy(mut self, policy: RedirectPolicy) -> Self {
+        self.options.redirect_policy = policy;
+        self
+    }
+
+    /// Build the value of the `Range` header of the HTTP requests with `formatter`, from the first and the last offsets included
+    ///
+    /// It's an escape hatch for the servers rejecting the standard `bytes={start}-{end}`, for example to send `bytes {start}-{end}`.
+    /// The header replaces the one curl sends, the responses are still expected to be standard.
+    #[must_use]
+    pub const fn with_range_formatter(mut self, formatter: fn(u64, u64) -> String) -> Self {
+        self.options.range_formatter = Some(formatter);
+        self
+    }
+
+    /// Connect to `addr` for the requests to `host` on `port`, instead of the address from the DNS, like `curl --resolve`
+    ///
+    /// The URL and the `Host` header are unchanged, so the TLS certificate is still checked against `host`.
+    /// It can be called several times to override several hosts.
+    #[must_use]
+    pub fn with_resolve(mut self, host: &str, port: u16, addr: &str) -> Self {
+        // curl expects the IPv6 addresses between brackets
+        let addr = if addr.contains(':') && !addr.starts_with('[') {
+            format!("[{addr}]")
+        } else {
+            addr.to_string()
+        };
+        self.options.resolve.push(format!("{host}:{port}:{addr}"));
+        self
+    }
+
+    /// Authenticate against the proxy with the given username and password
+    #[must_use]
+    pub fn with_proxy_auth(mut self, username: &str, password: &str) -> Self {
+        self.options.proxy_credentials = Some(Credentials {
+            username: username.to_string(),
+            password: password.to_string(),
+        });
+        self
+    }
+
+    /// Set the size in bytes of the cache of the blocks already fetched, 0 disables it
+    ///
+    /// The archive is fetched in aligned blocks so the small reads around the same area are served from the cache.
+    /// The sequential reads, like the one of a big entry, fetch up to the size of the cache ahead at once.
+    #[must_use]
+    pub fn with_cache_size(mut self, bytes: u64) -> Self {
+        self.cache = BlockCache::new(bytes);
+        self
     }
 
-    /// Returns the zip size for the entire archive of the [`PartialZip`]
-    pub const fn file_size(&self) -> u64 {
-        self.file_size
+    /// Returns how many reads have been served from the block cache
+    #[must_use]
+    pub const fn cache_hits(&self) -> u64 {
+        self.cache.hits()
     }
 
-    /// Get a list of the filenames in the archive
-    pub fn list_names(&self) -> Vec<String> {
-        self.archive
-            .borrow()
-            .file_names()
-            .map(std::borrow::ToOwned::to_owned)
-            .collect()
+    /// Set the size in bytes of the end of the archive fetched at once when a read lands there, 0 disables it
+    ///
+    /// The zip crate does many small reads around the end of central directory and the central directory itself,
+    /// prefetching the end of the archive usually serves all of them with a single request.
+    /// It requires the block cache to be enabled.
+    #[must_use]
+    pub const fn with_tail_prefetch(mut self, bytes: u64) -> Self {
+        self.tail_prefetch = bytes;
+        self
     }
 
-    /// Get a list of the files in the archive with details (much slower than just listing names because it fetches much more data around with more requests)
-    pub fn list_detailed(&self) -> Vec<PartialZipFileDetailed> {
-        let mut file_list = Vec::new();
-        let num_files = self.archive.borrow().len();
-        for i in 0..num_files {
-            match self.archive.borrow_mut().by_index(i) {
-                Ok(file) => {
-                    let compression_method = file.compression();
-                    // we only support some compressions
-                    let supported = matches!(
-                        compression_method,
-                        zip::CompressionMethod::Stored
-                            | zip::CompressionMethod::Deflated
-                            | zip::CompressionMethod::Bzip2
-                            | zip::CompressionMethod::Zstd
-                    );
-                    let date = NaiveDate::from_ymd_opt(
-                        file.last_modified().year().into(),
-                        file.last_modified().month().into(),
-                        file.last_modified().day().into(),
-                    );
-                    let time = NaiveTime::from_hms_opt(
-                        file.last_modified().hour().into(),
-                        file.last_modified().minute().into(),
-                        file.last_modified().second().into(),
-                    );
-                    let last_modified = if let (Some(d), Some(t)) = (date, time) {
-                        Some(NaiveDateTime::new(d, t))
-                    } else {
-                        None
-                    };
-                    let pzf = PartialZipFileDetailed {
-                        name: file.name().to_string(),
-                        compressed_size: file.compressed_size(),
-                        compression_method: compression_method.into(),
-                        supported,
-                        last_modified,
-                    };
-                    file_list.push(pzf);
-                }
-                Err(e) => {
-                    // We are unable to get a file, let's try to continue,
-                    // and at least return the files we can
-                    log::warn!("list: error while matching file by index: {i} - {e}");
-                    continue;
-                }
-            };
-        }
-        file_list
+    /// Limit the download speed of every request to `bytes_per_sec`
+    #[must_use]
+    pub const fn with_max_recv_speed(mut self, bytes_per_sec: u64) -> Self {
+        self.options.max_recv_speed = Some(bytes_per_sec);
+        self
     }
-    /// Download a single file from the archive
+
+    /// Abort the requests transferring less than `bytes_per_sec` for `over`, instead of waiting for a stalled server
     ///
-    /// # Errors
-    /// Will return a [`PartialZipError`] depending on what happened
-    pub fn download(&self, filename: &str) -> Result<Vec<u8>, PartialZipError> {
-        let mut content: Vec<u8> = Vec::new();
-        self.download_to_write(filename, &mut content)?;
-        Ok(content)
+    /// Unlike a timeout, a slow but steady transfer of a large file isn't aborted.
+    /// An aborted request fails with [`PartialZipError::CURLError`].
+    #[must_use]
+    pub fn with_low_speed_abort(mut self, bytes_per_sec: u64, over: Duration) -> Self {
+        self.options.low_speed = Some((bytes_per_sec.to_u32().unwrap_or(u32::MAX), over));
+        self
     }
 
-    /// Download a single file from the archive and writes it to a [`std::io::Write`]
+    /// Use the given mode for the FTP data connections, instead of the passive mode
     ///
-    /// # Errors
-    /// Will return a [`PartialZipError`] depending on what happened
-    pub fn download_to_write(
-        &self,
-        filename: &str,
-        writer: &mut dyn std::io::Write,
-    ) -> Result<(), PartialZipError> {
-        let mut archive = self.archive.borrow_mut();
-        let mut file = archive.by_name(filename)?;
-        io::copy(&mut file, writer)?;
-        Ok(())
+    /// The credentials of [`PartialReader::new_with_auth`] are used to log in the FTP servers too.
+    #[must_use]
+    pub const fn with_ftp_mode(mut self, mode: FtpMode) -> Self {
+        self.options.ftp_mode = mode;
+        self
     }
 
-    /// Download a single file from the archive showing a progress bar
+    /// Use the given version of the HTTP protocol, instead of letting curl choose
     ///
-    /// # Errors
-    /// Will return a [`PartialZipError`] depending on what happened
-    #[cfg(feature = "progressbar")]
-    pub fn download_with_progressbar(&self, filename: &str) -> Result<Vec<u8>, PartialZipError> {
-        let mut content: Vec<u8> = Vec::new();
-        self.download_to_write_with_progressbar(filename, &mut content)?;
-        Ok(content)
+    /// With HTTP/2 the range requests are multiplexed on a single connection, which saves a lot of time
+    /// when there are many small ones, like while listing. It needs a libcurl built with HTTP/2 support,
+    /// like the one of the `http2` feature, otherwise the requests fail with a [`PartialZipError::CURLError`].
+    /// Like with HTTP/1.1, all the requests of a reader go through the same connection while the server keeps it open.
+    #[must_use]
+    pub const fn with_http_version(mut self, version: HttpVersion) -> Self {
+        self.options.http_version = version;
+        self
     }
 
-    /// Download a single file from the archive showing a progress bar to a [`std::io::Write`]
+    /// Retry a failed range request up to `max` times, waiting `base_delay` before the first retry and doubling it at every attempt
     ///
-    /// # Errors
-    /// Will return a [`PartialZipError`] depending on what happened
-    #[cfg(feature = "progressbar")]
-    pub fn download_to_write_with_progressbar(
-        &self,
-        filename: &str,
-        writer: &mut dyn std::io::Write,
-    ) -> Result<(), PartialZipError> {
-        use indicatif::ProgressBar;
+    /// Only the network failures and the server errors (HTTP 5xx) are retried, not the client errors (HTTP 4xx).
+    #[must_use]
+    pub const fn with_retries(mut self, max: u32, base_delay: Duration) -> Self {
+        self.retries = max;
+        self.retry_delay = base_delay;
+        self
+    }
 
-        let mut archive = self.archive.borrow_mut();
-        let file = archive.by_name(filename)?;
-        let pb = ProgressBar::new(file.compressed_size());
-        io::copy(&mut pb.wrap_read(file), writer)?;
-        Ok(())
+    /// Use `size` as the size of the archive instead of probing it, saving the HEAD request when the size is already known
+    ///
+    /// The size and the range support are checked against the response to the first range request instead,
+    /// which fails with an [`ErrorKind::InvalidData`] error if the archive has another size.
+    /// Local files are still probed, it doesn't need any request.
+    #[must_use]
+    pub const fn with_file_size(mut self, size: u64) -> Self {
+        if self.local.is_none() {
+            self.file_size = size;
+            self.probed = true;
+            self.given_size = Some(size);
+        }
+        self
     }
-}
 
-/// Reader for the partialzip doing only the partial read instead of downloading everything
-#[derive(Debug)]
-pub struct PartialReader {
-    /// URL at which we read the file
-    url: String,
-    file_size: u64,
-    easy: Easy,
-    pos: u64,
-}
+    /// Serve the end of the archive of `total_size` bytes from `directory` instead of fetching it, without probing the archive
+    fn with_directory(
+        mut self,
+        directory: &[u8],
+        total_size: u64,
+    ) -> Result<Self, PartialZipError> {
+        let start = total_size
+            .checked_sub(directory.len().to_u64().unwrap_or(u64::MAX))
+            .ok_or_else(|| {
+                io::Error::new(
+                    ErrorKind::InvalidInput,
+                    "the directory is larger than the archive",
+                )
+            })?;
+        self.file_size = total_size;
+        self.probed = true;
+        self.preload(start, directory.into());
+        Ok(self)
+    }
 
-const HTTP_PARTIAL_CONTENT: u32 = 206;
+    /// Serve the reads of the bytes from `start` with `content`, without fetching them
+    fn preload(&mut self, start: u64, content: Arc<[u8]>) {
+        self.preloaded.push((start, content));
+    }
 
-impl PartialReader {
-    /// Creates a new [`PartialReader`]
+    /// Fetch the bytes from `start` to `end` (included) with a single request, serving the following reads of them from memory
     ///
-    /// # Errors
-    /// Will return a [`PartialZipError`] enum depending on what happened
-    pub fn new(url: &dyn ToString) -> Result<Self, PartialZipError> {
-        Self::new_check_range(url, false)
+    /// Local files are read directly, they are never preloaded.
+    fn preload_range(&mut self, start: u64, end: u64) -> io::Result<()> {
+        if self.local.is_none() {
+            let content = self.fetch(start, end)?;
+            self.preload(start, content.into());
+        }
+        Ok(())
     }
-    /// Creates a new [`PartialReader`]
+
+    /// Returns how many HTTP requests have been done, including the ones of the readers cloned from this one
+    #[must_use]
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    /// Returns how many requests have been done and how many bytes have been received, including by the readers cloned from this one
+    #[must_use]
+    pub fn stats(&self) -> ReadStats {
+        ReadStats {
+            requests: self.requests.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Probe the archive with a HEAD request to get its size, and check if range requests are supported when required.
+    ///
+    /// It's done only once, and it's called automatically on the first read or seek.
     ///
     /// # Errors
     /// Will return a [`PartialZipError`] enum depending on what happened
-    pub fn new_check_range(url: &dyn ToString, check_range: bool) -> Result<Self, PartialZipError> {
-        let url = &url.to_string();
-        if !utils::url_is_valid(url) {
-            return Err(PartialZipError::InvalidUrl);
+    pub fn probe(&mut self) -> Result<(), PartialZipError> {
+        if self.probed {
+            return Ok(());
         }
-
-        let mut easy = Easy::new();
-        easy.url(url)?;
-        easy.follow_location(true)?;
-        easy.tcp_keepalive(true)?;
-        easy.tcp_keepidle(Duration::from_secs(120))?;
-        easy.tcp_keepintvl(Duration::from_secs(60))?;
+        // local files always support seeking, no need to check the range support
+        if let Some(file) = &self.local {
+            self.file_size = file.metadata()?.len();
+            self.probed = true;
+            return Ok(());
+        }
+        self.prepare()?;
+        let easy = &mut self.easy;
         easy.nobody(true)?;
         easy.write_function(|data| Ok(data.len()))?;
-        easy.perform()?;
-        let file_size = easy
-            .content_length_download()?
-            .to_u64()
-            .ok_or_else(|| std::io::Error::new(ErrorKind::InvalidData, "invalid content length"))?;
-
-        if check_range {
+        let headers = Arc::new(Mutex::new(Vec::new()));
+        let received = headers.clone();
+        easy.header_function(move |header| {
+            received
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(String::from_utf8_lossy(header).into_owned());
+            true
+        })?;
+        let max_redirections = self
+            .options
+            .max_redirections
+            .unwrap_or(DEFAULT_MAX_REDIRECTIONS);
+        let mut redirects = 0;
+        loop {
+            easy.perform()?;
+            count_request(easy, &self.requests, self.client.as_ref());
+            if self.options.redirect_policy == RedirectPolicy::All
+                || !(300..400).contains(&easy.response_code()?)
+            {
+                break;
+            }
+            let Some(target) = easy.redirect_url()?.map(str::to_owned) else {
+                break;
+            };
+            if !utils::same_origin(&self.url, &target) {
+                log::warn!("redirect from {} to {target} refused", self.url);
+                return Err(PartialZipError::RedirectRefused(target));
+            }
+            if redirects >= max_redirections {
+                return Err(curl::Error::new(curl_sys::CURLE_TOO_MANY_REDIRECTS).into());
+            }
+            redirects += 1;
+            easy.url(&target)?;
+        }
+        check_status(easy, 200..400)?;
+        easy.header_function(|_| true)?;
+        self.validator =
+            utils::range_validator(&headers.lock().unwrap_or_else(PoisonError::into_inner));
+        self.content_type = easy.content_type()?.map(str::to_owned);
+        self.resolved_url = easy.effective_url()?.map(str::to_owned);
+        self.cookies = easy
+            .cookies()?
+            .iter()
+            .filter_map(|cookie| std::str::from_utf8(cookie).ok().map(str::to_owned))
+            .collect();
+        // a chunked response has no length, the range response tells the total size instead,
+        // proving the range support at the same time
+        let (file_size, range_checked) = match easy.content_length_download()?.to_u64() {
+            Some(file_size) => (file_size, false),
+            None => (self.content_range_size()?, true),
+        };
+        if self.check_range && self.url.starts_with("ftp") {
+            // FTP has no HEAD for a range, fetch the last byte with the `REST` command,
+            // curl goes on if a server refuses to restart at 0
+            let last = file_size.saturating_sub(1);
+            if self.fetch_once(last, last)?.len() != 1 {
+                return Err(PartialZipError::RangeNotSupported);
+            }
+        } else if self.check_range && !range_checked {
             // check if range-request is possible by request 1 byte. if 206 Partial Content (HTTP_PARTIAL_CONTENT) is returned, we can make future request.
-            easy.range("0-0")?;
+            self.set_range(Some((0, 0)))?;
+            let easy = &mut self.easy;
             easy.nobody(true)?;
             easy.perform()?;
+            count_request(easy, &self.requests, self.client.as_ref());
             let head_size = easy.content_length_download()?.to_u64().ok_or_else(|| {
                 std::io::Error::new(ErrorKind::InvalidData, "can not perform range request")
             })?;
@@ -314,15 +4118,12 @@ impl PartialReader {
             if easy.response_code()? != HTTP_PARTIAL_CONTENT {
                 return Err(PartialZipError::RangeNotSupported);
             }
-            easy.range("")?;
             easy.nobody(false)?;
+            self.set_range(None)?;
         }
-        Ok(Self {
-            url: url.to_string(),
-            file_size,
-            easy,
-            pos: 0,
-        })
+        self.file_size = file_size;
+        self.probed = true;
+        Ok(())
     }
 
     /// Returns the url for the [`PartialReader`]
@@ -330,10 +4131,400 @@ impl PartialReader {
     pub fn url(&self) -> String {
         self.url.clone()
     }
+
+    /// Returns the size of the archive, known once the archive is probed
+    #[must_use]
+    pub const fn file_size(&self) -> u64 {
+        self.file_size
+    }
+
+    /// Returns the URL of the archive after following the redirects, known once the archive is probed
+    ///
+    /// It's the URL given to [`PartialReader::new`] when there was no redirect, and `None` for the local files.
+    #[must_use]
+    pub fn resolved_url(&self) -> Option<&str> {
+        self.resolved_url.as_deref()
+    }
+
+    /// Returns the `Content-Type` announced by the server, known once the archive is probed
+    #[must_use]
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+}
+
+impl PartialReader {
+    /// Configure the curl handle before a request
+    fn prepare(&mut self) -> Result<(), PartialZipError> {
+        if self.options.redirect_policy == RedirectPolicy::All {
+            self.easy.url(&self.url)?;
+            self.easy.follow_location(true)?;
+        } else {
+            // the redirects were checked while probing
+            self.easy
+                .url(self.resolved_url.as_deref().unwrap_or(&self.url))?;
+            self.easy.follow_location(false)?;
+        }
+        // a plain HTTP archive has nothing to downgrade, its redirects are left to the curl defaults
+        if self.options.refuse_downgrades && utils::is_https_url(&self.url) {
+            setopt_str(&self.easy, CURLOPT_REDIR_PROTOCOLS_STR, c"https")?;
+        }
+        self.easy.tcp_keepalive(true)?;
+        self.easy.tcp_keepidle(Duration::from_secs(120))?;
+        self.easy.tcp_keepintvl(Duration::from_secs(60))?;
+        if !self.cookie_engine {
+            // an empty file enables the cookie engine without loading anything, so the cookies set by the server,
+            // for example on an authentication redirect, are sent back with the following requests.
+            // It's done once per handle since curl keeps a list of all the cookie files set
+            self.easy.cookie_file("")?;
+            for cookie in &self.cookies {
+                self.easy.cookie_list(cookie)?;
+            }
+            self.cookie_engine = true;
+        }
+        // curl may reset some state between performs, so apply the options again
+        self.options.apply(&mut self.easy)?;
+        Ok(self.set_headers(None)?)
+    }
+
+    /// Set the custom headers of the requests with the `If-Range` validator and the formatted `range`, when there are some
+    fn set_headers(&mut self, range: Option<&str>) -> Result<(), curl::Error> {
+        // a formatted range of a previous request must be removed too
+        if self.validator.is_none() && self.options.range_formatter.is_none() {
+            return Ok(());
+        }
+        let mut list = List::new();
+        for (name, value) in &self.options.headers {
+            list.append(&format!("{name}: {value}"))?;
+        }
+        if let Some(validator) = &self.validator {
+            // the server sends the whole archive instead of the range if it doesn't match anymore
+            list.append(&format!("If-Range: {validator}"))?;
+        }
+        if let Some(range) = range {
+            list.append(&format!("Range: {range}"))?;
+        }
+        self.easy.http_headers(list)
+    }
+
+    /// Ask for the bytes from `start` to `end` (included) with the next requests, or for the whole archive with `None`
+    fn set_range(&mut self, range: Option<(u64, u64)>) -> Result<(), curl::Error> {
+        let formatter = self
+            .options
+            .range_formatter
+            .filter(|_| self.url.starts_with("http"));
+        if let Some(formatter) = formatter {
+            self.easy.range("")?;
+            return self.set_headers(range.map(|(start, end)| formatter(start, end)).as_deref());
+        }
+        self.easy
+            .range(&range.map_or_else(String::new, |(start, end)| format!("{start}-{end}")))
+    }
+
+    /// Current position of the reader
+    fn position(&self) -> io::Result<u64> {
+        self.local
+            .as_ref()
+            .map_or(Ok(self.pos), |mut file| file.stream_position())
+    }
+
+    /// Create a new reader of the same archive positioned at `pos`, with its own connection and without probing again.
+    ///
+    /// It starts with a copy of the block cache, the blocks it fetches afterwards aren't added to the cache of this reader.
+    /// A local file which can't be opened again is read through curl, which reports the error on read.
+    fn clone_at(&self, pos: u64) -> Self {
+        let local = self
+            .local
+            .as_ref()
+            .and_then(|_| utils::local_path(&self.url))
+            .and_then(|path| File::open(path).ok())
+            .and_then(|mut file| file.seek(io::SeekFrom::Start(pos)).ok().map(|_| file));
+        Self {
+            url: self.url.clone(),
+            file_size: self.file_size,
+            easy: self
+                .client
+                .as_ref()
+                .map_or_else(Easy::new, PartialZipClient::take),
+            pos,
+            options: self.options.clone(),
+            check_range: self.check_range,
+            probed: self.probed,
+            local,
+            cache: self.cache.clone(),
+            tail_prefetch: self.tail_prefetch,
+            read_end: pos,
+            sequential_blocks: 0,
+            requests: self.requests.clone(),
+            bytes: self.bytes.clone(),
+            content_type: self.content_type.clone(),
+            resolved_url: self.resolved_url.clone(),
+            validator: self.validator.clone(),
+            given_size: self.given_size,
+            retries: self.retries,
+            retry_delay: self.retry_delay,
+            cookies: self.cookies.clone(),
+            cookie_engine: false,
+            preloaded: self.preloaded.clone(),
+            lowest_read: self.lowest_read.clone(),
+            // the clones serve other downloads
+            cancel: Arc::default(),
+            client: self.client.clone(),
+        }
+    }
+
+    /// Get the size of the archive from the `Content-Range` header of the response to a range request of its first byte
+    ///
+    /// A server ignoring the range sends the whole archive, the transfer is aborted after the headers then.
+    fn content_range_size(&mut self) -> Result<u64, PartialZipError> {
+        let mut headers = Vec::new();
+        let mut received = 0;
+        self.easy.nobody(false)?;
+        self.set_range(Some((0, 0)))?;
+        {
+            let mut transfer = self.easy.transfer();
+            transfer.header_function(|header| {
+                headers.push(String::from_utf8_lossy(header).into_owned());
+                true
+            })?;
+            transfer.write_function(|data| {
+                received += data.len();
+                // returning less than the data aborts the transfer
+                Ok(if received > 1 { 0 } else { data.len() })
+            })?;
+            match transfer.perform() {
+                Err(e) if e.is_write_error() => {}
+                result => result?,
+            }
+        }
+        count_request(&self.easy, &self.requests, self.client.as_ref());
+        self.set_range(None)?;
+        check_status(&self.easy, 200..300)?;
+        // the last response after the redirects
+        Ok(headers
+            .iter()
+            .rev()
+            .find_map(|header| utils::content_range_total(header))
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "invalid content length"))?)
+    }
+
+    /// Fetch the bytes from `start` to `end` (included) with a range request
+    ///
+    /// Transient failures are retried as configured with [`PartialReader::with_retries`].
+    fn fetch(&mut self, start: u64, end: u64) -> io::Result<Vec<u8>> {
+        let mut attempt = 0;
+        loop {
+            match self.fetch_once(start, end) {
+                Ok(content) => return Ok(content),
+                Err(e) if attempt < self.retries && is_transient(&e) => {
+                    let delay = self
+                        .retry_delay
+                        .saturating_mul(2u32.saturating_pow(attempt));
+                    log::warn!("range {start}-{end} failed, retrying in {delay:?}: {e}");
+                    thread::sleep(delay);
+                    attempt += 1;
+                }
+                Err(e) => return Err(io::Error::other(e)),
+            }
+        }
+    }
+
+    /// Fetch the bytes from `start` to `end` (included) with a single range request
+    fn fetch_once(&mut self, start: u64, end: u64) -> Result<Vec<u8>, PartialZipError> {
+        let range = format!("{start}-{end}");
+        log::trace!("range = {range}");
+
+        let cancel = self
+            .cancel
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        let cancelled = || cancel.as_ref().is_some_and(|c| c.load(Ordering::Relaxed));
+        if cancelled() {
+            return Err(PartialZipError::Cancelled);
+        }
+        self.prepare()?;
+        self.set_range(Some((start, end)))?;
+        self.easy.get(true)?;
+        // the progress callback aborts the transfer as soon as the download is cancelled
+        self.easy.progress(cancel.is_some())?;
+
+        let mut content: Vec<u8> = Vec::new();
+        let mut headers = Vec::new();
+        {
+            let mut transfer = self.easy.transfer();
+            transfer.progress_function(|_, _, _, _| !cancelled())?;
+            transfer.write_function(|data| {
+                log::trace!("transfered {:x} bytes", data.len());
+                content.extend_from_slice(data);
+                Ok(data.len())
+            })?;
+            transfer.header_function(|header| {
+                headers.push(String::from_utf8_lossy(header).into_owned());
+                true
+            })?;
+
+            transfer.perform().map_err(|e| {
+                if cancelled() {
+                    PartialZipError::Cancelled
+                } else {
+                    range_error(e)
+                }
+            })?;
+        };
+        count_request(&self.easy, &self.requests, self.client.as_ref());
+        self.bytes.fetch_add(
+            content.len().to_u64().unwrap_or_default(),
+            Ordering::Relaxed,
+        );
+        check_status(&self.easy, 200..300)?;
+        if let Some(size) = self.given_size.filter(|_| self.url.starts_with("http")) {
+            self.verify_size(&headers, size, start == 0 && end.saturating_add(1) == size)?;
+        }
+        if self.url.starts_with("http")
+            && self.easy.response_code()? == 200
+            && self.validator.is_some()
+            && utils::range_validator(&headers) != self.validator
+        {
+            log::warn!("range {range} answered with another version of the archive");
+            return Err(PartialZipError::ArchiveChanged);
+        }
+        let whole_range = start == 0 && end.saturating_add(1) == self.file_size;
+        if self.url.starts_with("http") && self.easy.response_code()? == 200 && !whole_range {
+            // the server ignored the range and sent the whole archive, keep it to serve all the following reads
+            log::warn!("range {range} ignored by the server, keeping the whole archive");
+            if content.len().to_u64() != Some(self.file_size) {
+                return Err(PartialZipError::RangeNotSupported);
+            }
+            let (start, end) = (start.to_usize(), end.to_usize());
+            let (Some(start), Some(end)) = (start, end) else {
+                return Err(PartialZipError::RangeNotSupported);
+            };
+            let whole: Arc<[u8]> = content.into();
+            self.preload(0, whole.clone());
+            return Ok(whole[start..=end].to_vec());
+        }
+        Ok(content)
+    }
+
+    /// Check the size given with [`PartialReader::with_file_size`] and the range support against the `headers` of the first range response,
+    /// taking the validator of the archive from them since it wasn't probed
+    ///
+    /// Some servers answer a range covering the `whole` archive with the whole archive, not proving the range support,
+    /// but there is nothing else to fetch then.
+    fn verify_size(
+        &mut self,
+        headers: &[String],
+        size: u64,
+        whole: bool,
+    ) -> Result<(), PartialZipError> {
+        let actual = if self.easy.response_code()? == HTTP_PARTIAL_CONTENT {
+            // the last response after the redirects
+            headers
+                .iter()
+                .rev()
+                .find_map(|header| utils::content_range_total(header))
+        } else if self.check_range && !whole {
+            return Err(PartialZipError::RangeNotSupported);
+        } else {
+            // the whole archive was sent, its size is checked with the content
+            Some(size)
+        };
+        if actual != Some(size) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "the archive has {} bytes, not {}",
+                    actual.map_or_else(|| "an unknown number of".to_string(), |a| a.to_string()),
+                    size
+                ),
+            )
+            .into());
+        }
+        self.validator = utils::range_validator(headers);
+        self.given_size = None;
+        Ok(())
+    }
+
+    /// Read the bytes from `start` up to `end` (included) going through the block cache.
+    ///
+    /// It can return less bytes than requested, but always at least one.
+    fn read_cached(&mut self, start: u64, end: u64) -> io::Result<Vec<u8>> {
+        let mut first = start / BLOCK_SIZE;
+        let offset = (start % BLOCK_SIZE)
+            .to_usize()
+            .ok_or_else(|| std::io::Error::new(ErrorKind::InvalidData, "invalid block offset"))?;
+        let sequential = start == self.read_end;
+        if let Some(content) = self.cache.get(first, offset) {
+            if !content.is_empty() {
+                log::trace!("block {first:x} served from the cache");
+                return Ok(content.to_vec());
+            }
+        }
+        // a read following the previous one fetches twice as much ahead as the last time, up to the size of the cache
+        let ahead = if sequential {
+            (self.sequential_blocks * 2).min(self.cache.capacity())
+        } else {
+            0
+        };
+        let last_block = (self.file_size - 1) / BLOCK_SIZE;
+        // fetch the missing blocks covering the range in a single request, up to the next cached one
+        let mut last = first;
+        while (last < end / BLOCK_SIZE || last + 1 < first + ahead)
+            && last < last_block
+            && !self.cache.contains(last + 1)
+        {
+            last += 1;
+        }
+        self.sequential_blocks = last - first + 1;
+        let tail_start = self.file_size.saturating_sub(self.tail_prefetch);
+        if self.tail_prefetch > 0 && start >= tail_start {
+            // the read is in the tail, fetch it whole since the zip crate will read around
+            first = std::cmp::min(first, tail_start / BLOCK_SIZE);
+            last = last_block;
+        }
+        let fetch_start = first * BLOCK_SIZE;
+        let offset = (start - fetch_start)
+            .to_usize()
+            .ok_or_else(|| std::io::Error::new(ErrorKind::InvalidData, "invalid block offset"))?;
+        let fetch_end = std::cmp::min((last + 1) * BLOCK_SIZE, self.file_size) - 1;
+        let mut content = self.fetch(fetch_start, fetch_end)?;
+        for (block, chunk) in (first..).zip(content.chunks(BLOCK_LEN)) {
+            // only cache whole blocks, or the last one of the archive
+            let block_end = block * BLOCK_SIZE + chunk.len() as u64;
+            if chunk.len() as u64 == BLOCK_SIZE || block_end == self.file_size {
+                self.cache.insert(block, chunk.to_vec());
+            }
+        }
+        if offset > content.len() {
+            return Err(std::io::Error::new(
+                ErrorKind::UnexpectedEof,
+                format!("short range response {fetch_start}-{fetch_end}"),
+            ));
+        }
+        content.drain(..offset);
+        Ok(content)
+    }
 }
 
 impl io::Read for PartialReader {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.lowest_read
+            .fetch_min(self.position()?, Ordering::Relaxed);
+        if let Some(file) = &mut self.local {
+            return file.read(buf);
+        }
+        for (start, preloaded) in &self.preloaded {
+            let offset = self.pos.checked_sub(*start).and_then(|o| o.to_usize());
+            if let Some(mut content) = offset.and_then(|o| preloaded.get(o..)) {
+                if !content.is_empty() {
+                    let n = content.read(buf)?;
+                    self.pos += n.to_u64().unwrap_or_default();
+                    return Ok(n);
+                }
+            }
+        }
+        self.probe().map_err(io::Error::other)?;
         log::trace!(
             "read self.pos = {:x} self.file_size = {:x}",
             self.pos,
@@ -384,22 +4575,10 @@ impl io::Read for PartialReader {
                 format!("end < start: {end} < {start}"),
             ));
         }
-        let range = format!("{start}-{end}");
-        log::trace!("range = {range}");
-
-        self.easy.range(&range)?;
-        self.easy.get(true)?;
-
-        let mut content: Vec<u8> = Vec::new();
-        {
-            let mut transfer = self.easy.transfer();
-            transfer.write_function(|data| {
-                log::trace!("transfered {:x} bytes", data.len());
-                content.extend_from_slice(data);
-                Ok(data.len())
-            })?;
-
-            transfer.perform()?;
+        let content = if self.cache.is_enabled() {
+            self.read_cached(start, end)?
+        } else {
+            self.fetch(start, end)?
         };
 
         let n = io::Read::read(&mut content[..].as_ref(), buf)?;
@@ -416,19 +4595,26 @@ impl io::Read for PartialReader {
                 )
             })?;
         log::trace!("new self.pos = {:x}", self.pos);
+        self.read_end = self.pos;
         Ok(n)
     }
 }
 
 impl io::Seek for PartialReader {
     fn seek(&mut self, style: io::SeekFrom) -> io::Result<u64> {
+        if let Some(file) = &mut self.local {
+            return file.seek(style);
+        }
         // we can seek both from start, end, or current position
         let (base_pos, offset) = match style {
             io::SeekFrom::Start(n) => {
                 self.pos = n;
                 return Ok(n);
             }
-            io::SeekFrom::End(n) => (self.file_size, n),
+            io::SeekFrom::End(n) => {
+                self.probe().map_err(io::Error::other)?;
+                (self.file_size, n)
+            }
             io::SeekFrom::Current(n) => (self.pos, n),
         };
         log::trace!("seek base_pos = {base_pos:x} offset = {offset:x}");
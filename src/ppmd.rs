@@ -0,0 +1,58 @@
+use ppmd_rust::{Ppmd8Decoder, RestoreMethod};
+use std::io::{self, ErrorKind, Read};
+
+/// Reader decompressing a `PPMd` entry as stored in a zip archive (APPNOTE 5.10).
+///
+/// The content starts with a 2 bytes header holding the parameters of the model, followed by the `PPMd8` (variant I rev. 1) stream.
+/// The decoder doesn't know where the stream ends, so it's bounded by the uncompressed size, and the CRC32 is checked at the end.
+pub struct PpmdReader<R: Read> {
+    /// The decoder of the content following the header
+    decoder: io::Take<Ppmd8Decoder<R>>,
+    /// CRC32 of the uncompressed content, stored in the archive
+    crc32: u32,
+    /// CRC32 of the content returned so far
+    hasher: crc32fast::Hasher,
+}
+
+impl<R: Read> PpmdReader<R> {
+    /// Create a new [`PpmdReader`] over the raw content of an entry with the given uncompressed `size` and `crc32`
+    ///
+    /// # Errors
+    /// Will return an error if the header can't be read or has invalid parameters
+    pub fn new(mut inner: R, size: u64, crc32: u32) -> io::Result<Self> {
+        let mut header = [0; 2];
+        inner.read_exact(&mut header)?;
+        let header = u16::from_le_bytes(header);
+        let order = u32::from(header & 0x000f) + 1;
+        let mem_size = (u32::from((header >> 4) & 0x00ff) + 1) << 20;
+        let restore_method = match header >> 12 {
+            0 => RestoreMethod::Restart,
+            1 => RestoreMethod::CutOff,
+            method => {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("unexpected PPMd restore method {method}"),
+                ))
+            }
+        };
+        let decoder = Ppmd8Decoder::new(inner, order, mem_size, restore_method).map_err(|e| {
+            io::Error::new(ErrorKind::InvalidData, format!("invalid PPMd stream: {e}"))
+        })?;
+        Ok(Self {
+            decoder: decoder.take(size),
+            crc32,
+            hasher: crc32fast::Hasher::new(),
+        })
+    }
+}
+
+impl<R: Read> Read for PpmdReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.decoder.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        if n == 0 && !buf.is_empty() && self.hasher.clone().finalize() != self.crc32 {
+            return Err(io::Error::other("Invalid checksum"));
+        }
+        Ok(n)
+    }
+}
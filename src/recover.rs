@@ -0,0 +1,140 @@
+use num_traits::ToPrimitive;
+
+use super::partzip::{
+    le_u16, le_u32, CENTRAL_HEADER_SIGNATURE, END_OF_CENTRAL_DIRECTORY_SIGNATURE,
+    LOCAL_HEADER_SIGNATURE,
+};
+
+/// Size of a local file header without the filename and the extra fields
+const LOCAL_HEADER_LEN: usize = 30;
+/// Signature of the optional data descriptor following the content of a file
+const DATA_DESCRIPTOR_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x07, 0x08];
+/// Flag of the local header telling the sizes and the CRC32 are in a data descriptor after the content
+const DATA_DESCRIPTOR_FLAG: u16 = 0x0008;
+
+/// A file found from its local header
+struct LocalEntry<'a> {
+    /// Offset of the local header in the archive
+    offset: u32,
+    /// The local header, filename and extra fields included
+    header: &'a [u8],
+    crc32: u32,
+    compressed_size: u32,
+    uncompressed_size: u32,
+}
+
+/// Returns the offset of the first local or central header signature at or after `from`
+fn next_header(data: &[u8], from: usize) -> Option<usize> {
+    data.get(from..)?
+        .windows(4)
+        .position(|window| window == LOCAL_HEADER_SIGNATURE || window == CENTRAL_HEADER_SIGNATURE)
+        .map(|position| from + position)
+}
+
+/// Parse the local header at `offset`, returning the file and where its content ends
+fn local_entry(data: &[u8], offset: usize) -> Result<(LocalEntry<'_>, usize), String> {
+    let truncated = || format!("truncated local header at offset {offset:#x}");
+    let record = data.get(offset..).ok_or_else(truncated)?;
+    let u16_at = |at| le_u16(record, at).map(usize::from).ok_or_else(truncated);
+    let u32_at = |at| le_u32(record, at).ok_or_else(truncated);
+    let data_start = offset + LOCAL_HEADER_LEN + u16_at(26)? + u16_at(28)?;
+    let header = data.get(offset..data_start).ok_or_else(truncated)?;
+    let (mut crc32, mut compressed_size, mut uncompressed_size) =
+        (u32_at(14)?, u32_at(18)?, u32_at(22)?);
+    if compressed_size == u32::MAX || uncompressed_size == u32::MAX {
+        return Err(format!(
+            "zip64 file at offset {offset:#x} isn't recoverable"
+        ));
+    }
+    let flags = le_u16(record, 6).ok_or_else(truncated)?;
+    let data_end = if flags & DATA_DESCRIPTOR_FLAG != 0 && compressed_size == 0 {
+        // the sizes are only in the data descriptor, the content ends with the next header
+        let next = next_header(data, data_start).unwrap_or(data.len());
+        let descriptor = data
+            .get(next.saturating_sub(16)..next)
+            .filter(|descriptor| descriptor.starts_with(&DATA_DESCRIPTOR_SIGNATURE))
+            .map_or_else(|| data.get(next.saturating_sub(12)..next), |d| d.get(4..))
+            .filter(|_| next >= data_start + 12)
+            .ok_or_else(|| format!("no data descriptor for the file at offset {offset:#x}"))?;
+        crc32 = le_u32(descriptor, 0).ok_or_else(truncated)?;
+        compressed_size = le_u32(descriptor, 4).ok_or_else(truncated)?;
+        uncompressed_size = le_u32(descriptor, 8).ok_or_else(truncated)?;
+        next
+    } else {
+        data_start + usize::try_from(compressed_size).map_err(|_| truncated())?
+    };
+    if data_start + usize::try_from(compressed_size).map_err(|_| truncated())? > data.len() {
+        return Err(format!(
+            "truncated content of the file at offset {offset:#x}"
+        ));
+    }
+    let entry = LocalEntry {
+        offset: offset
+            .to_u32()
+            .ok_or_else(|| format!("file at offset {offset:#x} beyond the zip64 limit"))?,
+        header,
+        crc32,
+        compressed_size,
+        uncompressed_size,
+    };
+    Ok((entry, data_end))
+}
+
+/// Rebuild the archive in `data` from its local headers, ignoring the central directory
+///
+/// The local headers are walked from the start of the archive, skipping over the content of the files,
+/// and a new central directory is appended listing the files found. What couldn't be recovered is added to `warnings`.
+pub fn rebuild_archive(mut data: Vec<u8>, warnings: &mut Vec<String>) -> Vec<u8> {
+    let mut directory = Vec::new();
+    let mut count: u16 = 0;
+    let mut position = 0;
+    while let Some(offset) = next_header(&data, position) {
+        if !data[offset..].starts_with(&LOCAL_HEADER_SIGNATURE) {
+            // the central directory is after all the files
+            break;
+        }
+        let (entry, end) = match local_entry(&data, offset) {
+            Ok(found) => found,
+            Err(warning) => {
+                warnings.push(warning);
+                position = offset + 4;
+                continue;
+            }
+        };
+        let Some(next_count) = count.checked_add(1).filter(|c| *c < u16::MAX) else {
+            warnings.push(format!("too many files, stopped at offset {offset:#x}"));
+            break;
+        };
+        count = next_count;
+        let header = entry.header;
+        directory.extend_from_slice(&CENTRAL_HEADER_SIGNATURE);
+        // made by the same version as the one needed, on MS-DOS as the external attributes are unknown
+        directory.extend_from_slice(&header[4..6]);
+        directory.extend_from_slice(&header[4..14]);
+        directory.extend_from_slice(&entry.crc32.to_le_bytes());
+        directory.extend_from_slice(&entry.compressed_size.to_le_bytes());
+        directory.extend_from_slice(&entry.uncompressed_size.to_le_bytes());
+        // the lengths of the filename and of the extra fields, no comment, disk 0, no attributes
+        directory.extend_from_slice(&header[26..30]);
+        directory.extend_from_slice(&[0; 10]);
+        directory.extend_from_slice(&entry.offset.to_le_bytes());
+        directory.extend_from_slice(&header[LOCAL_HEADER_LEN..]);
+        position = end.max(offset + 4);
+    }
+    if count == 0 {
+        warnings.push("no local header found".to_string());
+    } else {
+        warnings.push(format!("recovered {count} files from their local headers"));
+    }
+    let directory_offset = data.len().to_u32().unwrap_or(u32::MAX);
+    let directory_len = directory.len().to_u32().unwrap_or(u32::MAX);
+    data.append(&mut directory);
+    data.extend_from_slice(&END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+    data.extend_from_slice(&[0; 4]);
+    data.extend_from_slice(&count.to_le_bytes());
+    data.extend_from_slice(&count.to_le_bytes());
+    data.extend_from_slice(&directory_len.to_le_bytes());
+    data.extend_from_slice(&directory_offset.to_le_bytes());
+    data.extend_from_slice(&[0; 2]);
+    data
+}
@@ -0,0 +1,133 @@
+use std::io;
+
+use aws_config::BehaviorVersion;
+use aws_sdk_s3::config::Region;
+use aws_sdk_s3::Client;
+use tokio::runtime::Runtime;
+
+use super::partzip::{PartialZipError, RangeReader};
+
+/// Archive stored in an S3 bucket, read with `GetObject` range requests through the AWS SDK
+///
+/// The credentials come from the standard provider chain of the SDK: the environment, the shared config and
+/// credentials files, the web identity token, the ECS container or the EC2 instance metadata, so private
+/// buckets are read without presigned URLs. Open it with [`PartialZip::from_range_reader`](crate::PartialZip::from_range_reader).
+///
+/// The requests run on a runtime owned by the reader, so it must not be used from an async task, use
+/// `tokio::task::spawn_blocking` there.
+#[derive(Debug)]
+pub struct S3RangeReader {
+    /// Client of the region of the bucket
+    client: Client,
+    /// Bucket of the archive
+    bucket: String,
+    /// Key of the archive in the bucket
+    key: String,
+    /// Size of the archive
+    len: u64,
+    /// Runtime running the requests of the SDK
+    runtime: Runtime,
+}
+
+impl S3RangeReader {
+    /// Create a new [`S3RangeReader`] for the archive at `key` in `bucket` of `region`, fetching only its size
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] if the credentials can't be found or the archive doesn't exist
+    pub fn new(bucket: &str, key: &str, region: &str) -> Result<Self, PartialZipError> {
+        Self::open(bucket, key, Some(region.to_string()))
+    }
+
+    /// Create a new [`S3RangeReader`] with the client built from `config`, for example for another endpoint
+    ///
+    /// The client is built by the reader, a client already used on another runtime would hang on its connections.
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] if the archive doesn't exist
+    pub fn from_config(
+        config: aws_sdk_s3::Config,
+        bucket: &str,
+        key: &str,
+    ) -> Result<Self, PartialZipError> {
+        Self::with_config(runtime()?, config, bucket, key)
+    }
+
+    /// Create a new [`S3RangeReader`] in `region`, or the region of the standard provider chain when it's `None`
+    pub(crate) fn open(
+        bucket: &str,
+        key: &str,
+        region: Option<String>,
+    ) -> Result<Self, PartialZipError> {
+        let runtime = runtime()?;
+        let mut loader = aws_config::defaults(BehaviorVersion::latest());
+        if let Some(region) = region {
+            loader = loader.region(Region::new(region));
+        }
+        let config = aws_sdk_s3::Config::from(&runtime.block_on(loader.load()));
+        Self::with_config(runtime, config, bucket, key)
+    }
+
+    /// Fetch the size of the archive with the client built from `config` in `runtime`, which runs all its requests
+    ///
+    /// It's taken from the `Content-Range` of the first byte, which also checks that the ranges work.
+    fn with_config(
+        runtime: Runtime,
+        config: aws_sdk_s3::Config,
+        bucket: &str,
+        key: &str,
+    ) -> Result<Self, PartialZipError> {
+        let client = {
+            let _runtime = runtime.enter();
+            Client::from_conf(config)
+        };
+        let first = client
+            .get_object()
+            .bucket(bucket)
+            .key(key)
+            .range("bytes=0-0")
+            .send();
+        let len = runtime
+            .block_on(first)
+            .map_err(|e| PartialZipError::S3Error(Box::new(e.into())))?
+            .content_range()
+            .and_then(|range| range.rsplit_once('/')?.1.parse().ok())
+            .ok_or_else(|| io::Error::other(format!("no size for s3://{bucket}/{key}")))?;
+        Ok(Self {
+            client,
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            len,
+            runtime,
+        })
+    }
+}
+
+/// Build the runtime of a reader, a single thread is enough as the reads wait for each request
+fn runtime() -> io::Result<Runtime> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+}
+
+impl RangeReader for S3RangeReader {
+    fn read_range(&mut self, start: u64, end: u64) -> io::Result<Vec<u8>> {
+        log::trace!("s3 range = {start}-{end}");
+        self.runtime.block_on(async {
+            let object = self
+                .client
+                .get_object()
+                .bucket(&self.bucket)
+                .key(&self.key)
+                .range(format!("bytes={start}-{end}"))
+                .send()
+                .await
+                .map_err(|e| io::Error::other(aws_sdk_s3::Error::from(e)))?;
+            let body = object.body.collect().await.map_err(io::Error::other)?;
+            Ok(body.into_bytes().to_vec())
+        })
+    }
+
+    fn len(&self) -> u64 {
+        self.len
+    }
+}
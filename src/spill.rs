@@ -0,0 +1,50 @@
+use std::fs::File;
+use std::io::{self, Read, Seek};
+
+use num_traits::ToPrimitive;
+
+use super::partzip::RangeReader;
+
+/// Archive read from a stream which can't seek, like a pipe, through a copy in a temporary file
+///
+/// The file has no name, created with [`tempfile::tempfile`], so no other process can open it
+/// and the system removes it once the [`SpilledStream`] is dropped, even after a crash.
+#[derive(Debug)]
+pub struct SpilledStream {
+    /// The copy of the stream
+    file: File,
+    /// Number of bytes copied
+    len: u64,
+}
+
+impl SpilledStream {
+    /// Copy `stream` to a new temporary file until its end, without keeping it in memory
+    ///
+    /// # Errors
+    /// Will return an [`io::Error`] if the stream can't be read or the file can't be written
+    pub fn new(stream: &mut dyn Read) -> io::Result<Self> {
+        let mut file = tempfile::tempfile()?;
+        let len = io::copy(stream, &mut file)?;
+        Ok(Self { file, len })
+    }
+}
+
+impl RangeReader for SpilledStream {
+    fn read_range(&mut self, start: u64, end: u64) -> io::Result<Vec<u8>> {
+        let end = end.min(self.len.saturating_sub(1));
+        let mut content = vec![
+            0;
+            (end + 1)
+                .saturating_sub(start)
+                .to_usize()
+                .unwrap_or_default()
+        ];
+        self.file.seek(io::SeekFrom::Start(start))?;
+        self.file.read_exact(&mut content)?;
+        Ok(content)
+    }
+
+    fn len(&self) -> u64 {
+        self.len
+    }
+}
@@ -0,0 +1,428 @@
+use std::io;
+
+use num_traits::ToPrimitive;
+
+use super::partzip::{
+    le_u16, le_u32, zip64_field, PartialZipError, RangeReader, CENTRAL_HEADER_SIGNATURE,
+    END_OF_CENTRAL_DIRECTORY_SIGNATURE,
+};
+
+/// Size of the end of central directory record without the comment
+const END_LEN: usize = 22;
+/// Signature of the zip64 end of central directory record
+const ZIP64_END_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x06, 0x06];
+/// Size of the zip64 end of central directory record without the extensible data
+const ZIP64_END_LEN: usize = 56;
+/// Signature of the zip64 end of central directory locator
+const ZIP64_LOCATOR_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x06, 0x07];
+/// Size of the zip64 end of central directory locator
+const ZIP64_LOCATOR_LEN: usize = 20;
+/// Size of a central header without the filename, the extra fields and the comment
+const CENTRAL_HEADER_LEN: usize = 46;
+
+/// The end of central directory records of the last volume of a split archive
+struct SplitEnd {
+    /// Number of volumes
+    disks: u32,
+    /// Volume where the central directory starts
+    directory_disk: u32,
+    /// Offset of the central directory in its volume
+    directory_offset: u64,
+    /// Size of the whole central directory
+    directory_size: u64,
+    /// Number of files in the whole archive
+    entries: u64,
+    /// Comment of the archive
+    comment: Vec<u8>,
+    /// Volume and offset of the zip64 end of central directory record, which has the actual values
+    zip64: Option<(u32, u64)>,
+}
+
+/// Error for a split archive whose structures don't make sense
+fn invalid(what: &str) -> PartialZipError {
+    PartialZipError::InvalidArchive(format!("split archive: {what}"))
+}
+
+/// Read the end of the volume `last`, where the end of central directory records are
+fn read_tail(last: &mut dyn RangeReader) -> io::Result<Vec<u8>> {
+    let len = last.len();
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    let tail_len = (END_LEN + usize::from(u16::MAX) + ZIP64_LOCATOR_LEN)
+        .to_u64()
+        .unwrap_or(u64::MAX);
+    last.read_range(len.saturating_sub(tail_len), len - 1)
+}
+
+/// Parse the end of central directory records at the end of `tail`
+fn parse_end(tail: &[u8]) -> Result<SplitEnd, PartialZipError> {
+    let last = tail
+        .len()
+        .checked_sub(END_LEN)
+        .ok_or_else(|| invalid("no end of central directory record"))?;
+    // the last record whose comment reaches exactly the end of the volume
+    let start = (0..=last)
+        .rev()
+        .find(|start| {
+            let record = &tail[*start..];
+            record.starts_with(&END_OF_CENTRAL_DIRECTORY_SIGNATURE)
+                && le_u16(record, 20).map(|len| END_LEN + usize::from(len)) == Some(record.len())
+        })
+        .ok_or_else(|| invalid("no end of central directory record"))?;
+    let record = &tail[start..];
+    let u16_at = |at| le_u16(record, at).map(u32::from);
+    let (
+        Some(disk),
+        Some(directory_disk),
+        Some(entries),
+        Some(directory_size),
+        Some(directory_offset),
+    ) = (
+        u16_at(4),
+        u16_at(6),
+        u16_at(10),
+        le_u32(record, 12),
+        le_u32(record, 16),
+    )
+    else {
+        return Err(invalid("truncated end of central directory record"));
+    };
+    let mut end = SplitEnd {
+        disks: disk + 1,
+        directory_disk,
+        directory_offset: directory_offset.into(),
+        directory_size: directory_size.into(),
+        entries: entries.into(),
+        comment: record[END_LEN..].to_vec(),
+        zip64: None,
+    };
+    let locator = start
+        .checked_sub(ZIP64_LOCATOR_LEN)
+        .and_then(|locator| tail.get(locator..start))
+        .filter(|locator| locator.starts_with(&ZIP64_LOCATOR_SIGNATURE));
+    if let Some(locator) = locator {
+        let offset = locator
+            .get(8..16)
+            .and_then(|offset| offset.try_into().ok())
+            .map(u64::from_le_bytes);
+        let (Some(zip64_disk), Some(offset), Some(disks)) =
+            (le_u32(locator, 4), offset, le_u32(locator, 16))
+        else {
+            return Err(invalid("truncated zip64 locator"));
+        };
+        end.disks = disks;
+        end.zip64 = Some((zip64_disk, offset));
+    }
+    Ok(end)
+}
+
+/// Read a little endian `u64` at `at` in `data`
+fn le_u64(data: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
+
+/// Returns the number of files and the size of the central directory of an archive which isn't split, from its end records
+///
+/// Only the end of the archive is read, so an archive announcing too many files can be refused before parsing its directory.
+///
+/// # Errors
+/// Will return a [`PartialZipError`] if the end of central directory records can't be read
+pub fn directory_counts(archive: &mut dyn RangeReader) -> Result<(u64, u64), PartialZipError> {
+    let end = parse_end(&read_tail(archive)?)?;
+    let Some((_, offset)) = end.zip64 else {
+        return Ok((end.entries, end.directory_size));
+    };
+    let record = archive.read_range(
+        offset,
+        offset + ZIP64_END_LEN.to_u64().unwrap_or_default() - 1,
+    )?;
+    match (
+        record.starts_with(&ZIP64_END_SIGNATURE),
+        le_u64(&record, 32),
+        le_u64(&record, 40),
+    ) {
+        (true, Some(entries), Some(directory_size)) => Ok((entries, directory_size)),
+        _ => Err(invalid("invalid zip64 end of central directory record")),
+    }
+}
+
+/// Returns the number of volumes of a split archive from the end of its last volume, 1 for an archive which isn't split
+///
+/// # Errors
+/// Will return a [`PartialZipError`] if the end of central directory records can't be read
+pub fn volume_count(last: &mut dyn RangeReader) -> Result<u32, PartialZipError> {
+    Ok(parse_end(&read_tail(last)?)?.disks)
+}
+
+/// The volumes of a split archive read as a single archive
+///
+/// The volumes are concatenated up to the central directory, which is replaced by one with the offsets in the
+/// concatenation instead of the offsets in the volumes, followed by zip64 end of central directory records.
+pub struct SplitVolumes {
+    /// The volumes in order, with the offset where they start in the concatenation
+    volumes: Vec<(u64, Box<dyn RangeReader + Send>)>,
+    /// Offset of the central directory in the concatenation, where the volumes stop being read
+    directory_start: u64,
+    /// The rewritten central directory and end records
+    directory: Vec<u8>,
+}
+
+impl std::fmt::Debug for SplitVolumes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SplitVolumes")
+            .field("volumes", &self.volumes.len())
+            .field("directory_start", &self.directory_start)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SplitVolumes {
+    /// Read the central directory of the split archive of `volumes` and rewrite it for their concatenation
+    ///
+    /// # Errors
+    /// Will return a [`PartialZipError`] depending on what happened,
+    /// [`PartialZipError::InvalidArchive`] if the volumes don't match the central directory
+    pub fn new(volumes: Vec<Box<dyn RangeReader + Send>>) -> Result<Self, PartialZipError> {
+        let mut start = 0;
+        let mut split = Self {
+            volumes: volumes
+                .into_iter()
+                .map(|volume| {
+                    let volume_start = start;
+                    start += volume.len();
+                    (volume_start, volume)
+                })
+                .collect(),
+            directory_start: 0,
+            directory: Vec::new(),
+        };
+        let last = split
+            .volumes
+            .last_mut()
+            .ok_or_else(|| invalid("no volume"))?;
+        let mut end = parse_end(&read_tail(last.1.as_mut())?)?;
+        if end.disks.to_usize() != Some(split.volumes.len()) {
+            return Err(invalid(&format!(
+                "{} volumes for {} disks",
+                split.volumes.len(),
+                end.disks
+            )));
+        }
+        if let Some((disk, offset)) = end.zip64 {
+            let record_start = split.disk_start(disk)? + offset;
+            let record = split.read_volumes(
+                record_start,
+                record_start + ZIP64_END_LEN.to_u64().unwrap_or_default() - 1,
+            )?;
+            let (true, Some(directory_disk), Some(directory_size), Some(directory_offset)) = (
+                record.starts_with(&ZIP64_END_SIGNATURE),
+                le_u32(&record, 20),
+                le_u64(&record, 40),
+                le_u64(&record, 48),
+            ) else {
+                return Err(invalid("invalid zip64 end of central directory record"));
+            };
+            end.directory_disk = directory_disk;
+            end.directory_size = directory_size;
+            end.directory_offset = directory_offset;
+        }
+        split.directory_start = split.disk_start(end.directory_disk)? + end.directory_offset;
+        let directory = if end.directory_size == 0 {
+            Vec::new()
+        } else {
+            split.read_volumes(
+                split.directory_start,
+                split.directory_start + end.directory_size - 1,
+            )?
+        };
+        split.directory = split.rewrite_directory(&directory, &end.comment)?;
+        Ok(split)
+    }
+
+    /// Returns the offset where the volume of `disk` starts in the concatenation
+    fn disk_start(&self, disk: u32) -> Result<u64, PartialZipError> {
+        disk.to_usize()
+            .and_then(|disk| self.volumes.get(disk))
+            .map(|(start, _)| *start)
+            .ok_or_else(|| invalid(&format!("no volume for the disk {disk}")))
+    }
+
+    /// Read the bytes from `start` to `end` (included) of the concatenation of the volumes
+    fn read_volumes(&mut self, start: u64, end: u64) -> io::Result<Vec<u8>> {
+        let mut content = Vec::new();
+        for (volume_start, volume) in &mut self.volumes {
+            let volume_end = *volume_start + volume.len();
+            if volume_end <= start || *volume_start > end || volume.is_empty() {
+                continue;
+            }
+            let from = start.max(*volume_start) - *volume_start;
+            let to = end.min(volume_end - 1) - *volume_start;
+            content.extend(volume.read_range(from, to)?);
+        }
+        if content.len().to_u64() != Some(end - start + 1) {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("range {start}-{end} beyond the volumes"),
+            ));
+        }
+        Ok(content)
+    }
+
+    /// Rewrite the central headers of `directory` with the offsets in the concatenation, always in the zip64 extra field,
+    /// and append the end records pointing to it
+    fn rewrite_directory(
+        &self,
+        directory: &[u8],
+        comment: &[u8],
+    ) -> Result<Vec<u8>, PartialZipError> {
+        let mut rewritten = Vec::new();
+        let mut entries: u64 = 0;
+        let mut record = directory;
+        while record.starts_with(&CENTRAL_HEADER_SIGNATURE) {
+            let len = self.rewrite_header(record, &mut rewritten)?;
+            record = &record[len..];
+            entries += 1;
+        }
+        let directory_len = rewritten.len().to_u64().unwrap_or(u64::MAX);
+        let zip64_start = self.directory_start + directory_len;
+        rewritten.extend_from_slice(&ZIP64_END_SIGNATURE);
+        rewritten
+            .extend_from_slice(&(ZIP64_END_LEN.to_u64().unwrap_or_default() - 12).to_le_bytes());
+        // made by and needed to extract zip64, on disk 0 with the directory
+        rewritten.extend_from_slice(&[45, 0, 45, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        for field in [entries, entries, directory_len, self.directory_start] {
+            rewritten.extend_from_slice(&field.to_le_bytes());
+        }
+        rewritten.extend_from_slice(&ZIP64_LOCATOR_SIGNATURE);
+        rewritten.extend_from_slice(&0_u32.to_le_bytes());
+        rewritten.extend_from_slice(&zip64_start.to_le_bytes());
+        rewritten.extend_from_slice(&1_u32.to_le_bytes());
+        // the actual values when they fit, for the readers ignoring the zip64 records
+        let entries = entries.to_u16().unwrap_or(u16::MAX).to_le_bytes();
+        rewritten.extend_from_slice(&END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        rewritten.extend_from_slice(&[0, 0, 0, 0]);
+        rewritten.extend_from_slice(&entries);
+        rewritten.extend_from_slice(&entries);
+        rewritten.extend_from_slice(&directory_len.to_u32().unwrap_or(u32::MAX).to_le_bytes());
+        rewritten.extend_from_slice(
+            &self
+                .directory_start
+                .to_u32()
+                .unwrap_or(u32::MAX)
+                .to_le_bytes(),
+        );
+        rewritten.extend_from_slice(&comment.len().to_u16().unwrap_or_default().to_le_bytes());
+        rewritten.extend_from_slice(comment);
+        Ok(rewritten)
+    }
+
+    /// Append the central header at the start of `record` to `rewritten` with its offset in the concatenation,
+    /// returning the length of the original header
+    fn rewrite_header(
+        &self,
+        record: &[u8],
+        rewritten: &mut Vec<u8>,
+    ) -> Result<usize, PartialZipError> {
+        let truncated = || invalid("truncated central header");
+        let len_at = |at| le_u16(record, at).map(usize::from).ok_or_else(truncated);
+        let (name_len, extra_len, comment_len) = (len_at(28)?, len_at(30)?, len_at(32)?);
+        let extra_start = CENTRAL_HEADER_LEN + name_len;
+        let extra = record
+            .get(extra_start..extra_start + extra_len)
+            .ok_or_else(truncated)?;
+        // the fields at their maximum are in the zip64 extra field, in this order
+        let zip64 = zip64_field(extra).unwrap_or_default();
+        let mut at = 0;
+        let mut sizes = Vec::new();
+        for field in [24, 20] {
+            if le_u32(record, field) == Some(u32::MAX) {
+                sizes.extend_from_slice(zip64.get(at..at + 8).ok_or_else(truncated)?);
+                at += 8;
+            }
+        }
+        let offset = match le_u32(record, 42).ok_or_else(truncated)? {
+            u32::MAX => {
+                let offset = zip64.get(at..at + 8).ok_or_else(truncated)?;
+                at += 8;
+                u64::from_le_bytes(offset.try_into().map_err(|_| truncated())?)
+            }
+            offset => offset.into(),
+        };
+        let disk = match le_u16(record, 34).ok_or_else(truncated)? {
+            u16::MAX => le_u32(zip64, at).ok_or_else(truncated)?,
+            disk => disk.into(),
+        };
+        let offset = self.disk_start(disk)? + offset;
+        let mut new_extra = Vec::new();
+        new_extra.extend_from_slice(&1_u16.to_le_bytes());
+        new_extra.extend_from_slice(&(sizes.len() + 8).to_u16().unwrap_or_default().to_le_bytes());
+        new_extra.extend_from_slice(&sizes);
+        new_extra.extend_from_slice(&offset.to_le_bytes());
+        // keep the other extra fields as they are
+        let mut others = extra;
+        while let [id0, id1, len0, len1, rest @ ..] = others {
+            let len = usize::from(u16::from_le_bytes([*len0, *len1])).min(rest.len());
+            if u16::from_le_bytes([*id0, *id1]) != 0x0001 {
+                new_extra.extend_from_slice(&others[..4 + len]);
+            }
+            others = &rest[len..];
+        }
+        let mut header = record
+            .get(..CENTRAL_HEADER_LEN)
+            .ok_or_else(truncated)?
+            .to_vec();
+        header[30..32].copy_from_slice(
+            &new_extra
+                .len()
+                .to_u16()
+                .ok_or_else(|| invalid("extra fields too long"))?
+                .to_le_bytes(),
+        );
+        header[34..36].copy_from_slice(&0_u16.to_le_bytes());
+        header[42..46].copy_from_slice(&u32::MAX.to_le_bytes());
+        rewritten.extend_from_slice(&header);
+        rewritten.extend_from_slice(
+            record
+                .get(CENTRAL_HEADER_LEN..extra_start)
+                .ok_or_else(truncated)?,
+        );
+        rewritten.extend_from_slice(&new_extra);
+        let comment_start = extra_start + extra_len;
+        rewritten.extend_from_slice(
+            record
+                .get(comment_start..comment_start + comment_len)
+                .ok_or_else(truncated)?,
+        );
+        Ok(comment_start + comment_len)
+    }
+}
+
+impl RangeReader for SplitVolumes {
+    fn read_range(&mut self, start: u64, end: u64) -> io::Result<Vec<u8>> {
+        let mut content = if start < self.directory_start {
+            self.read_volumes(start, end.min(self.directory_start - 1))?
+        } else {
+            Vec::new()
+        };
+        if end >= self.directory_start {
+            let from = start.saturating_sub(self.directory_start).to_usize();
+            let to = (end - self.directory_start).to_usize();
+            let directory = from
+                .zip(to)
+                .and_then(|(from, to)| self.directory.get(from..=to))
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        format!("range {start}-{end} beyond the archive"),
+                    )
+                })?;
+            content.extend_from_slice(directory);
+        }
+        Ok(content)
+    }
+
+    fn len(&self) -> u64 {
+        self.directory_start + self.directory.len().to_u64().unwrap_or_default()
+    }
+}
@@ -8,12 +8,19 @@ mod utils_tests {
             "https://sub.test.com",
             "ftp://ftp.test.com",
             "file://localhost/home/test/1.zip",
+            "http://[::1]:8080/test.zip",
+            "https://[2001:db8::1]/test.zip",
+            "http://127.0.0.1:65535/test.zip",
         ];
         let invalid_urls = [
             "asdasd://",
             "js:",
             "smb://storage.test.com",
+            #[cfg(not(feature = "s3"))]
+            "s3://bucket/key.zip",
             "not parsable URL",
+            "http://[::1/test.zip",
+            "http://127.0.0.1:65536/test.zip",
         ];
         for url in valid_urls {
             assert!(
@@ -27,6 +34,189 @@ mod utils_tests {
                 "{url} should be a invalid url"
             );
         }
+        for scheme in crate::utils::SUPPORTED_SCHEMES {
+            let url = format!("{scheme}://localhost/test.zip");
+            assert!(
+                crate::utils::url_is_valid(&url),
+                "{url} should be a valid url"
+            );
+        }
+    }
+
+    #[cfg(feature = "s3")]
+    #[test]
+    /// Test the S3 URLs, accepted and split in their bucket and key only with the s3 feature
+    pub fn s3_url_tests() {
+        use crate::utils::{s3_location, url_is_valid};
+
+        assert!(url_is_valid("s3://bucket/key.zip"));
+        assert_eq!(
+            s3_location("s3://bucket/dir/my%20archive.zip"),
+            Some(("bucket".to_string(), "dir/my archive.zip".to_string()))
+        );
+        for url in [
+            "s3://bucket",
+            "s3://bucket/",
+            "s3:///key.zip",
+            "https://bucket/key.zip",
+        ] {
+            assert_eq!(s3_location(url), None, "{url} has no S3 location");
+        }
+        // curl can't read them
+        assert!(matches!(
+            crate::PartialReader::new(&"s3://bucket/key.zip"),
+            Err(crate::PartialZipError::InvalidUrl)
+        ));
+    }
+
+    #[test]
+    /// Test the parsing of the total size in the `Content-Range` headers
+    pub fn content_range_tests() {
+        use crate::utils::content_range_total;
+
+        assert_eq!(
+            content_range_total("Content-Range: bytes 0-0/368\r\n"),
+            Some(368)
+        );
+        assert_eq!(content_range_total("content-range:bytes 0-0/1"), Some(1));
+        assert_eq!(content_range_total("Content-Range: bytes 0-0/*"), None);
+        assert_eq!(content_range_total("Content-Length: 368"), None);
+        assert_eq!(content_range_total("HTTP/1.1 206 Partial Content"), None);
+    }
+
+    #[test]
+    /// Test the choice of the value sent as `If-Range`
+    pub fn range_validator_tests() {
+        use crate::utils::range_validator;
+
+        let headers = |lines: &[&str]| lines.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            range_validator(&headers(&[
+                "HTTP/1.1 302 Found\r\n",
+                "ETag: \"redirect\"\r\n",
+                "HTTP/1.1 200 OK\r\n",
+                "Last-Modified: Fri, 12 Aug 2022 15:24:30 GMT\r\n",
+                "etag: \"archive\"\r\n",
+            ])),
+            Some("\"archive\"".to_string())
+        );
+        assert_eq!(
+            range_validator(&headers(&[
+                "HTTP/1.1 200 OK\r\n",
+                "ETag: W/\"weak\"\r\n",
+                "Last-Modified: Fri, 12 Aug 2022 15:24:30 GMT\r\n",
+            ])),
+            Some("Fri, 12 Aug 2022 15:24:30 GMT".to_string())
+        );
+        assert_eq!(
+            range_validator(&headers(&["HTTP/1.1 200 OK\r\n", "ETag: W/\"weak\"\r\n"])),
+            None
+        );
+        assert_eq!(range_validator(&headers(&["ETag: \"archive\"\r\n"])), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    /// Test that only `file://` URLs are mapped to local paths
+    pub fn local_path_tests() {
+        assert_eq!(
+            crate::utils::local_path("file://localhost/home/test/1.zip"),
+            Some(std::path::PathBuf::from("/home/test/1.zip"))
+        );
+        assert_eq!(
+            crate::utils::local_path("file:///home/test/1.zip"),
+            Some(std::path::PathBuf::from("/home/test/1.zip"))
+        );
+        assert_eq!(crate::utils::local_path("http://www.test.com/1.zip"), None);
+        assert_eq!(crate::utils::local_path("not parsable URL"), None);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    /// Test that the `file://` URLs are mapped to paths with drive letters on Windows
+    pub fn local_path_windows_tests() {
+        for url in [
+            "file:///C:/Users/test/1.zip",
+            "file://localhost/C:/Users/test/1.zip",
+        ] {
+            assert_eq!(
+                crate::utils::local_path(url).map(|path| path.display().to_string()),
+                Some(r"C:\Users\test\1.zip".to_string()),
+                "{url} should be a local path"
+            );
+        }
+        assert_eq!(
+            crate::utils::local_path("file:///C:/Program%20Files/1.zip"),
+            Some(std::path::PathBuf::from(r"C:\Program Files\1.zip"))
+        );
+    }
+
+    #[test]
+    /// Test the encoding of the names in CP437
+    pub fn to_cp437_tests() {
+        assert_eq!(
+            crate::utils::to_cp437("abc/1.txt"),
+            Some(b"abc/1.txt".to_vec())
+        );
+        assert_eq!(crate::utils::to_cp437("café"), Some(b"caf\x82".to_vec()));
+        assert_eq!(crate::utils::to_cp437("├»"), Some(vec![0xc3, 0xaf]));
+        assert_eq!(crate::utils::to_cp437("\u{a0}"), Some(vec![0xff]));
+        assert_eq!(crate::utils::to_cp437("€"), None);
+    }
+
+    #[test]
+    /// Test the comparison of the origins of the URLs
+    pub fn same_origin_tests() {
+        use crate::utils::same_origin;
+        assert!(same_origin(
+            "https://www.test.com/1.zip",
+            "https://www.test.com:443/dir/2.zip"
+        ));
+        assert!(!same_origin(
+            "https://www.test.com/1.zip",
+            "http://www.test.com/1.zip"
+        ));
+        assert!(!same_origin(
+            "https://www.test.com/1.zip",
+            "https://cdn.test.com/1.zip"
+        ));
+        assert!(!same_origin(
+            "http://127.0.0.1:8080/1.zip",
+            "http://127.0.0.1:8081/1.zip"
+        ));
+        assert!(!same_origin("https://www.test.com/1.zip", "not a url"));
+    }
+
+    #[test]
+    /// Test the URLs of the volumes of a split archive
+    pub fn split_volume_url_tests() {
+        use crate::utils::split_volume_url;
+        assert_eq!(
+            split_volume_url("https://www.test.com/dir/archive.zip?token=1", 2),
+            Some("https://www.test.com/dir/archive.z02?token=1".to_string())
+        );
+        assert_eq!(
+            split_volume_url("https://www.test.com/ARCHIVE.ZIP", 12),
+            Some("https://www.test.com/ARCHIVE.z12".to_string())
+        );
+        assert_eq!(split_volume_url("https://www.test.com/archive", 1), None);
+        assert_eq!(split_volume_url("not a url", 1), None);
+    }
+
+    #[test]
+    /// Test that entry paths escaping the destination are rejected
+    pub fn entry_path_tests() {
+        assert_eq!(
+            crate::utils::entry_path("dir/./file.txt"),
+            Some(std::path::PathBuf::from("dir/file.txt"))
+        );
+        for name in ["../evil.txt", "dir/../../evil.txt", "/etc/passwd", "", "./"] {
+            assert_eq!(
+                crate::utils::entry_path(name),
+                None,
+                "{name} should be rejected"
+            );
+        }
     }
 }
 
@@ -34,13 +224,28 @@ mod utils_tests {
 mod partzip_tests {
     use actix_files as fs;
     use chrono::NaiveDateTime;
-    use std::{net::TcpListener, path::PathBuf};
+    use std::{
+        future::ready,
+        io::{BufRead, BufReader, Write},
+        net::{TcpListener, TcpStream},
+        path::PathBuf,
+        sync::{
+            atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    };
     use url::Url;
     use zip::result::ZipError;
 
-    use actix_web::{App, HttpResponse, HttpServer};
+    use actix_web::{http::Method, App, HttpRequest, HttpResponse, HttpServer};
 
-    use crate::partzip::{PartialZip, PartialZipError, PartialZipFileDetailed};
+    use crate::partzip::{
+        write_new_file, DownloadPlan, FtpMode, HttpVersion, PartialReader, PartialZip,
+        PartialZipClient, PartialZipCompressionMethod, PartialZipError, PartialZipFileDetailed,
+        PartialZipFileExtra, PartialZipFileFull, Preserve, RangeReader, ReadStats, RedirectPolicy,
+        ResumeStatus, SortKey,
+    };
 
     use anyhow::Result;
 
@@ -48,6 +253,302 @@ mod partzip_tests {
         address: Url,
     }
 
+    /// Serve the test archive only if the request carries the `header` with the given `value`
+    fn serve_if_header(req: &HttpRequest, header: &str, value: &str) -> HttpResponse {
+        if !req.headers().get(header).is_some_and(|h| h == value) {
+            return HttpResponse::Unauthorized().finish();
+        }
+        serve_test_zip(req)
+    }
+
+    /// Serve the test archive, or its range from a `Range` header like `bytes 0-99`, rejecting the standard ones
+    fn serve_quirky_range(req: &HttpRequest) -> HttpResponse {
+        let Some(range) = req.headers().get("Range").and_then(|h| h.to_str().ok()) else {
+            return serve_test_zip(req);
+        };
+        let content = std::fs::read("./testdata/test.zip").unwrap_or_default();
+        let bounds = range.strip_prefix("bytes ").and_then(|r| {
+            let (start, end) = r.split_once('-')?;
+            let start: usize = start.parse().ok()?;
+            let end: usize = end.parse().ok()?;
+            Some((start, end.min(content.len().checked_sub(1)?)))
+        });
+        match bounds {
+            Some((start, end)) if start <= end => HttpResponse::PartialContent()
+                .append_header((
+                    "Content-Range",
+                    format!("bytes {start}-{end}/{}", content.len()),
+                ))
+                .body(content[start..=end].to_vec()),
+            _ => HttpResponse::BadRequest().finish(),
+        }
+    }
+
+    /// Offset of the second file of the ZIP64 archive, past the 4 GiB limit of the plain zip format
+    #[cfg(unix)]
+    const ZIP64_OFFSET: u64 = 0x0001_4000_0000;
+
+    /// Write a ZIP64 archive with a file at the beginning and one past [`ZIP64_OFFSET`], leaving a hole between them
+    ///
+    /// The file is sparse, so the hole doesn't take any disk space.
+    #[cfg(unix)]
+    fn write_zip64(path: &std::path::Path) -> Result<()> {
+        use std::io::{Seek, SeekFrom, Write};
+
+        let files: [(&[u8], &[u8], u64); 2] = [
+            (b"first.txt", b"before the hole\n", 0),
+            (b"second.txt", b"after the hole\n", ZIP64_OFFSET),
+        ];
+        let mut zip = std::fs::File::create(path)?;
+        let mut central = Vec::new();
+        for (name, content, offset) in files {
+            let crc = crc32fast::hash(content);
+            let len = u32::try_from(content.len())?;
+            let name_len = u16::try_from(name.len())?;
+            zip.seek(SeekFrom::Start(offset))?;
+            zip.write_all(&0x0403_4b50_u32.to_le_bytes())?;
+            // version, flags, method, time, date
+            zip.write_all(&[45, 0, 0, 0, 0, 0, 0, 0, 0x21, 0x5a])?;
+            for field in [crc, len, len] {
+                zip.write_all(&field.to_le_bytes())?;
+            }
+            zip.write_all(&name_len.to_le_bytes())?;
+            zip.write_all(&0_u16.to_le_bytes())?;
+            zip.write_all(name)?;
+            zip.write_all(content)?;
+            // the offset is in the ZIP64 extra field when it doesn't fit in 32 bits
+            let (offset32, extra) = u32::try_from(offset).map_or_else(
+                |_| {
+                    let mut extra = vec![1, 0, 8, 0];
+                    extra.extend_from_slice(&offset.to_le_bytes());
+                    (u32::MAX, extra)
+                },
+                |offset| (offset, Vec::new()),
+            );
+            central.extend_from_slice(&0x0201_4b50_u32.to_le_bytes());
+            central.extend_from_slice(&[45, 3, 45, 0, 0, 0, 0, 0, 0, 0, 0x21, 0x5a]);
+            for field in [crc, len, len] {
+                central.extend_from_slice(&field.to_le_bytes());
+            }
+            central.extend_from_slice(&name_len.to_le_bytes());
+            central.extend_from_slice(&u16::try_from(extra.len())?.to_le_bytes());
+            // comment length, disk, internal and external attributes
+            central.extend_from_slice(&[0; 10]);
+            central.extend_from_slice(&offset32.to_le_bytes());
+            central.extend_from_slice(name);
+            central.extend_from_slice(&extra);
+        }
+        let central_offset = zip.stream_position()?;
+        let eocd64_offset = central_offset + u64::try_from(central.len())?;
+        zip.write_all(&central)?;
+        // ZIP64 end of central directory record
+        zip.write_all(&0x0606_4b50_u32.to_le_bytes())?;
+        zip.write_all(&44_u64.to_le_bytes())?;
+        zip.write_all(&[45, 3, 45, 0, 0, 0, 0, 0, 0, 0, 0, 0])?;
+        for field in [2, 2, u64::try_from(central.len())?, central_offset] {
+            zip.write_all(&field.to_le_bytes())?;
+        }
+        // ZIP64 end of central directory locator
+        zip.write_all(&0x0706_4b50_u32.to_le_bytes())?;
+        zip.write_all(&0_u32.to_le_bytes())?;
+        zip.write_all(&eocd64_offset.to_le_bytes())?;
+        zip.write_all(&1_u32.to_le_bytes())?;
+        // end of central directory, pointing to the ZIP64 one
+        zip.write_all(&0x0605_4b50_u32.to_le_bytes())?;
+        zip.write_all(&[0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff])?;
+        zip.write_all(&[0xff; 8])?;
+        zip.write_all(&[0, 0])?;
+        Ok(())
+    }
+
+    /// Empty body announcing the length of the content, to answer a HEAD request
+    struct HeadBody(u64);
+
+    impl actix_web::body::MessageBody for HeadBody {
+        type Error = std::convert::Infallible;
+
+        fn size(&self) -> actix_web::body::BodySize {
+            actix_web::body::BodySize::Sized(self.0)
+        }
+
+        fn poll_next(
+            self: std::pin::Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Option<Result<actix_web::web::Bytes, Self::Error>>> {
+            std::task::Poll::Ready(None)
+        }
+    }
+
+    /// Serve the test archive
+    fn serve_test_zip(req: &HttpRequest) -> HttpResponse {
+        fs::NamedFile::open("./testdata/test.zip").map_or_else(
+            |_| HttpResponse::NotFound().finish(),
+            |f| f.into_response(req),
+        )
+    }
+
+    /// Routes serving the test archive only to the requests carrying the right credentials, cookies or headers
+    fn restricted_routes(cfg: &mut actix_web::web::ServiceConfig) {
+        cfg.service(
+            actix_web::web::resource("/auth/test.zip").to(|req: HttpRequest| {
+                // user:pass
+                ready(serve_if_header(&req, "Authorization", "Basic dXNlcjpwYXNz"))
+            }),
+        )
+        .service(
+            actix_web::web::resource("/stalled/test.zip").to(|req: HttpRequest| async move {
+                // the archive is found, but its content never comes
+                if req.method() == Method::GET {
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                }
+                serve_test_zip(&req)
+            }),
+        )
+        .service(
+            actix_web::web::resource("/changing/test.zip").to(|req: HttpRequest| {
+                // the archive is replaced right after being probed
+                ready(if req.method() == Method::HEAD {
+                    serve_test_zip(&req)
+                } else if req.headers().contains_key("If-Range") {
+                    std::fs::read("./testdata/test.zip").map_or_else(
+                        |_| HttpResponse::NotFound().finish(),
+                        |content| {
+                            HttpResponse::Ok()
+                                .append_header(("ETag", "\"replaced\""))
+                                .body(content)
+                        },
+                    )
+                } else {
+                    HttpResponse::BadRequest().finish()
+                })
+            }),
+        )
+        .service(
+            actix_web::web::resource("/apikey/test.zip")
+                .to(|req: HttpRequest| ready(serve_if_header(&req, "X-Api-Key", "secret"))),
+        )
+        .service(
+            actix_web::web::resource("/useragent/test.zip").to(|req: HttpRequest| {
+                ready(serve_if_header(&req, "User-Agent", "partialzip-test"))
+            }),
+        )
+        .service(actix_web::web::resource("/cookie/login").to(|| async {
+            HttpResponse::Found()
+                .append_header(("Set-Cookie", "session=abc; Path=/"))
+                .append_header(("Location", "/cookie/test.zip"))
+                .finish()
+        }))
+        .service(
+            actix_web::web::resource("/cookie/test.zip")
+                .to(|req: HttpRequest| ready(serve_if_header(&req, "Cookie", "session=abc"))),
+        )
+        .service(
+            actix_web::web::resource("/proxied/test.zip").to(|req: HttpRequest| {
+                // the host doesn't exist, the request can only come through the proxy
+                ready(if req.uri().host() == Some("partialzip.invalid") {
+                    serve_if_header(&req, "Proxy-Authorization", "Basic dXNlcjpwYXNz")
+                } else {
+                    HttpResponse::NotFound().finish()
+                })
+            }),
+        )
+        .service(
+            actix_web::web::resource("/identity/test.zip").to(|req: HttpRequest| {
+                // compress the archive unless the client asks for it as it is
+                ready(
+                    if req
+                        .headers()
+                        .get("Accept-Encoding")
+                        .is_some_and(|h| h == "identity")
+                    {
+                        serve_test_zip(&req)
+                    } else {
+                        HttpResponse::Ok()
+                            .append_header(("Content-Encoding", "gzip"))
+                            .body(&b"\x1f\x8b\x08\x00 compressed archive"[..])
+                    },
+                )
+            }),
+        )
+        .service(
+            actix_web::web::resource("/quirky/test.zip")
+                .to(|req: HttpRequest| ready(serve_quirky_range(&req))),
+        );
+    }
+
+    /// Routes redirecting to the test archive, on another host or behind the credentials
+    fn redirect_routes(cfg: &mut actix_web::web::ServiceConfig) {
+        cfg.service(
+            actix_web::web::resource("/cross/test.zip").to(|req: HttpRequest| {
+                // same server, but another origin
+                let port = req.app_config().local_addr().port();
+                ready(
+                    HttpResponse::Found()
+                        .append_header((
+                            "Location",
+                            format!("http://localhost:{port}/files/test.zip"),
+                        ))
+                        .finish(),
+                )
+            }),
+        )
+        .service(actix_web::web::resource("/auth/redirect").to(|| async {
+            HttpResponse::Found()
+                .append_header(("Location", "/auth/test.zip"))
+                .finish()
+        }))
+        .service(
+            actix_web::web::resource("/auth/cross").to(|req: HttpRequest| {
+                // same server, but another host
+                let port = req.app_config().local_addr().port();
+                ready(
+                    HttpResponse::Found()
+                        .append_header((
+                            "Location",
+                            format!("http://localhost:{port}/auth/test.zip"),
+                        ))
+                        .finish(),
+                )
+            }),
+        );
+    }
+
+    /// Body of unknown size, sent with the chunked transfer encoding and without a `Content-Length`
+    struct ChunkedBody(Option<actix_web::web::Bytes>);
+
+    impl actix_web::body::MessageBody for ChunkedBody {
+        type Error = std::convert::Infallible;
+
+        fn size(&self) -> actix_web::body::BodySize {
+            actix_web::body::BodySize::Stream
+        }
+
+        fn poll_next(
+            self: std::pin::Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Option<Result<actix_web::web::Bytes, Self::Error>>> {
+            std::task::Poll::Ready(self.get_mut().0.take().map(Ok))
+        }
+    }
+
+    /// Body which never ends, sent with the chunked transfer encoding
+    struct EndlessBody;
+
+    impl actix_web::body::MessageBody for EndlessBody {
+        type Error = std::convert::Infallible;
+
+        fn size(&self) -> actix_web::body::BodySize {
+            actix_web::body::BodySize::Stream
+        }
+
+        fn poll_next(
+            self: std::pin::Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Option<Result<actix_web::web::Bytes, Self::Error>>> {
+            std::task::Poll::Ready(Some(Ok(actix_web::web::Bytes::from_static(&[0; 4096]))))
+        }
+    }
+
     /// Spawn the test server which hosts the test files
     fn spawn_server() -> Result<TestServer> {
         // Bind to a random local port
@@ -55,14 +556,94 @@ mod partzip_tests {
         let port = listener.local_addr()?.port();
         // Local server address
         let address = Url::parse(&format!("http://127.0.0.1:{port}"))?;
+        let flaky_requests = Arc::new(AtomicUsize::new(0));
         let server = HttpServer::new(move || {
+            let flaky_requests = flaky_requests.clone();
             App::new()
                 .service(fs::Files::new("/files/", "./testdata").show_files_listing())
+                .configure(restricted_routes)
+                .configure(redirect_routes)
                 .service(actix_web::web::resource("/redirect").to(|| async {
                     HttpResponse::Found()
                         .append_header(("Location", "/files/test.zip"))
                         .finish()
                 }))
+                .service(
+                    actix_web::web::resource("/tmp/{name}").to(|req: HttpRequest| {
+                        let name = req.match_info().get("name").unwrap_or_default();
+                        let path = std::env::temp_dir().join(name);
+                        ready(match std::fs::metadata(&path) {
+                            // the file body would be read whole even for a HEAD, only send the length
+                            Ok(metadata) if req.method() == Method::HEAD => {
+                                HttpResponse::Ok().body(HeadBody(metadata.len()))
+                            }
+                            _ => fs::NamedFile::open(path).map_or_else(
+                                |_| HttpResponse::NotFound().finish(),
+                                |f| f.into_response(&req),
+                            ),
+                        })
+                    }),
+                )
+                .service(
+                    actix_web::web::resource("/latest")
+                        .to(|req: HttpRequest| ready(serve_test_zip(&req))),
+                )
+                .service(actix_web::web::resource("/page").to(|| async {
+                    HttpResponse::Ok()
+                        .content_type("text/html")
+                        .body("<html><body>Not here</body></html>")
+                }))
+                .service(actix_web::web::resource("/slow").to(|| async {
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                    HttpResponse::Ok().finish()
+                }))
+                .service(
+                    actix_web::web::resource("/endless.zip").to(|req: HttpRequest| {
+                        // no length either way, but the server would never finish a response to HEAD
+                        ready(if req.method() == Method::HEAD {
+                            HttpResponse::Ok().body(ChunkedBody(None))
+                        } else {
+                            HttpResponse::Ok().body(EndlessBody)
+                        })
+                    }),
+                )
+                .service(
+                    actix_web::web::resource("/chunked/test.zip").to(|req: HttpRequest| {
+                        // only the range requests get a length
+                        ready(if req.method() == Method::HEAD {
+                            std::fs::read("./testdata/test.zip").map_or_else(
+                                |_| HttpResponse::NotFound().finish(),
+                                |content| {
+                                    HttpResponse::Ok().body(ChunkedBody(Some(content.into())))
+                                },
+                            )
+                        } else {
+                            serve_test_zip(&req)
+                        })
+                    }),
+                )
+                .service(
+                    actix_web::web::resource("/norange/{name}").to(|req: HttpRequest| {
+                        // ignore any Range header and always return the whole archive
+                        let name = req.match_info().get("name").unwrap_or_default();
+                        ready(std::fs::read(format!("./testdata/{name}")).map_or_else(
+                            |_| HttpResponse::NotFound().finish(),
+                            |content| HttpResponse::Ok().body(content),
+                        ))
+                    }),
+                )
+                .service(
+                    actix_web::web::resource("/flaky/test.zip").to(move |req: HttpRequest| {
+                        // fail the first two range requests with a server error
+                        let fail = req.method() != Method::HEAD
+                            && flaky_requests.fetch_add(1, Ordering::SeqCst) < 2;
+                        ready(if fail {
+                            HttpResponse::ServiceUnavailable().finish()
+                        } else {
+                            serve_test_zip(&req)
+                        })
+                    }),
+                )
         })
         .listen(listener)?
         .run();
@@ -71,6 +652,73 @@ mod partzip_tests {
         Ok(TestServer { address })
     }
 
+    /// Spawn a minimal FTP server hosting `content` as `/archive.zip`, in passive mode only
+    ///
+    /// Returns its URL and the number of bytes sent on the data connections.
+    fn spawn_ftp_server(content: Vec<u8>) -> Result<(String, Arc<AtomicU64>)> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("ftp://{}/archive.zip", listener.local_addr()?);
+        let content: Arc<[u8]> = content.into();
+        let sent = Arc::new(AtomicU64::new(0));
+        let counter = sent.clone();
+        std::thread::spawn(move || {
+            for control in listener.incoming().flatten() {
+                let (content, counter) = (content.clone(), counter.clone());
+                std::thread::spawn(move || ftp_session(control, &content, &counter));
+            }
+        });
+        Ok((url, sent))
+    }
+
+    /// Answer the commands of a FTP control connection, restarting the transfers at the offset of `REST`
+    fn ftp_session(control: TcpStream, content: &[u8], sent: &AtomicU64) -> std::io::Result<()> {
+        let mut lines = BufReader::new(control.try_clone()?).lines();
+        let mut control = control;
+        let mut passive = None;
+        let mut rest = 0;
+        control.write_all(b"220 ready\r\n")?;
+        while let Some(line) = lines.next().transpose()? {
+            let (command, argument) = line.split_once(' ').unwrap_or((&line, ""));
+            let reply = match command {
+                "USER" => "331 password required".to_string(),
+                "PASS" => "230 logged in".to_string(),
+                "PWD" => "257 \"/\"".to_string(),
+                "TYPE" => "200 binary".to_string(),
+                "SIZE" => format!("213 {}", content.len()),
+                "REST" => {
+                    rest = argument.parse().unwrap_or(0);
+                    "350 restarting".to_string()
+                }
+                "EPSV" => {
+                    let data = TcpListener::bind("127.0.0.1:0")?;
+                    let port = data.local_addr()?.port();
+                    passive = Some(data);
+                    format!("229 passive (|||{port}|)")
+                }
+                "RETR" => match passive.take() {
+                    Some(data) => {
+                        control.write_all(b"150 sending\r\n")?;
+                        let mut data = data.accept()?.0;
+                        let rest = std::mem::take(&mut rest);
+                        // the client closes the data connection once it has its range
+                        for chunk in content.get(rest..).unwrap_or_default().chunks(1024) {
+                            if data.write_all(chunk).is_err() {
+                                break;
+                            }
+                            sent.fetch_add(chunk.len() as u64, Ordering::SeqCst);
+                        }
+                        "226 sent".to_string()
+                    }
+                    None => "425 no data connection".to_string(),
+                },
+                "QUIT" => return control.write_all(b"221 bye\r\n"),
+                _ => "502 not implemented".to_string(),
+            };
+            control.write_all(format!("{reply}\r\n").as_bytes())?;
+        }
+        Ok(())
+    }
+
     #[tokio::test]
     /// Test the list functionality of the library
     async fn test_list() -> Result<()> {
@@ -84,6 +732,7 @@ mod partzip_tests {
                     PartialZipFileDetailed {
                         name: "1.txt".to_string(),
                         compressed_size: 7,
+                        uncompressed_size: 5,
                         compression_method: zip::CompressionMethod::Deflated.into(),
                         supported: true,
                         last_modified: NaiveDateTime::parse_from_str(
@@ -91,10 +740,15 @@ mod partzip_tests {
                             "%Y-%m-%dT%H:%M:%S"
                         )
                         .ok(),
+                        is_dir: false,
+                        is_symlink: false,
+                        has_data_descriptor: true,
+                        version_needed: 20,
                     },
                     PartialZipFileDetailed {
                         name: "2.txt".to_string(),
                         compressed_size: 7,
+                        uncompressed_size: 5,
                         compression_method: zip::CompressionMethod::Deflated.into(),
                         supported: true,
                         last_modified: NaiveDateTime::parse_from_str(
@@ -102,6 +756,55 @@ mod partzip_tests {
                             "%Y-%m-%dT%H:%M:%S"
                         )
                         .ok(),
+                        is_dir: false,
+                        is_symlink: false,
+                        has_data_descriptor: true,
+                        version_needed: 20,
+                    }
+                ]
+            );
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test the listing with all the file attributes
+    async fn test_list_full() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/test.zip")?)?;
+            let list = pz.list_full();
+            assert_eq!(
+                list,
+                vec![
+                    PartialZipFileFull {
+                        name: "1.txt".to_string(),
+                        compressed_size: 7,
+                        uncompressed_size: 5,
+                        compression_method: zip::CompressionMethod::Deflated.into(),
+                        supported: true,
+                        last_modified: time::OffsetDateTime::parse(
+                            "2022-08-12T15:24:30Z",
+                            &time::format_description::well_known::Rfc3339
+                        )
+                        .ok(),
+                        crc32: 0xf8f6_c911,
+                        unix_mode: Some(0o100_644),
+                    },
+                    PartialZipFileFull {
+                        name: "2.txt".to_string(),
+                        compressed_size: 7,
+                        uncompressed_size: 5,
+                        compression_method: zip::CompressionMethod::Deflated.into(),
+                        supported: true,
+                        last_modified: time::OffsetDateTime::parse(
+                            "2022-08-12T15:24:36Z",
+                            &time::format_description::well_known::Rfc3339
+                        )
+                        .ok(),
+                        crc32: 0x8488_f1b5,
+                        unix_mode: Some(0o100_644),
                     }
                 ]
             );
@@ -110,6 +813,77 @@ mod partzip_tests {
         .await?
     }
 
+    #[tokio::test]
+    /// Test the listing with the comments and the timestamps from the extra fields
+    async fn test_list_with_extra() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/extra.zip")?)?;
+            let list = pz.list_with_extra();
+            let local = NaiveDateTime::parse_from_str("2022-08-12T17:24:30", "%Y-%m-%dT%H:%M:%S")?;
+            let utc = NaiveDateTime::parse_from_str("2022-08-12T15:24:30", "%Y-%m-%dT%H:%M:%S")?;
+            assert_eq!(list.len(), 3);
+            assert_eq!(
+                list[0],
+                PartialZipFileExtra {
+                    name: "unix.txt".to_string(),
+                    compressed_size: 7,
+                    compression_method: PartialZipCompressionMethod::Deflated,
+                    supported: true,
+                    last_modified: Some(local),
+                    comment: "unix timestamp".to_string(),
+                    extra_field: vec![0x55, 0x54, 5, 0, 1, 0xae, 0x70, 0xf6, 0x62],
+                    modified: Some(utc),
+                }
+            );
+            assert_eq!(list[1].comment, "ntfs timestamp");
+            assert_eq!(list[1].extra_field.len(), 36);
+            assert_eq!(
+                list[1].modified,
+                Some(utc + chrono::Duration::milliseconds(500))
+            );
+            assert_eq!(list[2].comment, "");
+            assert!(list[2].extra_field.is_empty());
+            assert_eq!(list[2].modified, None);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[test]
+    /// Test the listing sorted by name and by size
+    fn test_list_sorted() -> Result<()> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let stored = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        for (name, content) in [
+            ("b.txt", "b".repeat(10)),
+            ("C.txt", "c".repeat(1000)),
+            ("a.txt", "a".repeat(100)),
+            ("B.txt", "b".repeat(100)),
+        ] {
+            writer.start_file(name, stored)?;
+            std::io::Write::write_all(&mut writer, content.as_bytes())?;
+        }
+        let pz = PartialZip::from_bytes(writer.finish()?.into_inner())?;
+        let names = |key| {
+            pz.list_sorted(key)
+                .into_iter()
+                .map(|file| file.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(SortKey::Name), ["a.txt", "B.txt", "b.txt", "C.txt"]);
+        assert_eq!(
+            names(SortKey::CompressedSize),
+            ["b.txt", "a.txt", "B.txt", "C.txt"]
+        );
+        assert_eq!(
+            names(SortKey::UncompressedSize),
+            names(SortKey::CompressedSize)
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     /// Test the download functionality of the library
     async fn test_download() -> Result<()> {
@@ -125,206 +899,2808 @@ mod partzip_tests {
         .await?
     }
 
-    #[cfg(feature = "progressbar")]
     #[tokio::test]
-    /// See if the code with the progress bar at least run
-    async fn test_download_progressbar() -> Result<()> {
+    /// Test downloading the files by their position in the archive
+    async fn test_download_by_index() -> Result<()> {
         let address = spawn_server()?.address;
         tokio::task::spawn_blocking(move || {
             let pz = PartialZip::new(&address.join("/files/test.zip")?)?;
-            let downloaded = pz.download_with_progressbar("1.txt")?;
-            assert_eq!(downloaded, vec![0x41, 0x41, 0x41, 0x41, 0xa]);
-            let downloaded = pz.download_with_progressbar("2.txt")?;
-            assert_eq!(downloaded, vec![0x42, 0x42, 0x42, 0x42, 0xa]);
+            assert_eq!(pz.list_names()[1], "2.txt");
+            assert_eq!(pz.download_by_index(1)?, b"BBBB\n");
+            assert_eq!(pz.download_by_index(0)?, pz.download("1.txt")?);
+            assert!(matches!(
+                pz.download_by_index(2),
+                Err(PartialZipError::FileNotFound { name }) if name == "#2"
+            ));
             Ok(())
         })
         .await?
     }
 
     #[tokio::test]
-    /// Test that downloading files that are not present in the archive throws an error
-    async fn test_download_invalid_file() -> Result<()> {
+    /// Test the progress callback while downloading
+    async fn test_download_progress() -> Result<()> {
         let address = spawn_server()?.address;
         tokio::task::spawn_blocking(move || {
-            let pz = PartialZip::new(&address.join("/files/test.zip")?)?;
-            let downloaded = pz.download("414141.txt");
-            assert!(
-                matches!(
-                    downloaded,
-                    Err(PartialZipError::ZipRsError(ZipError::FileNotFound))
-                ),
-                "didn't throw an error when a file is not in the zip"
-            );
+            let pz = PartialZip::new(&address.join("/files/lzma.zip")?)?;
+            let mut content = Vec::new();
+            let mut reports = Vec::new();
+            pz.download_to_write_with_progress("lzma.txt", &mut content, &mut |progress| {
+                reports.push(progress);
+            })?;
+            assert!(reports.len() > 1);
+            assert!(reports.windows(2).all(|w| w[0].done < w[1].done));
+            let last = reports
+                .last()
+                .ok_or_else(|| anyhow::anyhow!("no progress"))?;
+            assert_eq!((last.done, last.total), (180_000, 180_000));
+            assert_eq!(last.eta, Some(Duration::ZERO));
+            // everything was written in a few milliseconds at most
+            assert!(last.bytes_per_sec > 180_000);
+            assert_eq!(content.len(), 180_000);
             Ok(())
         })
         .await?
     }
 
     #[tokio::test]
-    /// Test that invalid zip archives are rejected
-    async fn test_invalid_header() -> Result<()> {
+    /// Test the streaming of a file of the archive
+    async fn test_open_entry() -> Result<()> {
+        use std::io::Read;
+
         let address = spawn_server()?.address;
         tokio::task::spawn_blocking(move || {
-            let pz = PartialZip::new(
-                &address
-                    .join("/files/invalid.zip")
-                    .expect("cannot join invalid URL"),
-            );
+            let pz = PartialZip::new(&address.join("/files/lzma.zip")?)?;
+            let mut entry = pz.open_entry("lzma.txt")?;
+            let mut start = [0; 9];
+            entry.read_exact(&mut start)?;
+            assert_eq!(&start, b"00000000\n");
+            let rest = std::io::copy(&mut entry, &mut std::io::sink())?;
+            assert_eq!(rest, 180_000 - 9);
             assert!(
                 matches!(
-                    pz,
-                    Err(PartialZipError::ZipRsError(ZipError::InvalidArchive(_)))
+                    pz.open_entry("missing.txt"),
+                    Err(PartialZipError::FileNotFound { .. })
                 ),
-                "didn't throw an error with invalid header"
+                "didn't throw an error on a missing file"
             );
+            // an error while decompressing is returned by the reads
+            let pz = PartialZip::new(&address.join("/files/badcrc.zip")?)?;
+            let mut content = Vec::new();
+            assert!(pz.open_entry("1.txt")?.read_to_end(&mut content).is_err());
+            Ok(())
         })
-        .await?;
-        Ok(())
+        .await?
     }
 
     #[tokio::test]
-    /// Test that invalid URLs don't get through
-    async fn test_invalid_url() -> Result<()> {
-        spawn_server()?;
+    /// Test the size, the number and the names of the files of the archive, known without any other request
+    async fn test_len() -> Result<()> {
+        let address = spawn_server()?.address;
         tokio::task::spawn_blocking(move || {
-            let pz = PartialZip::new(&"invalid URL");
-            assert!(
-                matches!(pz, Err(PartialZipError::InvalidUrl)),
-                "didn't throw an error with invalid URL"
+            let pz = PartialZip::new(&address.join("/files/tail.zip")?)?;
+            let requests = pz.requests();
+            assert_eq!(pz.file_size(), 66_222);
+            assert_eq!(pz.len(), 41);
+            assert!(!pz.is_empty());
+            assert!(pz.contains("big.bin"));
+            assert!(pz.contains("small/07.txt"));
+            assert!(!pz.contains("small/"));
+            assert!(pz.comment().is_empty());
+            assert_eq!(pz.requests(), requests);
+            let pz = PartialZip::new(&address.join("/files/comment.zip")?)?;
+            assert_eq!(pz.comment(), b"build 1234");
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that an archive opened with an exported central directory doesn't fetch it again
+    async fn test_directory() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let url = address.join("/files/tail.zip")?;
+            let pz = PartialZip::new(&url)?;
+            let directory = pz.export_directory()?;
+            // from the start of the central directory
+            assert_eq!(directory.len(), 2395);
+            assert!(std::fs::read("./testdata/tail.zip")?.ends_with(&directory));
+            let pz = PartialZip::new_with_directory(&url, &directory, pz.file_size())?;
+            assert_eq!(pz.len(), 41);
+            assert!(pz.list_names().contains(&"big.bin".to_string()));
+            assert_eq!(pz.requests(), 0);
+            assert_eq!(pz.export_directory()?, directory);
+            assert_eq!(pz.requests(), 0);
+            assert_eq!(
+                pz.download("small/07.txt")?,
+                PartialZip::new(&url)?.download("small/07.txt")?
             );
-            if let Err(e) = pz {
-                println!("{e:?}");
-            }
+            assert!(pz.requests() > 0);
+            assert!(PartialZip::new_with_directory(&url, &directory, 10).is_err());
+            Ok(())
         })
-        .await?;
-        Ok(())
+        .await?
     }
 
-    #[cfg(unix)]
-    #[test]
-    /// Test that we can open files over file:// not only http/https
-    fn test_file_protocol() -> Result<()> {
-        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        d.push("testdata/test.zip");
-        let pz = PartialZip::new(&format!("file://localhost{}", d.display()))?;
-        let list = pz.list_detailed();
-        assert_eq!(
-            list,
-            vec![
-                PartialZipFileDetailed {
-                    name: "1.txt".to_string(),
-                    compressed_size: 7,
-                    compression_method: zip::CompressionMethod::Deflated.into(),
-                    supported: true,
-                    last_modified: NaiveDateTime::parse_from_str(
-                        "2022-08-12T15:24:30",
-                        "%Y-%m-%dT%H:%M:%S"
-                    )
-                    .ok(),
-                },
-                PartialZipFileDetailed {
-                    name: "2.txt".to_string(),
-                    compressed_size: 7,
-                    compression_method: zip::CompressionMethod::Deflated.into(),
-                    supported: true,
-                    last_modified: NaiveDateTime::parse_from_str(
-                        "2022-08-12T15:24:36",
-                        "%Y-%m-%dT%H:%M:%S"
-                    )
-                    .ok(),
-                }
-            ]
+    #[tokio::test]
+    /// Test a server expecting another format of the `Range` header
+    async fn test_range_formatter() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let url = address.join("/quirky/test.zip")?;
+            let pz = PartialZip::new_check_range(&url, true);
+            assert!(matches!(pz, Err(PartialZipError::RangeNotSupported)));
+            let reader = PartialReader::new_check_range(&url, true)?
+                .with_range_formatter(|start, end| format!("bytes {start}-{end}"));
+            let pz = PartialZip::from_reader(reader)?;
+            assert_eq!(pz.list_names(), ["1.txt", "2.txt"]);
+            assert_eq!(pz.download("2.txt")?, b"BBBB\n");
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test opening an archive of a known size without probing it
+    async fn test_new_with_size() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let url = address.join("/files/test.zip")?;
+            let probed = PartialZip::new(&url)?.requests();
+            let pz = PartialZip::new_with_size(&url, 368, true)?;
+            assert_eq!(pz.requests(), probed - 1);
+            assert_eq!(pz.download("2.txt")?, b"BBBB\n");
+            assert!(matches!(
+                PartialZip::new_with_size(&url, 367, false),
+                Err(e) if e.to_string().contains("has 368 bytes, not 367")
+            ));
+            let url = address.join("/norange/test.zip")?;
+            assert_eq!(PartialZip::new_with_size(&url, 368, false)?.len(), 2);
+            // the first range covers the whole archive otherwise
+            let reader = PartialReader::new_check_range(&url, true)?
+                .with_cache_size(0)
+                .with_file_size(368);
+            assert!(matches!(
+                PartialZip::from_reader(reader),
+                Err(PartialZipError::RangeNotSupported)
+            ));
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test the archives opened through a client reuse its connections
+    async fn test_from_client() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let url = address.join("/files/test.zip")?;
+            let client = PartialZipClient::new();
+            let archives = 5;
+            for _ in 0..archives {
+                let pz = PartialZip::from_client(&client, &url)?;
+                assert_eq!(pz.download("2.txt")?, b"BBBB\n");
+            }
+            assert!(client.connections() > 0);
+            assert!(client.connections() < archives);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[test]
+    /// Test an archive read from a stream which can't seek, through a temporary file
+    fn test_from_reader_spilled() -> Result<()> {
+        let data = std::fs::read("./testdata/test.zip")?;
+        let pz = PartialZip::from_reader_spilled(data.as_slice())?;
+        assert_eq!(pz.url(), "");
+        assert_eq!(pz.file_size(), 368);
+        assert_eq!(pz.list_names(), vec!["1.txt", "2.txt"]);
+        assert_eq!(pz.download("2.txt")?, b"BBBB\n");
+        assert!(PartialZip::from_reader_spilled(&b"not a zip"[..]).is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "checksums")]
+    #[test]
+    /// Test hashing a file with every algorithm, and the errors of the download
+    fn test_hash_entry() -> Result<()> {
+        use crate::partzip::HashAlgorithm;
+
+        let pz = PartialZip::from_bytes(std::fs::read("./testdata/test.zip")?)?;
+        assert_eq!(
+            pz.hash_entry("2.txt", HashAlgorithm::Md5)?,
+            "6d799daf4e343f58d5050f6ea99c70e7"
+        );
+        assert_eq!(
+            pz.hash_entry("2.txt", HashAlgorithm::Sha1)?,
+            "920b4da8987cdc436abad0f1026320a686e41b6f"
+        );
+        assert_eq!(
+            pz.hash_entry("2.txt", HashAlgorithm::default())?,
+            "0bc4ed515ae40a64321fceadbbecceee6ea5baf3b6cf3f566f058cfce518aecc"
         );
+        assert!(matches!(
+            pz.hash_entry("missing.txt", HashAlgorithm::Sha256),
+            Err(PartialZipError::FileNotFound { .. })
+        ));
+        let pz = PartialZip::from_bytes(std::fs::read("./testdata/badcrc.zip")?)?;
+        assert!(pz.hash_entry("1.txt", HashAlgorithm::Sha256).is_err());
         Ok(())
     }
 
-    #[cfg(unix)]
     #[test]
-    /// Test that the url getter works
-    fn test_url_getter() -> Result<()> {
-        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        d.push("testdata/test.zip");
-        let url = format!("file://localhost{}", d.display());
-        let pz = PartialZip::new(&url)?;
-        assert_eq!(url, pz.url());
+    /// Test an archive already in memory
+    fn test_from_bytes() -> Result<()> {
+        let pz = PartialZip::from_bytes(std::fs::read("./testdata/test.zip")?)?;
+        assert_eq!(pz.url(), "");
+        assert_eq!(pz.file_size(), 368);
+        assert_eq!(pz.list_names(), vec!["1.txt", "2.txt"]);
+        assert_eq!(pz.download("1.txt")?, vec![0x41, 0x41, 0x41, 0x41, 0xa]);
+        let mut downloaded = Vec::new();
+        std::io::Read::read_to_end(&mut pz.open_entry("2.txt")?, &mut downloaded)?;
+        assert_eq!(downloaded, vec![0x42, 0x42, 0x42, 0x42, 0xa]);
+        assert_eq!(pz.download_range("2.txt", 1, 2)?, b"BB");
+        assert_eq!(pz.requests(), 0);
+        assert!(matches!(
+            PartialZip::from_bytes(b"not a zip".to_vec()),
+            Err(PartialZipError::ZipRsError(_))
+        ));
         Ok(())
     }
 
-    #[cfg(unix)]
+    /// Archive in memory read through the [`RangeReader`] trait, counting the reads
+    struct CountingRanges {
+        data: Vec<u8>,
+        reads: Arc<AtomicUsize>,
+    }
+
+    impl RangeReader for CountingRanges {
+        fn read_range(&mut self, start: u64, end: u64) -> std::io::Result<Vec<u8>> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            let range = usize::try_from(start).unwrap_or(usize::MAX)
+                ..=usize::try_from(end).unwrap_or(usize::MAX);
+            self.data.get(range).map(<[u8]>::to_vec).ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "out of range")
+            })
+        }
+
+        fn len(&self) -> u64 {
+            self.data.len() as u64
+        }
+    }
+
+    /// Archive in memory read through the [`RangeReader`] trait, failing the reads of the byte at `fail_at`
+    struct FailingRanges {
+        data: Vec<u8>,
+        fail_at: u64,
+    }
+
+    impl RangeReader for FailingRanges {
+        fn read_range(&mut self, start: u64, end: u64) -> std::io::Result<Vec<u8>> {
+            if (start..=end).contains(&self.fail_at) {
+                return Err(std::io::Error::other("connection reset"));
+            }
+            let range = usize::try_from(start).unwrap_or(usize::MAX)
+                ..=usize::try_from(end).unwrap_or(usize::MAX);
+            Ok(self.data[range].to_vec())
+        }
+
+        fn len(&self) -> u64 {
+            self.data.len() as u64
+        }
+    }
+
     #[test]
-    /// Test that the file size getter works
-    fn test_file_size_getter() -> Result<()> {
-        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        d.push("testdata/test.zip");
-        let url = format!("file://localhost{}", d.display());
-        let pz = PartialZip::new(&url)?;
-        // TODO get the filesize from filesystem
-        assert_eq!(368, pz.file_size());
+    /// Test that a read failing in the middle of the content isn't reported as a CRC mismatch
+    fn test_download_verified_read_error() -> Result<()> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        writer.start_file("big.bin", options)?;
+        std::io::Write::write_all(&mut writer, &vec![0x42; 0x0030_0000])?;
+        let pz = PartialZip::from_range_reader(FailingRanges {
+            data: writer.finish()?.into_inner(),
+            fail_at: 0x0020_0000,
+        })?;
+        let error = pz.download_verified("big.bin").unwrap_err();
+        assert!(
+            matches!(&error, PartialZipError::IOError(e) if e.to_string() == "connection reset"),
+            "didn't return the error of the read: {error:?}"
+        );
         Ok(())
     }
 
-    #[cfg(unix)]
+    #[tokio::test]
+    /// Test an archive read through another implementation of [`RangeReader`]
+    async fn test_range_reader() -> Result<()> {
+        let reads = Arc::new(AtomicUsize::new(0));
+        let archive = std::fs::read("./testdata/tail.zip")?;
+        let pz = PartialZip::from_range_reader(CountingRanges {
+            data: archive.clone(),
+            reads: reads.clone(),
+        })?;
+        assert!(reads.load(Ordering::SeqCst) > 0);
+        let expected = PartialZip::from_bytes(archive)?;
+        assert_eq!(pz.url(), "");
+        assert_eq!(pz.list_names(), expected.list_names());
+        assert_eq!(pz.download("big.bin")?, expected.download("big.bin")?);
+        assert_eq!(pz.export_directory()?.len(), 2395);
+        let out_dir = tempfile::tempdir()?;
+        for result in pz.download_many(&["small/00.txt", "small/01.txt"], out_dir.path(), 2) {
+            assert!(result?.is_file());
+        }
+
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let mut reader = PartialReader::new(&address.join("/files/test.zip")?)?;
+            assert_eq!(reader.read_range(0, 3)?, b"PK\x03\x04");
+            assert_eq!(RangeReader::len(&reader), 368);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that the size of the archive comes from a range request when the server doesn't send its length
+    async fn test_chunked_size() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let mut reader = PartialReader::new(&address.join("/chunked/test.zip")?)?;
+            reader.probe()?;
+            assert_eq!(reader.file_size(), 368);
+            assert_eq!(reader.requests(), 2);
+            let pz = PartialZip::new_check_range(&address.join("/chunked/test.zip")?, true)?;
+            assert_eq!(pz.file_size(), 368);
+            assert_eq!(pz.download("2.txt")?, vec![0x42, 0x42, 0x42, 0x42, 0xa]);
+            // a server ignoring the range isn't read past the headers, the body would never end
+            let mut reader = PartialReader::new(&address.join("/endless.zip")?)?;
+            assert!(reader.probe().is_err(), "found a size without any range");
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that the files are fetched only as far as the iteration goes
+    async fn test_entries() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let url = address.join("/files/tail.zip")?;
+            let open = || -> Result<PartialZip> {
+                let reader = PartialReader::new(&url)?
+                    .with_cache_size(0)
+                    .with_tail_prefetch(0);
+                Ok(PartialZip::from_reader(reader)?)
+            };
+            let pz = open()?;
+            let entries = pz.entries().collect::<Result<Vec<_>, _>>()?;
+            assert_eq!(entries, pz.list_detailed());
+            let pz = open()?;
+            let before = pz.stats().requests;
+            let found = pz.entries().find(|entry| {
+                entry
+                    .as_ref()
+                    .is_ok_and(|entry| entry.name == "small/00.txt")
+            });
+            assert_eq!(
+                found.transpose()?.map(|entry| entry.compressed_size),
+                Some(2)
+            );
+            let early = pz.stats().requests - before;
+            let before = pz.stats().requests;
+            assert_eq!(pz.entries().count(), 41);
+            assert!(
+                early * 5 < pz.stats().requests - before,
+                "{early} requests to find the second file"
+            );
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that the archives exceeding the limits are refused
+    async fn test_new_with_limits() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let url = address.join("/files/test.zip")?;
+            let pz = PartialZip::new_with_limits(&url, 2, 10)?;
+            assert_eq!(pz.download("1.txt")?, vec![0x41, 0x41, 0x41, 0x41, 0xa]);
+            let error = PartialZip::new_with_limits(&url, 1, 10)
+                .err()
+                .ok_or_else(|| anyhow::anyhow!("too many entries accepted"))?;
+            assert!(matches!(
+                error,
+                PartialZipError::LimitExceeded {
+                    what: "entries",
+                    actual: 2,
+                    limit: 1
+                }
+            ));
+            assert_eq!(
+                error.to_string(),
+                "the archive has 2 entries, more than the limit of 1"
+            );
+            assert!(matches!(
+                PartialZip::new_with_limits(&url, 2, 9),
+                Err(PartialZipError::LimitExceeded {
+                    what: "uncompressed bytes",
+                    actual: 10,
+                    limit: 9
+                })
+            ));
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test an archive split in volumes, with a file spanning all of them
+    async fn test_new_split() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new_split(&address.join("/files/split.zip")?)?;
+            assert_eq!(pz.url(), address.join("/files/split.zip")?.to_string());
+            assert_eq!(pz.list_names(), vec!["small.txt", "big.bin"]);
+            assert_eq!(pz.download("small.txt")?, b"split archive\n");
+            let big = pz.download("big.bin")?;
+            assert_eq!(big.len(), 150_000);
+            assert!(big.starts_with(&[0x81, 0xd6, 0x74, 0x56]));
+            assert_eq!(crc32fast::hash(&big), 0xb1bf_d0ee);
+            pz.verify_archive()?;
+            assert!(pz.stats().requests > 3);
+            // an archive which isn't split
+            let pz = PartialZip::new_split(&address.join("/files/test.zip")?)?;
+            assert_eq!(pz.download("1.txt")?, vec![0x41, 0x41, 0x41, 0x41, 0xa]);
+            // the offsets are in the volumes, not in the last one
+            let pz = PartialZip::new(&address.join("/files/split.zip")?)?;
+            assert!(pz.download("big.bin").is_err());
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test the totals over the files, computed from the central directory only
+    async fn test_summary() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/tail.zip")?)?;
+            let before = pz.stats();
+            let summary = pz.summary()?;
+            assert_eq!(pz.stats(), before, "fetched something for the summary");
+            assert_eq!(summary.files, 41);
+            assert_eq!(summary.uncompressed_size, 62110);
+            assert_eq!(summary.compressed_size, 62110);
+            assert_eq!(summary.ratio(), Some(1.0));
+            assert_eq!(
+                summary.methods.into_iter().collect::<Vec<_>>(),
+                vec![(PartialZipCompressionMethod::Stored, 41)]
+            );
+            let pz = PartialZip::new(&address.join("/files/test.zip")?)?;
+            let summary = pz.summary()?;
+            assert_eq!(summary.compressed_size, 14);
+            assert_eq!(
+                summary.methods.get(&PartialZipCompressionMethod::Deflated),
+                Some(&2)
+            );
+            assert_eq!(pz.list_detailed()[0].ratio(), Some(1.4));
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test the details of a single file
+    async fn test_stat() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/test.zip")?)?;
+            assert_eq!(pz.stat("2.txt")?, pz.list_detailed()[1]);
+            assert!(
+                matches!(pz.stat("nope.txt"), Err(PartialZipError::FileNotFound { name }) if name == "nope.txt"),
+                "didn't throw an error with a missing file"
+            );
+            let error = pz.download("dir/nope.txt").unwrap_err();
+            assert_eq!(error.to_string(), "File Not Found: dir/nope.txt");
+            Ok(())
+        })
+        .await?
+    }
+
     #[test]
-    /// Test that it throws an error when the range protocol is not supported
-    fn test_check_range_on_not_ranging_protocol() {
-        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        d.push("testdata/test.zip");
-        let pz = PartialZip::new_check_range(&format!("file://localhost{}", d.display()), true);
+    /// Test the names without the UTF-8 flag, decoded from CP437 but also found from their UTF-8 form
+    fn test_cp437_names() -> Result<()> {
+        let pz = PartialZip::from_bytes(std::fs::read("./testdata/cp437.zip")?)?;
+        // the second name is written in UTF-8 without the flag
+        assert_eq!(pz.list_names(), vec!["café.txt", "na├»ve.txt"]);
+        assert_eq!(pz.download("café.txt")?, b"cp437 name\n");
+        assert_eq!(pz.download("na├»ve.txt")?, b"utf8 without flag\n");
+        assert_eq!(pz.download("naïve.txt")?, b"utf8 without flag\n");
+        assert_eq!(pz.stat("naïve.txt")?.name, "na├»ve.txt");
         assert!(
-            matches!(pz, Err(PartialZipError::RangeNotSupported)),
-            "didn't throw an error with range not supported"
+            matches!(
+                pz.download("cafe.txt"),
+                Err(PartialZipError::FileNotFound { .. })
+            ),
+            "didn't throw an error with a missing file"
+        );
+        Ok(())
+    }
+
+    #[test]
+    /// Test the lookup of the names with mixed separators and case
+    fn test_download_normalized() -> Result<()> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        for name in [
+            "Dir\\file.txt",
+            "dir/FILE.txt",
+            "Other\\A.txt",
+            "other/a.TXT",
+        ] {
+            writer.start_file(name, options)?;
+            std::io::Write::write_all(&mut writer, name.as_bytes())?;
+        }
+        let pz = PartialZip::from_bytes(writer.finish()?.into_inner())?;
+        assert_eq!(pz.download_normalized("Dir/file.txt")?, b"Dir\\file.txt");
+        assert_eq!(pz.download_normalized("dir\\FILE.txt")?, b"dir/FILE.txt");
+        assert_eq!(pz.download_normalized("other/a.TXT")?, b"other/a.TXT");
+        let error = pz.download_normalized("OTHER/a.txt").unwrap_err();
+        assert!(
+            matches!(&error, PartialZipError::AmbiguousFilename { candidates, .. } if candidates.len() == 2),
+            "didn't throw an error with several matches"
+        );
+        assert_eq!(
+            error.to_string(),
+            "OTHER/a.txt matches several files: Other\\A.txt, other/a.TXT"
+        );
+        assert!(
+            matches!(
+                pz.download_normalized("dir/nope.txt"),
+                Err(PartialZipError::FileNotFound { .. })
+            ),
+            "didn't throw an error with a missing file"
         );
+        Ok(())
     }
 
     #[tokio::test]
-    /// Test that the range header is correctly detected
-    async fn test_range_support() -> Result<()> {
+    /// Test that only the files passing the predicate are listed
+    async fn test_list_filtered() -> Result<()> {
         let address = spawn_server()?.address;
         tokio::task::spawn_blocking(move || {
-            let pz = PartialZip::new_check_range(&address.join("/files/test.zip")?, true)?;
-            let downloaded = pz.download("1.txt")?;
-            assert_eq!(downloaded, vec![0x41, 0x41, 0x41, 0x41, 0xa]);
+            let pz = PartialZip::new(&address.join("/files/test.zip")?)?;
+            let mut all = pz.list_detailed();
+            assert_eq!(pz.list_filtered(|_| true), all);
+            all.remove(0);
+            assert_eq!(pz.list_filtered(|name| name.starts_with('2')), all);
+            assert!(pz.list_filtered(|_| false).is_empty());
             Ok(())
         })
         .await?
     }
 
     #[tokio::test]
-    /// Check if we follow redirects correctly
-    async fn test_redirect() -> Result<()> {
+    /// Test that the offset and the compressed size point to the raw content of the file
+    async fn test_data_offset() -> Result<()> {
         let address = spawn_server()?.address;
         tokio::task::spawn_blocking(move || {
-            let pz = PartialZip::new(&address.join("/redirect")?)?;
-            let list = pz.list_detailed();
+            let pz = PartialZip::new(&address.join("/files/test.zip")?)?;
+            let archive = std::fs::read("./testdata/test.zip")?;
+            for name in ["1.txt", "2.txt"] {
+                let range = pz.data_offset(name)?;
+                assert_eq!(range.end - range.start, pz.stat(name)?.compressed_size);
+                let range = usize::try_from(range.start)?..usize::try_from(range.end)?;
+                assert_eq!(archive[range], pz.download_raw(name)?.0);
+            }
+            assert!(
+                matches!(
+                    pz.data_offset("nope.txt"),
+                    Err(PartialZipError::FileNotFound { .. })
+                ),
+                "didn't throw an error with a missing file"
+            );
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that the directories and the symlinks are told apart from the files, and the target of the symlinks
+    async fn test_dir_and_symlink() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/symlink.zip")?)?;
+            let kinds: Vec<_> = pz
+                .list_detailed()
+                .into_iter()
+                .map(|f| (f.name, f.is_dir, f.is_symlink))
+                .collect();
             assert_eq!(
-                list,
+                kinds,
                 vec![
-                    PartialZipFileDetailed {
-                        name: "1.txt".to_string(),
-                        compressed_size: 7,
-                        compression_method: zip::CompressionMethod::Deflated.into(),
-                        supported: true,
-                        last_modified: NaiveDateTime::parse_from_str(
-                            "2022-08-12T15:24:30",
-                            "%Y-%m-%dT%H:%M:%S"
-                        )
-                        .ok(),
-                    },
-                    PartialZipFileDetailed {
-                        name: "2.txt".to_string(),
-                        compressed_size: 7,
-                        compression_method: zip::CompressionMethod::Deflated.into(),
-                        supported: true,
-                        last_modified: NaiveDateTime::parse_from_str(
-                            "2022-08-12T15:24:36",
-                            "%Y-%m-%dT%H:%M:%S"
-                        )
-                        .ok(),
-                    }
+                    ("dir/".to_string(), true, false),
+                    ("dir/file.txt".to_string(), false, false),
+                    ("link".to_string(), false, true),
                 ]
             );
+            assert_eq!(pz.read_link("link")?, "dir/file.txt");
+            assert!(
+                matches!(pz.read_link("dir/file.txt"), Err(PartialZipError::IOError(e)) if e.kind() == std::io::ErrorKind::InvalidInput),
+                "read the target of a regular file"
+            );
+            assert!(
+                matches!(pz.read_link("nope"), Err(PartialZipError::FileNotFound { .. })),
+                "didn't throw an error with a missing file"
+            );
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test the structure checks of the archives, without decompressing anything
+    async fn test_verify_archive() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            for name in ["test.zip", "tail.zip", "comment.zip", "badcrc.zip"] {
+                PartialZip::new(&address.join(&format!("/files/{name}"))?)?.verify_archive()?;
+            }
+            // the signatures are read alone, not with the buffer of the archive reader around them
+            let reader = PartialReader::new(&address.join("/files/tail.zip")?)?
+                .with_cache_size(0)
+                .with_tail_prefetch(0);
+            let pz = PartialZip::from_reader(reader)?;
+            let before = pz.stats();
+            pz.verify_archive()?;
+            let received = pz.stats().bytes - before.bytes;
+            assert!(received < 4096, "{received} bytes received");
+            let pz = PartialZip::new(&address.join("/files/badheader.zip")?)?;
+            assert_eq!(pz.list_names(), vec!["1.txt", "2.txt"]);
+            assert!(
+                matches!(pz.verify_archive(), Err(PartialZipError::InvalidArchive(e)) if e == "no local header for 2.txt at offset 0x5a"),
+                "didn't find the broken local header"
+            );
+            let pz = PartialZip::new(&address.join("/files/badcount.zip")?)?;
+            assert!(
+                matches!(pz.verify_archive(), Err(PartialZipError::InvalidArchive(e)) if e.contains("in the central directory")),
+                "didn't find the wrong number of files"
+            );
             Ok(())
         })
         .await?
     }
+
+    #[tokio::test]
+    /// Test that the files next to each other are fetched with a single request
+    async fn test_download_batch() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let reader = PartialReader::new(&address.join("/files/tail.zip")?)?
+                .with_cache_size(0)
+                .with_tail_prefetch(0);
+            let pz = PartialZip::from_reader(reader)?;
+            let names = ["small/05.txt", "small/01.txt", "small/02.txt"];
+            let before = pz.stats().requests;
+            let contents = pz.download_batch(&names)?;
+            // the central directory without a cache, the signature and the lengths of the local header of each file,
+            // then small/05.txt alone and the adjacent small/01.txt and small/02.txt
+            assert_eq!(pz.stats().requests - before, 3 + 3 * 3);
+            let expected = PartialZip::from_bytes(std::fs::read("./testdata/tail.zip")?)?;
+            assert_eq!(contents.len(), names.len());
+            for name in names {
+                assert_eq!(contents[name], expected.download(name)?);
+            }
+            assert_eq!(
+                expected.download_batch(&["big.bin"])?["big.bin"],
+                pz.download("big.bin")?
+            );
+            assert!(
+                matches!(
+                    pz.download_batch(&["small/01.txt", "nope.txt"]),
+                    Err(PartialZipError::FileNotFound { .. })
+                ),
+                "didn't throw an error with a missing file"
+            );
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that the plan of a download matches the local header and the content of the file
+    async fn test_download_plan() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/tail.zip")?)?;
+            let before = pz.stats();
+            let plan = pz.download_plan("big.bin")?;
+            assert_eq!(pz.stats(), before, "fetched something for the plan");
+            let data_start = pz.data_offset("big.bin")?.start;
+            assert_eq!(
+                plan,
+                DownloadPlan {
+                    ranges: vec![(0, data_start - 1), (data_start, data_start + 61_999)],
+                    total_bytes: data_start + 62_000,
+                }
+            );
+            let plan = pz.download_plan("small/07.txt")?;
+            assert_eq!(plan.ranges[1], {
+                let range = pz.data_offset("small/07.txt")?;
+                (range.start, range.end - 1)
+            });
+            assert!(
+                matches!(
+                    pz.download_plan("nope.txt"),
+                    Err(PartialZipError::FileNotFound { .. })
+                ),
+                "didn't throw an error with a missing file"
+            );
+            Ok(())
+        })
+        .await?
+    }
+
+    /// Returns an archive with two files named `dup.txt`, which the zip crate lists once with the last one
+    ///
+    /// It's read through ranges, [`PartialZip::from_bytes`] would find the central directory from the listed file only.
+    fn duplicate_names_zip() -> Result<PartialZip<CountingRanges>> {
+        Ok(PartialZip::from_range_reader(CountingRanges {
+            data: duplicate_names_data()?,
+            reads: Arc::default(),
+        })?)
+    }
+
+    /// Returns the content of the archive of [`duplicate_names_zip`]
+    fn duplicate_names_data() -> Result<Vec<u8>> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        // a later version is needed to extract the first one
+        writer.start_file(
+            "dup.txt",
+            options.compression_method(zip::CompressionMethod::Bzip2),
+        )?;
+        std::io::Write::write_all(&mut writer, b"first")?;
+        writer.start_file("dup.tmp", options)?;
+        std::io::Write::write_all(&mut writer, b"second one")?;
+        let mut data = writer.finish()?.into_inner();
+        // renamed in the local and central headers, the zip crate doesn't write the same name twice
+        while let Some(at) = data.windows(7).position(|name| name == b"dup.tmp") {
+            data[at..at + 7].copy_from_slice(b"dup.txt");
+        }
+        Ok(data)
+    }
+
+    #[test]
+    /// Test the plan of a file whose name is in the archive twice, with the last header of the central directory for it
+    fn test_download_plan_duplicate_names() -> Result<()> {
+        let pz = duplicate_names_zip()?;
+        assert_eq!(pz.list_names(), ["dup.txt"]);
+        assert_eq!(pz.download("dup.txt")?, b"second one");
+        let range = pz.data_offset("dup.txt")?;
+        assert_eq!(
+            pz.download_plan("dup.txt")?.ranges[1],
+            (range.start, range.end - 1)
+        );
+        Ok(())
+    }
+
+    #[test]
+    /// Test the details of a file whose name is in the archive twice, from the last header of the central directory for it
+    fn test_stat_duplicate_names() -> Result<()> {
+        let pz = duplicate_names_zip()?;
+        let details = pz.stat("dup.txt")?;
+        assert_eq!(details.version_needed, 20);
+        assert_eq!(details.uncompressed_size, 10);
+        assert_eq!(pz.list_detailed(), [details]);
+        assert_eq!(
+            pz.entries().collect::<Result<Vec<_>, _>>()?,
+            pz.list_detailed()
+        );
+        Ok(())
+    }
+
+    #[test]
+    /// Test the fingerprint of an archive with a name in it twice, which counts both files
+    fn test_directory_fingerprint_duplicate_names() -> Result<()> {
+        let pz = duplicate_names_zip()?;
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        writer.start_file("dup.txt", zip::write::SimpleFileOptions::default())?;
+        std::io::Write::write_all(&mut writer, b"second one")?;
+        let listed = PartialZip::from_bytes(writer.finish()?.into_inner())?;
+        assert_ne!(pz.directory_fingerprint()?, listed.directory_fingerprint()?);
+        Ok(())
+    }
+
+    #[test]
+    /// Test the batch download of a file whose name is in the archive twice, fetching only the region of the last one
+    fn test_download_batch_duplicate_names() -> Result<()> {
+        let (url, _) = spawn_ftp_server(duplicate_names_data()?)?;
+        let reader = PartialReader::new(&url)?
+            .with_cache_size(0)
+            .with_tail_prefetch(0);
+        let pz = PartialZip::from_reader(reader)?;
+        let before = pz.stats().requests;
+        assert_eq!(pz.download_batch(&["dup.txt"])?["dup.txt"], b"second one");
+        // the central directory, the signature and the lengths of the local header, then the region of the file
+        assert_eq!(pz.stats().requests - before, 5);
+        Ok(())
+    }
+
+    #[test]
+    /// Test the structure checks of an archive with a name in it twice, which has the local headers of both files
+    fn test_verify_archive_duplicate_names() -> Result<()> {
+        duplicate_names_zip()?.verify_archive()?;
+        let mut data = duplicate_names_data()?;
+        // the signature of the local header of the first file
+        data[0] = 0;
+        let pz = PartialZip::from_range_reader(CountingRanges {
+            data,
+            reads: Arc::default(),
+        })?;
+        assert!(
+            matches!(pz.verify_archive(), Err(PartialZipError::InvalidArchive(e)) if e == "no local header for dup.txt at offset 0x0"),
+            "didn't find the broken local header of the first file"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    /// Test the files written in streaming mode, with zero sizes in their local header
+    async fn test_data_descriptor() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/streaming.zip")?)?;
+            assert!(pz.list_detailed().iter().all(|f| f.has_data_descriptor));
+            assert!(pz.stat("stored.txt")?.has_data_descriptor);
+            assert_eq!(pz.download("stored.txt")?, b"stored while streaming\n");
+            assert_eq!(pz.download("deflated.txt")?, b"streamed line\n".repeat(100));
+            let pz = PartialZip::new(&address.join("/files/tail.zip")?)?;
+            assert!(!pz.stat("big.bin")?.has_data_descriptor);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that the fingerprint depends only on the files, and without fetching them
+    async fn test_directory_fingerprint() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/test.zip")?)?;
+            let before = pz.stats();
+            let fingerprint = pz.directory_fingerprint()?;
+            assert_eq!(pz.stats(), before, "fetched something for the fingerprint");
+            // the same files, stored with different compressions and offsets
+            let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+            let options = zip::write::SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Stored);
+            for (name, content) in [("2.txt", b"BBBB\n"), ("1.txt", b"AAAA\n")] {
+                writer.start_file(name, options)?;
+                std::io::Write::write_all(&mut writer, content)?;
+            }
+            let rewritten = PartialZip::from_bytes(writer.finish()?.into_inner())?;
+            assert_eq!(rewritten.directory_fingerprint()?, fingerprint);
+            let other = PartialZip::new(&address.join("/files/badcrc.zip")?)?;
+            assert_ne!(other.directory_fingerprint()?, fingerprint);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test the download to a file, replaced only when the download succeeds
+    async fn test_download_to_file() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/test.zip")?)?;
+            let out_dir = tempfile::tempdir()?;
+            let dest = out_dir.path().join("out.txt");
+            pz.download_to_file("2.txt", &dest)?;
+            assert_eq!(std::fs::read(&dest)?, b"BBBB\n");
+            let failed = pz.download_to_file("missing.txt", &dest);
+            assert!(failed.is_err(), "didn't throw an error with a missing file");
+            // the previous content is still there, and the temporary file is gone
+            assert_eq!(std::fs::read(&dest)?, b"BBBB\n");
+            assert_eq!(std::fs::read_dir(out_dir.path())?.count(), 1);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that a new file is written only if there's none at the destination
+    async fn test_write_new_file() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/test.zip")?)?;
+            let out_dir = tempfile::tempdir()?;
+            let dest = out_dir.path().join("out.txt");
+            write_new_file(&dest, |file| pz.download_to_write("2.txt", file))?;
+            assert_eq!(std::fs::read(&dest)?, b"BBBB\n");
+            let existing = write_new_file(&dest, |file| pz.download_to_write("1.txt", file));
+            assert!(matches!(
+                existing,
+                Err(PartialZipError::IOError(e)) if e.kind() == std::io::ErrorKind::AlreadyExists
+            ));
+            // the existing file is kept, and the temporary file is gone
+            assert_eq!(std::fs::read(&dest)?, b"BBBB\n");
+            assert_eq!(std::fs::read_dir(out_dir.path())?.count(), 1);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[test]
+    /// Test that the threads writing the same new file at the same time each have their own temporary file
+    fn test_write_new_file_threads() -> Result<()> {
+        let out_dir = tempfile::tempdir()?;
+        let dest = out_dir.path().join("out.txt");
+        let barrier = std::sync::Barrier::new(2);
+        // the longer content is written first, then the other one over its start if the file were the same
+        let results: Vec<_> = std::thread::scope(|scope| {
+            let writers: Vec<_> = [b"long content".as_slice(), b"short"]
+                .into_iter()
+                .enumerate()
+                .map(|(i, content)| {
+                    let (barrier, dest) = (&barrier, &dest);
+                    scope.spawn(move || {
+                        write_new_file(dest, |file| {
+                            barrier.wait();
+                            if i == 1 {
+                                barrier.wait();
+                            }
+                            file.write_all(content)?;
+                            if i == 0 {
+                                barrier.wait();
+                            }
+                            Ok(())
+                        })
+                    })
+                })
+                .collect();
+            writers
+                .into_iter()
+                .map(|writer| writer.join().expect("writer panicked"))
+                .collect()
+        });
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+        let written = std::fs::read(&dest)?;
+        assert!(
+            written == b"long content" || written == b"short",
+            "wrote {written:?}"
+        );
+        assert_eq!(std::fs::read_dir(out_dir.path())?.count(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    /// Test that an interrupted download is completed, resuming it only for the stored files
+    async fn test_resume_download() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let out_dir = tempfile::tempdir()?;
+            let dest = out_dir.path().join("plain.txt");
+            let pz = PartialZip::new(&address.join("/files/aes.zip")?)?;
+            std::fs::write(&dest, b"this one ")?;
+            assert_eq!(
+                pz.resume_download("plain.txt", &dest)?,
+                ResumeStatus::Resumed(9)
+            );
+            assert_eq!(std::fs::read(&dest)?, b"this one is not encrypted\n");
+            // nothing left to fetch
+            assert_eq!(
+                pz.resume_download("plain.txt", &dest)?,
+                ResumeStatus::Resumed(26)
+            );
+            assert_eq!(std::fs::read(&dest)?, b"this one is not encrypted\n");
+
+            let dest = out_dir.path().join("1.txt");
+            let pz = PartialZip::new(&address.join("/files/test.zip")?)?;
+            std::fs::write(&dest, b"AA")?;
+            assert_eq!(
+                pz.resume_download("1.txt", &dest)?,
+                ResumeStatus::Restarted(2)
+            );
+            assert_eq!(std::fs::read(&dest)?, b"AAAA\n");
+            // longer than the file, written again
+            std::fs::write(&dest, b"not a part of 1.txt")?;
+            assert_eq!(
+                pz.resume_download("1.txt", &dest)?,
+                ResumeStatus::Restarted(0)
+            );
+            assert_eq!(std::fs::read(&dest)?, b"AAAA\n");
+            let dest = out_dir.path().join("2.txt");
+            assert_eq!(
+                pz.resume_download("2.txt", &dest)?,
+                ResumeStatus::Restarted(0)
+            );
+            assert_eq!(std::fs::read(&dest)?, b"BBBB\n");
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test the download of the compressed content of a file
+    async fn test_download_raw() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/lzma.zip")?)?;
+            let (raw, method) = pz.download_raw("lzma.txt")?;
+            assert_eq!(method, PartialZipCompressionMethod::Lzma);
+            assert_eq!(
+                raw.len().try_into(),
+                Ok(pz.stat("lzma.txt")?.compressed_size)
+            );
+            // zip LZMA header: LZMA SDK version and size of the properties
+            assert!(raw.starts_with(&[0x09, 0x04, 0x05, 0x00]));
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that a [`PartialZip`] can be shared between threads
+    async fn test_shared_between_threads() -> Result<()> {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = Arc::new(PartialZip::new(&address.join("/files/test.zip")?)?);
+            assert_send_sync(&pz);
+            let threads: Vec<_> = (0..4)
+                .map(|i| {
+                    let pz = pz.clone();
+                    std::thread::spawn(move || {
+                        pz.download(if i % 2 == 0 { "1.txt" } else { "2.txt" })
+                    })
+                })
+                .collect();
+            for (i, thread) in threads.into_iter().enumerate() {
+                let content = thread.join().expect("download thread panicked")?;
+                assert_eq!(content, if i % 2 == 0 { b"AAAA\n" } else { b"BBBB\n" });
+            }
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test the download of a part of a file, stored or compressed
+    async fn test_download_range() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/tail.zip")?)?;
+            let full = pz.download("big.bin")?;
+            assert_eq!(pz.download_range("big.bin", 1000, 100)?, &full[1000..1100]);
+            // truncated at the end of the file
+            assert_eq!(pz.download_range("big.bin", 61_990, 100)?, &full[61_990..]);
+            assert!(pz.download_range("big.bin", 70_000, 100)?.is_empty());
+            let pz = PartialZip::new(&address.join("/files/lzma.zip")?)?;
+            assert_eq!(pz.download_range("lzma.txt", 9, 9)?, b"9e3779b1\n");
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that only the start of the files is fetched and decompressed
+    async fn test_peek() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let reader = PartialReader::new(&address.join("/files/tail.zip")?)?
+                .with_cache_size(0)
+                .with_tail_prefetch(0);
+            let pz = PartialZip::from_reader(reader)?;
+            let before = pz.stats().bytes;
+            assert_eq!(pz.peek("big.bin", 4)?, b"\x22\x91\xd8\xcd");
+            // the signature and the name and extra lengths of the local header, its signature again
+            // to check the encryption and the 4 bytes, without the central directory
+            assert_eq!(pz.stats().bytes - before, 4 + 2 + 2 + 4 + 4);
+            assert_eq!(pz.peek("small/00.txt", 100)?, pz.download("small/00.txt")?);
+            assert!(pz.peek("big.bin", 0)?.is_empty());
+            let pz = PartialZip::new(&address.join("/files/lzma.zip")?)?;
+            assert_eq!(pz.peek("lzma.txt", 12)?, b"00000000\n9e3");
+            let pz = PartialZip::new(&address.join("/files/test.zip")?)?;
+            assert_eq!(pz.peek("1.txt", 2)?, b"AA");
+            assert!(
+                matches!(
+                    pz.peek("nope.txt", 2),
+                    Err(PartialZipError::FileNotFound { .. })
+                ),
+                "didn't throw an error with a missing file"
+            );
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test the download of a LZMA compressed file
+    async fn test_download_lzma() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/lzma.zip")?)?;
+            let list = pz.list_detailed();
+            assert_eq!(
+                list[0].compression_method,
+                PartialZipCompressionMethod::Lzma
+            );
+            assert!(list[0].supported);
+            let downloaded = pz.download("lzma.txt")?;
+            // the content is checked against the CRC32 while decompressing
+            assert_eq!(downloaded.len(), 180_000);
+            assert!(downloaded.starts_with(b"00000000\n9e3779b1\n"));
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test the download of a `PPMd` compressed file, or the error telling the feature is missing
+    async fn test_download_ppmd() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/ppmd.zip")?)?;
+            let list = pz.list_detailed();
+            assert_eq!(
+                list[0].compression_method,
+                PartialZipCompressionMethod::Ppmd
+            );
+            assert_eq!(list[0].supported, cfg!(feature = "ppmd"));
+            assert_eq!(list[0].version_needed, 63);
+            let downloaded = pz.download("ppmd.txt");
+            if cfg!(feature = "ppmd") {
+                let downloaded = downloaded?;
+                assert_eq!(downloaded.len(), 21490);
+                assert!(downloaded.starts_with(b"line 0: the quick brown fox"));
+                assert!(downloaded
+                    .ends_with(b"line 399: the quick brown fox jumps over the lazy dog\n"));
+            } else {
+                assert!(matches!(
+                    downloaded,
+                    Err(PartialZipError::UnsupportedCompression(98))
+                ));
+            }
+            assert_eq!(
+                PartialZipError::UnsupportedCompression(6).to_string(),
+                "Implode compression (method 6) is not supported"
+            );
+            Ok(())
+        })
+        .await?
+    }
+
+    #[test]
+    /// Test the supported flag follows the methods of the build, and that they can be decompressed
+    fn test_supported_methods() -> Result<()> {
+        let methods = PartialZip::supported_methods();
+        for method in [
+            PartialZipCompressionMethod::Stored,
+            PartialZipCompressionMethod::Deflated,
+            PartialZipCompressionMethod::Deflate64,
+            PartialZipCompressionMethod::Bzip2,
+            PartialZipCompressionMethod::Zstd,
+            PartialZipCompressionMethod::Lzma,
+        ] {
+            assert!(methods.contains(&method), "{method:?} is not supported");
+        }
+        assert_eq!(
+            methods.contains(&PartialZipCompressionMethod::Ppmd),
+            cfg!(feature = "ppmd")
+        );
+        assert!(!methods.contains(&PartialZipCompressionMethod::Unsupported));
+        for archive in ["test.zip", "deflate64.zip", "lzma.zip", "ppmd.zip"] {
+            let pz = PartialZip::from_bytes(std::fs::read(format!("./testdata/{archive}"))?)?;
+            for file in pz.list_detailed() {
+                assert_eq!(
+                    file.supported,
+                    methods.contains(&file.compression_method),
+                    "{archive}: {}",
+                    file.name
+                );
+                assert_eq!(pz.download(&file.name).is_ok(), file.supported);
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    /// Test the download of a Deflate64 compressed file
+    async fn test_download_deflate64() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/deflate64.zip")?)?;
+            let list = pz.list_detailed();
+            assert_eq!(
+                list[0].compression_method,
+                PartialZipCompressionMethod::Deflate64
+            );
+            assert!(list[0].supported);
+            let mut downloaded = Vec::new();
+            pz.download_to_write("deflate64.txt", &mut downloaded)?;
+            // the content is checked against the CRC32 while decompressing
+            assert_eq!(downloaded.len(), 70_890);
+            assert!(downloaded.starts_with(b"entry 0 of the deflate64 fixture\n"));
+            assert!(downloaded.ends_with(b"entry 1999 of the deflate64 fixture\n"));
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test the download of a file encrypted with `WinZip` AES
+    async fn test_download_encrypted() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/aes.zip")?)?;
+            let downloaded = pz.download_encrypted("secret.txt", b"partialzip")?;
+            assert_eq!(downloaded.len(), 32_890);
+            assert!(downloaded.starts_with(b"secret line 0\nsecret line 1\n"));
+            assert!(
+                matches!(
+                    pz.download_encrypted("secret.txt", b"wrong"),
+                    Err(PartialZipError::InvalidPassword)
+                ),
+                "didn't throw an error with a wrong password"
+            );
+            assert!(
+                pz.download("secret.txt").is_err(),
+                "downloaded an encrypted file without password"
+            );
+            assert_eq!(pz.download("plain.txt")?, b"this one is not encrypted\n");
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that the CRC32 is verified while downloading
+    async fn test_download_verified() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/test.zip")?)?;
+            let downloaded = pz.download_verified("1.txt")?;
+            assert_eq!(downloaded, vec![0x41, 0x41, 0x41, 0x41, 0xa]);
+            let pz = PartialZip::new(&address.join("/files/badcrc.zip")?)?;
+            let downloaded = pz.download_verified("1.txt");
+            assert!(
+                matches!(
+                    downloaded,
+                    Err(PartialZipError::CrcMismatch {
+                        expected: 0x265b_77fe,
+                        actual: 0xf8f6_c911
+                    })
+                ),
+                "didn't throw an error on a CRC mismatch"
+            );
+            let downloaded = pz.download_verified("2.txt")?;
+            assert_eq!(downloaded, vec![0x42, 0x42, 0x42, 0x42, 0xa]);
+            let (downloaded, details) = pz.download_with_info("2.txt")?;
+            assert_eq!(downloaded, b"BBBB\n");
+            assert_eq!(details.name, "2.txt");
+            assert_eq!(details.uncompressed_size, 5);
+            assert_eq!(details.compressed_size, 7);
+            assert_eq!(
+                details.compression_method,
+                zip::CompressionMethod::Deflated.into()
+            );
+            assert!(matches!(
+                pz.download_with_info("1.txt"),
+                Err(PartialZipError::CrcMismatch { .. })
+            ));
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Check that a content shorter than the size stored in the archive is an error
+    async fn test_size_mismatch() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/badsize.zip")?)?;
+            let mut content = Vec::new();
+            let downloaded = pz.download_to_write("1.txt", &mut content);
+            assert!(
+                matches!(
+                    downloaded,
+                    Err(PartialZipError::SizeMismatch {
+                        expected: 6,
+                        actual: 5
+                    })
+                ),
+                "didn't throw an error on a size mismatch"
+            );
+            let downloaded = pz.download_to_write_with_progress("1.txt", &mut content, &mut |_| {});
+            assert!(matches!(
+                downloaded,
+                Err(PartialZipError::SizeMismatch { .. })
+            ));
+            assert!(matches!(
+                pz.download_verified("1.txt"),
+                Err(PartialZipError::SizeMismatch { .. })
+            ));
+            let out_dir = tempfile::tempdir()?;
+            assert!(matches!(
+                pz.download_all(out_dir.path()),
+                Err(PartialZipError::SizeMismatch { .. })
+            ));
+            let downloaded = pz.download("2.txt")?;
+            assert_eq!(downloaded, vec![0x42, 0x42, 0x42, 0x42, 0xa]);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[cfg(feature = "progressbar")]
+    #[tokio::test]
+    /// See if the code with the progress bar at least run
+    async fn test_download_progressbar() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/test.zip")?)?;
+            let downloaded = pz.download_with_progressbar("1.txt")?;
+            assert_eq!(downloaded, vec![0x41, 0x41, 0x41, 0x41, 0xa]);
+            let downloaded = pz.download_with_progressbar("2.txt")?;
+            assert_eq!(downloaded, vec![0x42, 0x42, 0x42, 0x42, 0xa]);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that downloading files that are not present in the archive throws an error
+    async fn test_download_invalid_file() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/test.zip")?)?;
+            let downloaded = pz.download("414141.txt");
+            assert!(
+                matches!(downloaded, Err(PartialZipError::FileNotFound { .. })),
+                "didn't throw an error when a file is not in the zip"
+            );
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that invalid zip archives are rejected
+    async fn test_invalid_header() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(
+                &address
+                    .join("/files/invalid.zip")
+                    .expect("cannot join invalid URL"),
+            );
+            assert!(
+                matches!(
+                    pz,
+                    Err(PartialZipError::NotAZip {
+                        ref content_type,
+                        source: ZipError::InvalidArchive(_)
+                    }) if content_type == "application/zip"
+                ),
+                "didn't throw an error with invalid header"
+            );
+        })
+        .await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    /// Test that the URL extension doesn't matter, and that the content type is reported when it's not a zip
+    async fn test_content_type() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/latest")?)?;
+            assert_eq!(pz.list_names().len(), 2);
+            let pz = PartialZip::new(&address.join("/page")?);
+            assert!(
+                matches!(
+                    pz,
+                    Err(PartialZipError::NotAZip { ref content_type, .. }) if content_type == "text/html"
+                ),
+                "didn't report the content type"
+            );
+            let mut reader = PartialReader::new(&address.join("/latest")?)?;
+            assert_eq!(reader.content_type(), None);
+            reader.probe()?;
+            assert_eq!(reader.content_type(), Some("application/zip"));
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that an HTTP error on the archive is reported as it is
+    async fn test_http_status() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/missing.zip")?);
+            assert!(
+                matches!(pz, Err(PartialZipError::HttpStatus(404))),
+                "didn't throw an error with a missing archive"
+            );
+            let pz = PartialZip::new(&address.join("/auth/test.zip")?);
+            assert!(
+                matches!(pz, Err(PartialZipError::HttpStatus(401))),
+                "didn't throw an error without credentials"
+            );
+            Ok(())
+        })
+        .await?
+    }
+
+    #[test]
+    /// Test that the FTP support and its options are available in curl
+    fn test_ftp_mode() -> Result<()> {
+        // nothing listens on port 1, the connection has to be refused
+        for mode in [FtpMode::Passive, FtpMode::PassiveNoEpsv, FtpMode::Active] {
+            let probe = PartialReader::new(&"ftp://127.0.0.1:1/test.zip")?
+                .with_ftp_mode(mode)
+                .probe();
+            assert!(
+                matches!(probe, Err(PartialZipError::CURLError(ref e)) if e.is_couldnt_connect()),
+                "unexpected result with {mode:?}: {probe:?}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    /// Test that a file is downloaded over FTP restarting the transfers with `REST`, without the whole archive
+    fn test_ftp_range() -> Result<()> {
+        // bigger than the tail fetched with the central directory
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        writer.start_file("big.bin", options)?;
+        writer.write_all(&vec![0x42; 0x0010_0000])?;
+        writer.start_file("small.txt", options)?;
+        writer.write_all(b"small\n")?;
+        let (url, sent) = spawn_ftp_server(writer.finish()?.into_inner())?;
+        let pz = PartialZip::new(&url)?;
+        assert_eq!(pz.download("small.txt")?, b"small\n");
+        let size = pz.file_size();
+        assert!(
+            sent.load(Ordering::SeqCst) < size,
+            "sent {} bytes of {size}",
+            sent.load(Ordering::SeqCst)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    /// Test that the download speed is limited
+    async fn test_max_recv_speed() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let url = address.join("/files/lzma.zip")?;
+            let start = Instant::now();
+            PartialZip::new(&url)?.download("lzma.txt")?;
+            let unthrottled = start.elapsed();
+            // about 93 KB to fetch at 64 KB/s
+            let start = Instant::now();
+            PartialZip::from_reader(PartialReader::new(&url)?.with_max_recv_speed(0x0001_0000))?
+                .download("lzma.txt")?;
+            let throttled = start.elapsed();
+            assert!(
+                throttled >= Duration::from_millis(500) && throttled > unthrottled,
+                "download not throttled: {throttled:?} vs {unthrottled:?}"
+            );
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that the server errors are retried with the given backoff
+    async fn test_retries() -> Result<()> {
+        use std::io::Read;
+
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let url = address.join("/flaky/test.zip")?;
+            // first failure
+            let mut reader = PartialReader::new(&url)?;
+            let read = reader.read_exact(&mut [0; 4]);
+            assert!(read.is_err(), "didn't throw an error without retries");
+            // second failure, then success
+            let mut reader = PartialReader::new(&url)?.with_retries(3, Duration::from_millis(100));
+            let start = Instant::now();
+            let mut signature = [0; 4];
+            reader.read_exact(&mut signature)?;
+            assert_eq!(&signature, b"PK\x03\x04");
+            // HEAD, failed range request, successful one
+            assert_eq!(reader.requests(), 3);
+            assert!(start.elapsed() >= Duration::from_millis(100));
+            Ok(())
+        })
+        .await?
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    /// Test a ZIP64 archive bigger than 4 GiB
+    async fn test_zip64() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("partialzip-{}.zip", std::process::id()));
+        write_zip64(&path)?;
+        let address = spawn_server()?.address;
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        let url = address.join(&format!("/tmp/{file_name}"))?;
+        let result = tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&url)?;
+            assert!(pz.file_size() > ZIP64_OFFSET);
+            assert_eq!(pz.list_names(), ["first.txt", "second.txt"]);
+            assert_eq!(pz.download("first.txt")?, b"before the hole\n");
+            assert_eq!(pz.download("second.txt")?, b"after the hole\n");
+            Ok(())
+        })
+        .await?;
+        std::fs::remove_file(&path)?;
+        result
+    }
+
+    #[tokio::test]
+    /// Test a big entry read sequentially is fetched in a few growing ranges
+    async fn test_sequential_prefetch() -> Result<()> {
+        const ENTRY_SIZE: usize = 16 * 1024 * 1024;
+        let path = std::env::temp_dir().join(format!("sequential-{}.zip", std::process::id()));
+        let content: Vec<u8> = (0..ENTRY_SIZE)
+            .map(|i| u8::try_from(i % 251).unwrap_or_default())
+            .collect();
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(&path)?);
+        writer.start_file(
+            "big.bin",
+            zip::write::SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Stored),
+        )?;
+        std::io::Write::write_all(&mut writer, &content)?;
+        writer.finish()?;
+        let address = spawn_server()?.address;
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        let url = address.join(&format!("/tmp/{file_name}"))?;
+        let result = tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&url)?;
+            let before = pz.requests();
+            assert!(pz.download("big.bin")? == content);
+            // 1 MiB at a time otherwise, the size of the buffer of the archive
+            let requests = pz.requests() - before;
+            assert!(requests < 8, "{requests} requests");
+            Ok(())
+        })
+        .await?;
+        std::fs::remove_file(&path)?;
+        result
+    }
+
+    #[tokio::test]
+    /// Test that invalid URLs don't get through
+    async fn test_invalid_url() -> Result<()> {
+        spawn_server()?;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&"invalid URL");
+            assert!(
+                matches!(pz, Err(PartialZipError::InvalidUrl)),
+                "didn't throw an error with invalid URL"
+            );
+            if let Err(e) = pz {
+                println!("{e:?}");
+            }
+        })
+        .await?;
+        Ok(())
+    }
+
+    #[test]
+    /// Test that we can open files over file:// not only http/https, with the native paths of every platform
+    fn test_file_protocol() -> Result<()> {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("testdata/test.zip");
+        let url = Url::from_file_path(&d)
+            .map_err(|()| anyhow::anyhow!("{} isn't absolute", d.display()))?;
+        // the host can also be localhost, with the same path
+        let localhost = url.as_str().replacen("file://", "file://localhost", 1);
+        assert_eq!(
+            PartialZip::new(&localhost)?.list_names(),
+            vec!["1.txt", "2.txt"]
+        );
+        let pz = PartialZip::new(&url)?;
+        assert_eq!(pz.stats(), ReadStats::default(), "not read as a local file");
+        let list = pz.list_detailed();
+        assert_eq!(
+            list,
+            vec![
+                PartialZipFileDetailed {
+                    name: "1.txt".to_string(),
+                    compressed_size: 7,
+                    uncompressed_size: 5,
+                    compression_method: zip::CompressionMethod::Deflated.into(),
+                    supported: true,
+                    last_modified: NaiveDateTime::parse_from_str(
+                        "2022-08-12T15:24:30",
+                        "%Y-%m-%dT%H:%M:%S"
+                    )
+                    .ok(),
+                    is_dir: false,
+                    is_symlink: false,
+                    has_data_descriptor: true,
+                    version_needed: 20,
+                },
+                PartialZipFileDetailed {
+                    name: "2.txt".to_string(),
+                    compressed_size: 7,
+                    uncompressed_size: 5,
+                    compression_method: zip::CompressionMethod::Deflated.into(),
+                    supported: true,
+                    last_modified: NaiveDateTime::parse_from_str(
+                        "2022-08-12T15:24:36",
+                        "%Y-%m-%dT%H:%M:%S"
+                    )
+                    .ok(),
+                    is_dir: false,
+                    is_symlink: false,
+                    has_data_descriptor: true,
+                    version_needed: 20,
+                }
+            ]
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    /// Test that the url getter works
+    fn test_url_getter() -> Result<()> {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("testdata/test.zip");
+        let url = format!("file://localhost{}", d.display());
+        let pz = PartialZip::new(&url)?;
+        assert_eq!(url, pz.url());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    /// Test that the file size getter works
+    fn test_file_size_getter() -> Result<()> {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("testdata/test.zip");
+        let url = format!("file://localhost{}", d.display());
+        let pz = PartialZip::new(&url)?;
+        // TODO get the filesize from filesystem
+        assert_eq!(368, pz.file_size());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    /// Test that local files are read directly and always support ranges
+    fn test_check_range_on_local_file() -> Result<()> {
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("testdata/test.zip");
+        let pz = PartialZip::new_check_range(&format!("file://localhost{}", d.display()), true)?;
+        let downloaded = pz.download("2.txt")?;
+        assert_eq!(downloaded, vec![0x42, 0x42, 0x42, 0x42, 0xa]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    /// Test that it throws an error when the server doesn't support range requests
+    async fn test_check_range_on_not_ranging_server() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new_check_range(&address.join("/norange/test.zip")?, true);
+            assert!(
+                matches!(pz, Err(PartialZipError::RangeNotSupported)),
+                "didn't throw an error with range not supported"
+            );
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that the whole archive sent by a server ignoring the range is downloaded only once
+    async fn test_ignored_range() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            // small reads at any offset, not whole blocks from the start of the archive
+            let reader = PartialReader::new(&address.join("/norange/tail.zip")?)?
+                .with_cache_size(0)
+                .with_tail_prefetch(0);
+            let pz = PartialZip::from_reader(reader)?;
+            let expected = PartialZip::new(&address.join("/files/tail.zip")?)?;
+            assert_eq!(pz.list_names(), expected.list_names());
+            let before = pz.stats();
+            assert_eq!(
+                pz.download("small/07.txt")?,
+                expected.download("small/07.txt")?
+            );
+            assert_eq!(pz.download("big.bin")?, expected.download("big.bin")?);
+            // the following reads are served from the archive kept in memory
+            assert_eq!(pz.stats(), before);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that the range header is correctly detected
+    async fn test_range_support() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new_check_range(&address.join("/files/test.zip")?, true)?;
+            let downloaded = pz.download("1.txt")?;
+            assert_eq!(downloaded, vec![0x41, 0x41, 0x41, 0x41, 0xa]);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Check if we follow redirects correctly
+    async fn test_redirect() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/redirect")?)?;
+            let list = pz.list_detailed();
+            assert_eq!(
+                list,
+                vec![
+                    PartialZipFileDetailed {
+                        name: "1.txt".to_string(),
+                        compressed_size: 7,
+                        uncompressed_size: 5,
+                        compression_method: zip::CompressionMethod::Deflated.into(),
+                        supported: true,
+                        last_modified: NaiveDateTime::parse_from_str(
+                            "2022-08-12T15:24:30",
+                            "%Y-%m-%dT%H:%M:%S"
+                        )
+                        .ok(),
+                        is_dir: false,
+                        is_symlink: false,
+                        has_data_descriptor: true,
+                        version_needed: 20,
+                    },
+                    PartialZipFileDetailed {
+                        name: "2.txt".to_string(),
+                        compressed_size: 7,
+                        uncompressed_size: 5,
+                        compression_method: zip::CompressionMethod::Deflated.into(),
+                        supported: true,
+                        last_modified: NaiveDateTime::parse_from_str(
+                            "2022-08-12T15:24:36",
+                            "%Y-%m-%dT%H:%M:%S"
+                        )
+                        .ok(),
+                        is_dir: false,
+                        is_symlink: false,
+                        has_data_descriptor: true,
+                        version_needed: 20,
+                    }
+                ]
+            );
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test the URL resolved after the redirects, and the limits on the redirects followed
+    async fn test_redirect_limits() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/redirect")?)?;
+            assert_eq!(
+                pz.resolved_url(),
+                Some(address.join("/files/test.zip")?.to_string())
+            );
+            let pz = PartialZip::new(&address.join("/files/test.zip")?)?;
+            assert_eq!(
+                pz.resolved_url(),
+                Some(address.join("/files/test.zip")?.to_string())
+            );
+            let reader = PartialReader::new(&address.join("/redirect")?)?.with_max_redirections(0);
+            assert!(
+                matches!(
+                    PartialZip::from_reader(reader),
+                    Err(PartialZipError::CURLError(_))
+                ),
+                "followed a redirect over the limit"
+            );
+            // the test server is plain HTTP, there's nothing to downgrade
+            let reader =
+                PartialReader::new(&address.join("/redirect")?)?.with_refuse_downgrades(true);
+            assert_eq!(PartialZip::from_reader(reader)?.list_names().len(), 2);
+            assert!(crate::utils::is_https_url("HTTPS://www.test.com/1.zip"));
+            assert!(!crate::utils::is_https_url("http://www.test.com/1.zip"));
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that only the redirects to the same origin are followed with the same origin policy
+    async fn test_redirect_policy() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let reader = PartialReader::new(&address.join("/redirect")?)?
+                .with_redirect_policy(RedirectPolicy::SameOrigin);
+            let pz = PartialZip::from_reader(reader)?;
+            assert_eq!(
+                pz.resolved_url(),
+                Some(address.join("/files/test.zip")?.to_string())
+            );
+            assert_eq!(pz.download("2.txt")?, vec![0x42, 0x42, 0x42, 0x42, 0xa]);
+            let reader = PartialReader::new(&address.join("/cross/test.zip")?)?
+                .with_redirect_policy(RedirectPolicy::SameOrigin);
+            assert!(
+                matches!(
+                    PartialZip::from_reader(reader),
+                    Err(PartialZipError::RedirectRefused(ref target)) if target.starts_with("http://localhost:")
+                ),
+                "followed a redirect to another origin"
+            );
+            let reader = PartialReader::new(&address.join("/redirect")?)?
+                .with_redirect_policy(RedirectPolicy::SameOrigin)
+                .with_max_redirections(0);
+            assert!(
+                matches!(
+                    PartialZip::from_reader(reader),
+                    Err(PartialZipError::CURLError(_))
+                ),
+                "followed a redirect over the limit"
+            );
+            let pz = PartialZip::new(&address.join("/cross/test.zip")?)?;
+            assert_eq!(pz.list_names(), vec!["1.txt", "2.txt"]);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that the basic auth credentials are sent, also after a redirect, but to another host only on demand
+    async fn test_basic_auth() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/auth/test.zip")?);
+            assert!(pz.is_err(), "opened an archive without credentials");
+            let pz =
+                PartialZip::new_with_auth(&address.join("/auth/test.zip")?, "user", "pass", true)?;
+            let downloaded = pz.download("1.txt")?;
+            assert_eq!(downloaded, vec![0x41, 0x41, 0x41, 0x41, 0xa]);
+            let pz =
+                PartialZip::new_with_auth(&address.join("/auth/redirect")?, "user", "pass", false)?;
+            let downloaded = pz.download("2.txt")?;
+            assert_eq!(downloaded, vec![0x42, 0x42, 0x42, 0x42, 0xa]);
+            let url = address.join("/auth/cross")?;
+            assert!(
+                PartialZip::new_with_auth(&url, "user", "pass", false).is_err(),
+                "sent the credentials to another host"
+            );
+            let reader = PartialReader::new_with_auth(&url, "user", "pass", false)?
+                .with_unrestricted_auth(true);
+            assert_eq!(
+                PartialZip::from_reader(reader)?.list_names(),
+                ["1.txt", "2.txt"]
+            );
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that a configured reader is reused without probing the archive again
+    async fn test_from_reader() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let mut reader = PartialReader::new_with_auth(
+                &address.join("/auth/test.zip")?,
+                "user",
+                "pass",
+                true,
+            )?;
+            reader.probe()?;
+            // HEAD and range check
+            assert_eq!(reader.requests(), 2);
+            let pz = PartialZip::from_reader(reader)?;
+            assert_eq!(pz.list_names(), ["1.txt", "2.txt"]);
+            assert_eq!(pz.download("2.txt")?, b"BBBB\n");
+            // the archive fits in the tail prefetched with the first read
+            assert_eq!(pz.requests(), 3);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that the custom headers are sent with the HEAD and all the range requests
+    async fn test_custom_headers() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let url = address.join("/apikey/test.zip")?;
+            assert!(
+                PartialZip::new(&url).is_err(),
+                "opened an archive without the API key"
+            );
+            let headers = vec![("X-Api-Key".to_string(), "secret".to_string())];
+            let pz = PartialZip::new_with_headers(&url, headers, true)?;
+            let downloaded = pz.download("1.txt")?;
+            assert_eq!(downloaded, vec![0x41, 0x41, 0x41, 0x41, 0xa]);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that the custom User-Agent is sent with the HEAD and all the range requests
+    async fn test_user_agent() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let url = address.join("/useragent/test.zip")?;
+            assert!(
+                PartialZip::new(&url).is_err(),
+                "opened an archive with the default User-Agent"
+            );
+            let reader = PartialReader::new(&url)?.with_user_agent("partialzip-test");
+            let pz = PartialZip::from_reader(reader)?;
+            let downloaded = pz.download("1.txt")?;
+            assert_eq!(downloaded, vec![0x41, 0x41, 0x41, 0x41, 0xa]);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    /// Test that the HEAD and all the range requests go through the Unix domain socket
+    async fn test_unix_socket() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let socket = dir.path().join("partialzip.sock");
+        let listener = std::os::unix::net::UnixListener::bind(&socket)?;
+        let server =
+            HttpServer::new(|| App::new().service(fs::Files::new("/files/", "./testdata")))
+                .listen_uds(listener)?
+                .run();
+        tokio::spawn(server);
+        tokio::task::spawn_blocking(move || {
+            // nothing listens on this port, the request can only succeed through the socket
+            let url = Url::parse("http://localhost:1/files/test.zip")?;
+            assert!(PartialZip::new(&url).is_err());
+            let reader = PartialReader::new(&url)?
+                .with_unix_socket(&socket)
+                .with_cache_size(0)
+                .with_tail_prefetch(0);
+            let pz = PartialZip::from_reader(reader)?;
+            assert_eq!(pz.list_names(), vec!["1.txt", "2.txt"]);
+            let downloaded = pz.download("2.txt")?;
+            assert_eq!(downloaded, vec![0x42, 0x42, 0x42, 0x42, 0xa]);
+            assert!(pz.stats().requests > 1);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that a host resolved to the address of the server is used for all the requests
+    async fn test_resolve() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let port = address.port().unwrap_or_default();
+            // the reserved .invalid domain can't be resolved by the DNS
+            let url = Url::parse(&format!("http://partialzip.invalid:{port}/files/test.zip"))?;
+            assert!(PartialZip::new(&url).is_err());
+            let reader = PartialReader::new(&url)?
+                .with_resolve("partialzip.invalid", port, "127.0.0.1")
+                .with_cache_size(0)
+                .with_tail_prefetch(0);
+            let pz = PartialZip::from_reader(reader)?;
+            assert_eq!(pz.download("2.txt")?, b"BBBB\n");
+            assert!(pz.stats().requests > 2);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that the files of an archive with a truncated central directory are recovered from their local headers
+    async fn test_new_lenient() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let (pz, warnings) = PartialZip::new_lenient(&address.join("/files/test.zip")?)?;
+            assert!(
+                warnings.is_empty(),
+                "warnings for a valid archive: {warnings:?}"
+            );
+            assert_eq!(pz.len(), 2);
+            let url = address.join("/files/truncated.zip")?;
+            assert!(PartialZip::new(&url).is_err());
+            let (pz, warnings) = PartialZip::new_lenient(&url)?;
+            assert_eq!(pz.url(), url.as_str());
+            assert_eq!(pz.list_names(), vec!["1.txt", "2.txt"]);
+            assert_eq!(pz.download("1.txt")?, b"AAAA\n");
+            assert_eq!(pz.download_verified("2.txt")?, b"BBBB\n");
+            assert_eq!(warnings.len(), 2, "unexpected warnings {warnings:?}");
+            assert!(warnings[0].starts_with("invalid central directory"));
+            assert!(matches!(
+                PartialZip::new_lenient(&address.join("/files/nope.zip")?),
+                Err(PartialZipError::HttpStatus(404))
+            ));
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that all the requests go through a single connection, whatever the HTTP version
+    async fn test_http_version() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = Url::parse(&format!(
+            "http://127.0.0.1:{}/files/test.zip",
+            listener.local_addr()?.port()
+        ))?;
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+        let server =
+            HttpServer::new(|| App::new().service(fs::Files::new("/files/", "./testdata")))
+                .on_connect(move |_, _| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                })
+                .listen_auto_h2c(listener)?
+                .run();
+        tokio::spawn(server);
+        tokio::task::spawn_blocking(move || {
+            let mut versions = vec![HttpVersion::Auto, HttpVersion::Http11];
+            // the requests are multiplexed on the connection only with a libcurl supporting HTTP/2
+            if cfg!(feature = "http2") {
+                versions.push(HttpVersion::Http2PriorKnowledge);
+            }
+            for version in versions {
+                connections.store(0, Ordering::SeqCst);
+                let reader = PartialReader::new(&url)?
+                    .with_http_version(version)
+                    .with_cache_size(0)
+                    .with_tail_prefetch(0);
+                let pz = PartialZip::from_reader(reader)?;
+                assert_eq!(pz.download("2.txt")?, b"BBBB\n");
+                assert!(pz.stats().requests > 2);
+                assert_eq!(
+                    connections.load(Ordering::SeqCst),
+                    1,
+                    "{version:?} didn't reuse the connection"
+                );
+            }
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that the archive is asked without any compression, so the ranges match its bytes
+    async fn test_identity_encoding() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/identity/test.zip")?)?;
+            assert_eq!(pz.file_size(), 368);
+            let downloaded = pz.download("1.txt")?;
+            assert_eq!(downloaded, vec![0x41, 0x41, 0x41, 0x41, 0xa]);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that the cookies set on a redirect are sent back, including by the clones of the reader
+    async fn test_cookies() -> Result<()> {
+        use std::io::Read;
+
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            assert!(
+                PartialZip::new(&address.join("/cookie/test.zip")?).is_err(),
+                "opened an archive without the session cookie"
+            );
+            let pz = PartialZip::new(&address.join("/cookie/login")?)?;
+            let downloaded = pz.download("1.txt")?;
+            assert_eq!(downloaded, vec![0x41, 0x41, 0x41, 0x41, 0xa]);
+            let mut downloaded = Vec::new();
+            pz.open_entry("2.txt")?.read_to_end(&mut downloaded)?;
+            assert_eq!(downloaded, vec![0x42, 0x42, 0x42, 0x42, 0xa]);
+
+            let reader =
+                PartialReader::new(&address.join("/cookie/test.zip")?)?.with_cookies("session=abc");
+            let pz = PartialZip::from_reader(reader)?;
+            let downloaded = pz.download("1.txt")?;
+            assert_eq!(downloaded, vec![0x41, 0x41, 0x41, 0x41, 0xa]);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that a client certificate doesn't get in the way of the servers which don't ask for it,
+    /// and that it's refused clearly when curl has no TLS support
+    async fn test_client_cert() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let missing = std::env::temp_dir().join("partialzip-missing.pem");
+            let reader = PartialReader::new(&address.join("/files/test.zip")?)?.with_client_cert(
+                &missing,
+                &missing,
+                Some("password"),
+            );
+            let pz = PartialZip::from_reader(reader);
+            if curl::Version::get().ssl_version().is_none() {
+                assert!(
+                    matches!(pz, Err(PartialZipError::TlsUnavailable(_))),
+                    "didn't refuse the client certificate without TLS"
+                );
+                return Ok(());
+            }
+            let downloaded = pz?.download("1.txt")?;
+            assert_eq!(downloaded, vec![0x41, 0x41, 0x41, 0x41, 0xa]);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that the first working mirror is used, and the error of the last one is returned when all of them fail
+    async fn test_mirrors() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let missing = address.join("/files/missing.zip")?;
+            let page = address.join("/page")?;
+            let good = address.join("/files/test.zip")?;
+            let pz = PartialZip::new_with_mirrors(
+                &[missing.clone(), page.clone(), good.clone()],
+                false,
+            )?;
+            assert_eq!(pz.url(), good.to_string());
+            let downloaded = pz.download("1.txt")?;
+            assert_eq!(downloaded, vec![0x41, 0x41, 0x41, 0x41, 0xa]);
+            assert!(matches!(
+                PartialZip::new_with_mirrors(&[missing, page], false),
+                Err(PartialZipError::NotAZip { .. })
+            ));
+            assert!(matches!(
+                PartialZip::new_with_mirrors(&[], false),
+                Err(PartialZipError::InvalidUrl)
+            ));
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that the requests go through the proxy, authenticating against it
+    async fn test_proxy() -> Result<()> {
+        use std::io::Read;
+
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let url = "http://partialzip.invalid/proxied/test.zip";
+            let mut reader = PartialReader::new_with_proxy(&url, address.as_str(), false)?;
+            assert!(
+                reader.read_exact(&mut [0; 4]).is_err(),
+                "didn't throw an error without the proxy credentials"
+            );
+            let mut reader = PartialReader::new_with_proxy(&url, address.as_str(), false)?
+                .with_proxy_auth("user", "pass");
+            let mut signature = [0; 4];
+            reader.read_exact(&mut signature)?;
+            assert_eq!(&signature, b"PK\x03\x04");
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that a hanging server makes the request time out instead of blocking forever
+    async fn test_timeout() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let start = Instant::now();
+            let pz = PartialZip::new_with_timeout(
+                &address.join("/slow")?,
+                Duration::from_secs(1),
+                Duration::from_millis(500),
+                false,
+            );
+            assert!(
+                matches!(pz, Err(PartialZipError::CURLError(ref e)) if e.is_operation_timedout()),
+                "didn't time out"
+            );
+            assert!(start.elapsed() < Duration::from_secs(5), "took too long");
+            let pz = PartialZip::new_with_timeout(
+                &address.join("/files/test.zip")?,
+                Duration::from_secs(1),
+                Duration::from_secs(5),
+                false,
+            )?;
+            assert_eq!(pz.download("1.txt")?, vec![0x41, 0x41, 0x41, 0x41, 0xa]);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that a stalled range request is aborted
+    async fn test_low_speed_abort() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let start = Instant::now();
+            let reader = PartialReader::new(&address.join("/stalled/test.zip")?)?
+                .with_low_speed_abort(100, Duration::from_secs(1));
+            let pz = PartialZip::from_reader(reader);
+            assert!(
+                matches!(pz, Err(PartialZipError::CURLError(ref e)) if e.is_operation_timedout()),
+                "didn't abort the stalled transfer"
+            );
+            assert!(start.elapsed() < Duration::from_secs(5), "took too long");
+            let reader = PartialReader::new(&address.join("/files/test.zip")?)?
+                .with_low_speed_abort(100, Duration::from_secs(1));
+            let pz = PartialZip::from_reader(reader)?;
+            assert_eq!(pz.download("1.txt")?, vec![0x41, 0x41, 0x41, 0x41, 0xa]);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test cancelling a download, even while a request is stalled
+    async fn test_download_cancellable() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/test.zip")?)?;
+            let cancel = Arc::new(AtomicBool::new(false));
+            let mut content = Vec::new();
+            pz.download_to_write_cancellable("1.txt", &mut content, &cancel)?;
+            assert_eq!(content, b"AAAA\n");
+            cancel.store(true, Ordering::Relaxed);
+            assert!(matches!(
+                pz.download_to_write_cancellable("2.txt", &mut Vec::new(), &cancel),
+                Err(PartialZipError::Cancelled)
+            ));
+            // the token doesn't outlive its download
+            assert_eq!(pz.download("2.txt")?, b"BBBB\n");
+            // only the content is fetched, and it never comes
+            let directory = pz.export_directory()?;
+            let url = address.join("/stalled/test.zip")?;
+            let pz = PartialZip::new_with_directory(&url, &directory, pz.file_size())?;
+            let cancel = Arc::new(AtomicBool::new(false));
+            let token = cancel.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(300));
+                token.store(true, Ordering::Relaxed);
+            });
+            let start = Instant::now();
+            assert!(matches!(
+                pz.download_to_write_cancellable("1.txt", &mut Vec::new(), &cancel),
+                Err(PartialZipError::Cancelled)
+            ));
+            assert!(start.elapsed() < Duration::from_secs(5), "took too long");
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that a range read of an archive replaced since the probe fails instead of mixing the versions
+    async fn test_archive_changed() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            assert!(
+                matches!(
+                    PartialZip::new(&address.join("/changing/test.zip")?),
+                    Err(PartialZipError::ArchiveChanged)
+                ),
+                "didn't notice the archive changed"
+            );
+            // the ETag of the files served is sent back and still matches
+            let pz = PartialZip::new(&address.join("/files/test.zip")?)?;
+            assert_eq!(pz.download("2.txt")?, vec![0x42, 0x42, 0x42, 0x42, 0xa]);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that reading the same area twice is served from the block cache
+    async fn test_block_cache() -> Result<()> {
+        use std::io::{Read, Seek, SeekFrom};
+
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let url = address.join("/files/test.zip")?;
+            let mut reader = PartialReader::new(&url)?;
+            let mut first = [0; 4];
+            reader.read_exact(&mut first)?;
+            assert_eq!(&first, b"PK\x03\x04");
+            reader.seek(SeekFrom::Start(0))?;
+            let mut second = [0; 4];
+            reader.read_exact(&mut second)?;
+            assert_eq!(first, second);
+            assert_eq!(reader.cache_hits(), 1);
+
+            let mut reader = PartialReader::new(&url)?.with_cache_size(0);
+            reader.read_exact(&mut first)?;
+            reader.seek(SeekFrom::Start(0))?;
+            reader.read_exact(&mut second)?;
+            assert_eq!(first, second);
+            assert_eq!(reader.cache_hits(), 0);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test the statistics of the requests done and the bytes received
+    async fn test_stats() -> Result<()> {
+        use std::io::Read;
+
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let url = address.join("/files/test.zip")?;
+            let mut reader = PartialReader::new(&url)?.with_cache_size(0);
+            assert_eq!(reader.stats(), ReadStats::default());
+            let mut first = [0; 4];
+            reader.read_exact(&mut first)?;
+            // the HEAD probing the archive, then the range request
+            assert_eq!(
+                reader.stats(),
+                ReadStats {
+                    requests: 2,
+                    bytes: 4
+                }
+            );
+
+            // without fetching whole blocks, nor the whole end of the archive at once
+            let reader = PartialReader::new(&address.join("/files/tail.zip")?)?
+                .with_cache_size(0)
+                .with_tail_prefetch(0);
+            let pz = PartialZip::from_reader(reader)?;
+            let opened = pz.stats();
+            assert_eq!(opened.requests, pz.requests());
+            assert!(opened.bytes > 0 && opened.bytes < 66_222);
+            pz.download("big.bin")?;
+            let downloaded = pz.stats();
+            assert!(downloaded.requests > opened.requests);
+            assert!(downloaded.bytes >= opened.bytes + pz.stat("big.bin")?.compressed_size);
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that the whole archive is extracted recreating the directory tree
+    async fn test_download_all() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let out_dir = tempfile::tempdir()?;
+            let pz = PartialZip::new(&address.join("/files/nested.zip")?)?;
+            let written = pz.download_all(out_dir.path())?;
+            assert_eq!(
+                written,
+                vec![
+                    out_dir.path().join("dir/sub/a.txt"),
+                    out_dir.path().join("b.txt")
+                ]
+            );
+            assert_eq!(std::fs::read(&written[0])?, b"AAAA\n");
+            assert_eq!(std::fs::read(&written[1])?, b"BBBB\n");
+            assert!(out_dir.path().join("dir").is_dir());
+            Ok(())
+        })
+        .await?
+    }
+
+    #[cfg(unix)]
+    #[test]
+    /// Test that the permissions and the modification time of the entries are restored only when asked
+    fn test_download_all_preserving() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let date = zip::DateTime::from_date_and_time(2020, 1, 2, 3, 4, 6)?;
+        for (name, mode) in [("run.sh", 0o750), ("bogus.txt", 0o044)] {
+            writer.start_file(
+                name,
+                zip::write::SimpleFileOptions::default()
+                    .unix_permissions(mode)
+                    .last_modified_time(date),
+            )?;
+            std::io::Write::write_all(&mut writer, b"content")?;
+        }
+        let pz = PartialZip::from_bytes(writer.finish()?.into_inner())?;
+        let out_dir = tempfile::tempdir()?;
+        let written = pz.download_all_preserving(out_dir.path(), Preserve::Attributes)?;
+        let mode = |path: &std::path::Path| -> Result<u32> {
+            Ok(std::fs::metadata(path)?.permissions().mode() & 0o7777)
+        };
+        assert_eq!(mode(&written[0])?, 0o750);
+        // the owner couldn't read it
+        assert_eq!(mode(&written[1])?, 0o644);
+        let mtime = std::fs::metadata(&written[0])?
+            .modified()?
+            .duration_since(std::time::UNIX_EPOCH)?;
+        assert_eq!(mtime.as_secs(), 1_577_934_246);
+        let other_dir = tempfile::tempdir()?;
+        let path = pz.download_to_dir("run.sh", other_dir.path())?;
+        assert_ne!(
+            std::fs::metadata(&path)?.modified()?,
+            std::fs::metadata(&written[0])?.modified()?
+        );
+        pz.restore_attributes("run.sh", &path, Preserve::Attributes)?;
+        assert_eq!(mode(&path)?, 0o750);
+        assert_eq!(
+            std::fs::metadata(&path)?.modified()?,
+            std::fs::metadata(&written[0])?.modified()?
+        );
+        // the symlink is extracted as a file holding its target, without its 0o777 mode
+        let pz = PartialZip::from_bytes(std::fs::read("./testdata/symlink.zip")?)?;
+        let path = pz.download_to_dir("link", other_dir.path())?;
+        pz.restore_attributes("link", &path, Preserve::Attributes)?;
+        assert_eq!(mode(&path)? & 0o022, 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    /// Test that only the children of a directory are listed
+    async fn test_list_dir() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/nested.zip")?)?;
+            assert_eq!(pz.list_dir(""), vec!["b.txt", "dir/"]);
+            assert_eq!(pz.list_dir("dir"), vec!["sub/"]);
+            // the directory has no entry of its own
+            assert_eq!(pz.list_dir("dir/sub/"), vec!["a.txt"]);
+            assert!(pz.list_dir("b.txt").is_empty());
+            assert!(pz.list_dir("missing").is_empty());
+            Ok(())
+        })
+        .await?
+    }
+
+    #[cfg(feature = "tar")]
+    #[tokio::test]
+    /// Test that the files are repacked into a tar archive with their directories and symlinks
+    async fn test_repack_to_tar() -> Result<()> {
+        use std::io::Read;
+
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/nested.zip")?)?;
+            let mut tar = Vec::new();
+            pz.repack_to_tar(&mut tar)?;
+            let mut entries = Vec::new();
+            for entry in tar::Archive::new(tar.as_slice()).entries()? {
+                let mut entry = entry?;
+                let mut content = String::new();
+                entry.read_to_string(&mut content)?;
+                entries.push((
+                    entry.path()?.display().to_string(),
+                    entry.header().entry_type(),
+                    content,
+                ));
+            }
+            assert_eq!(
+                entries,
+                vec![
+                    ("dir".to_string(), tar::EntryType::Directory, String::new()),
+                    (
+                        "dir/sub/a.txt".to_string(),
+                        tar::EntryType::Regular,
+                        "AAAA\n".to_string()
+                    ),
+                    (
+                        "b.txt".to_string(),
+                        tar::EntryType::Regular,
+                        "BBBB\n".to_string()
+                    ),
+                ]
+            );
+
+            let pz = PartialZip::new(&address.join("/files/symlink.zip")?)?;
+            let mut tar = Vec::new();
+            pz.repack_to_tar(&mut tar)?;
+            let mut archive = tar::Archive::new(tar.as_slice());
+            let link = archive
+                .entries()?
+                .filter_map(Result::ok)
+                .find(|entry| entry.header().entry_type() == tar::EntryType::Symlink)
+                .ok_or_else(|| anyhow::anyhow!("no symlink"))?;
+            assert_eq!(link.path()?.display().to_string(), "link");
+            assert_eq!(
+                link.link_name()?.map(|target| target.display().to_string()),
+                Some(pz.read_link("link")?)
+            );
+
+            let pz = PartialZip::new(&address.join("/files/traversal.zip")?)?;
+            assert!(matches!(
+                pz.repack_to_tar(&mut Vec::new()),
+                Err(PartialZipError::UnsafePath(_))
+            ));
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that entries escaping the destination directory are rejected before writing anything
+    async fn test_download_all_traversal() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let out_dir = tempfile::tempdir()?;
+            let pz = PartialZip::new(&address.join("/files/traversal.zip")?)?;
+            let written = pz.download_all(out_dir.path());
+            assert!(
+                matches!(written, Err(PartialZipError::UnsafePath(ref name)) if name == "../evil.txt"),
+                "didn't reject the path traversal"
+            );
+            assert!(!out_dir.path().join("ok.txt").exists());
+            let written = pz.download_to_dir("../evil.txt", out_dir.path());
+            assert!(
+                matches!(written, Err(PartialZipError::UnsafePath(_))),
+                "didn't reject the path traversal"
+            );
+            assert_eq!(
+                pz.download_to_dir("ok.txt", out_dir.path())?,
+                out_dir.path().join("ok.txt")
+            );
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that only the files matching the glob pattern are downloaded
+    async fn test_download_matching() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let out_dir = tempfile::tempdir()?;
+            let pz = PartialZip::new(&address.join("/files/nested.zip")?)?;
+            let written = pz.download_matching("dir/*", out_dir.path())?;
+            assert_eq!(written, vec![out_dir.path().join("dir/sub/a.txt")]);
+            assert!(!out_dir.path().join("b.txt").exists());
+            let written = pz.download_matching("*.plist", out_dir.path());
+            assert!(
+                matches!(written, Err(PartialZipError::FileNotFound { .. })),
+                "didn't throw an error without any match"
+            );
+            let written = pz.download_matching("[", out_dir.path());
+            assert!(
+                matches!(written, Err(PartialZipError::PatternError(_))),
+                "didn't throw an error with an invalid pattern"
+            );
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that the end of the archive is fetched with a single request
+    async fn test_tail_prefetch() -> Result<()> {
+        use std::io::{Read, Seek, SeekFrom};
+
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let url = address.join("/files/tail.zip")?;
+            // the central directory starts in the second to last block
+            let pz = PartialZip::new(&url)?;
+            assert_eq!(pz.list_names().len(), 41);
+            // HEAD and a single range request
+            assert_eq!(pz.requests(), 2);
+            for (prefetch, requests) in [(None, 2), (Some(0), 3)] {
+                let mut reader = PartialReader::new(&url)?;
+                if let Some(prefetch) = prefetch {
+                    reader = reader.with_tail_prefetch(prefetch);
+                }
+                let mut buf = [0; 0x100];
+                reader.seek(SeekFrom::End(-22))?;
+                reader.read_exact(&mut buf[..22])?;
+                reader.seek(SeekFrom::Start(63827))?;
+                reader.read_exact(&mut buf)?;
+                assert_eq!(reader.requests(), requests);
+            }
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test the parallel download of several files
+    async fn test_download_many() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let out_dir = tempfile::tempdir()?;
+            let pz = PartialZip::new(&address.join("/files/nested.zip")?)?;
+            let results = pz.download_many(
+                &["b.txt", "missing.txt", "dir/sub/a.txt"],
+                out_dir.path(),
+                2,
+            );
+            assert_eq!(results.len(), 3);
+            assert_eq!(
+                results[0].as_ref().ok(),
+                Some(&out_dir.path().join("b.txt"))
+            );
+            assert!(
+                matches!(results[1], Err(PartialZipError::FileNotFound { .. })),
+                "didn't throw an error on a missing file"
+            );
+            assert_eq!(
+                results[2].as_ref().ok(),
+                Some(&out_dir.path().join("dir/sub/a.txt"))
+            );
+            assert_eq!(std::fs::read(out_dir.path().join("b.txt"))?, b"BBBB\n");
+            assert_eq!(
+                std::fs::read(out_dir.path().join("dir/sub/a.txt"))?,
+                b"AAAA\n"
+            );
+            Ok(())
+        })
+        .await?
+    }
+
+    #[tokio::test]
+    /// Test that the parallel verification reports the corrupted files and only them
+    async fn test_verify_all() -> Result<()> {
+        let address = spawn_server()?.address;
+        tokio::task::spawn_blocking(move || {
+            let pz = PartialZip::new(&address.join("/files/badcrc.zip")?)?;
+            let results = pz.verify_all(4);
+            assert_eq!(results.len(), 2);
+            assert_eq!(results[0].0, "1.txt");
+            assert!(
+                matches!(results[0].1, Err(PartialZipError::CrcMismatch { .. })),
+                "didn't throw an error on a bad CRC"
+            );
+            assert_eq!(results[1].0, "2.txt");
+            assert!(results[1].1.is_ok());
+            let pz = PartialZip::new(&address.join("/files/nested.zip")?)?;
+            assert!(pz.verify_all(1).iter().all(|(_, result)| result.is_ok()));
+            Ok(())
+        })
+        .await?
+    }
+
+    #[cfg(feature = "s3")]
+    #[tokio::test]
+    /// Test the S3 reader against the test server standing for an S3 endpoint with path-style URLs
+    async fn test_s3_range_reader() -> Result<()> {
+        use aws_sdk_s3::config::{BehaviorVersion, Credentials, Region};
+
+        let address = spawn_server()?.address;
+        let config = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .endpoint_url(address.as_str().trim_end_matches('/'))
+            .force_path_style(true)
+            .credentials_provider(Credentials::new("key", "secret", None, None, "test"))
+            .build();
+        tokio::task::spawn_blocking(move || {
+            // the bucket is the first segment of the path
+            let reader = crate::S3RangeReader::from_config(config.clone(), "files", "test.zip")?;
+            assert_eq!(reader.len(), 368);
+            let pz = PartialZip::from_range_reader(reader)?;
+            assert_eq!(pz.list_names(), vec!["1.txt", "2.txt"]);
+            assert_eq!(pz.download("2.txt")?, b"BBBB\n");
+            assert!(matches!(
+                crate::S3RangeReader::from_config(config, "files", "missing.zip"),
+                Err(PartialZipError::S3Error(_))
+            ));
+            Ok(())
+        })
+        .await?
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    /// Test the listing and the download with the async API
+    async fn test_async_download() -> Result<()> {
+        let address = spawn_server()?.address;
+        let pz = crate::AsyncPartialZip::new(&address.join("/files/test.zip")?).await?;
+        assert_eq!(pz.file_size(), 368);
+        assert_eq!(pz.len(), 2);
+        assert!(pz.contains("2.txt") && !pz.contains("3.txt"));
+        assert_eq!(pz.list_names(), vec!["1.txt", "2.txt"]);
+        let sync_list = {
+            let address = address.clone();
+            tokio::task::spawn_blocking(move || -> Result<_> {
+                Ok(PartialZip::new(&address.join("/files/test.zip")?)?.list_full())
+            })
+            .await??
+        };
+        assert_eq!(pz.list_full().await, sync_list);
+        assert_eq!(pz.download("1.txt").await?, b"AAAA\n");
+        assert_eq!(pz.download("2.txt").await?, b"BBBB\n");
+        let pz = crate::AsyncPartialZip::new(&address.join("/files/lzma.zip")?).await?;
+        assert_eq!(pz.download("lzma.txt").await?.len(), 180_000);
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test(flavor = "multi_thread")]
+    /// Test that concurrent calls on the same archive don't lose the blocks the others need
+    async fn test_async_concurrent() -> Result<()> {
+        let address = spawn_server()?.address;
+        let pz = Arc::new(crate::AsyncPartialZip::new(&address.join("/files/tail.zip")?).await?);
+        let expected = tokio::task::spawn_blocking({
+            let address = address.clone();
+            move || -> Result<_> {
+                let pz = PartialZip::new(&address.join("/files/tail.zip")?)?;
+                Ok((pz.download("big.bin")?, pz.list_full()))
+            }
+        })
+        .await??;
+        let tasks: Vec<_> = (0..8)
+            .map(|i| {
+                let pz = pz.clone();
+                tokio::spawn(async move {
+                    if i % 2 == 0 {
+                        pz.download("big.bin").await.map(|content| (content, None))
+                    } else {
+                        Ok((Vec::new(), Some(pz.list_full().await)))
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            match task.await?? {
+                (_, Some(list)) => assert_eq!(list, expected.1),
+                (content, None) => assert!(content == expected.0),
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    /// Test the async API errors on servers not supporting range requests
+    async fn test_async_range_not_supported() -> Result<()> {
+        let address = spawn_server()?.address;
+        let pz = crate::AsyncPartialZip::new(&address.join("/norange/lzma.zip")?).await;
+        assert!(
+            matches!(pz, Err(PartialZipError::RangeNotSupported)),
+            "didn't throw an error when range requests are not supported"
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    /// Test the async API rejects the URLs other than http and https
+    async fn test_async_invalid_url() -> Result<()> {
+        for url in [
+            "ftp://127.0.0.1/test.zip",
+            "file:///tmp/test.zip",
+            "s3://bucket/test.zip",
+            "not a url",
+        ] {
+            assert!(
+                matches!(
+                    crate::AsyncPartialZip::new(&url).await,
+                    Err(PartialZipError::InvalidUrl)
+                ),
+                "didn't reject {url}"
+            );
+        }
+        Ok(())
+    }
 }
@@ -1,10 +1,207 @@
+use std::path::{Component, Path, PathBuf};
+
 use url::Url;
 
+/// URL schemes the readers of the library can fetch, a backend behind a feature adds its own here
+pub const SUPPORTED_SCHEMES: &[&str] = &[
+    "http",
+    "https",
+    "ftp",
+    "file",
+    #[cfg(feature = "s3")]
+    "s3",
+];
+
 /// Returns if a URL is a valid URL string supported by the library
 #[must_use]
 pub fn url_is_valid(url: &str) -> bool {
-    Url::parse(url).is_ok_and(|url| {
-        // Supported URL schemes
-        ["http", "https", "ftp", "file"].contains(&url.scheme())
-    })
+    Url::parse(url).is_ok_and(|url| SUPPORTED_SCHEMES.contains(&url.scheme()))
+}
+
+/// Returns the local path of a `file://` URL, or `None` for the other schemes
+///
+/// The path is the native one of the platform, like `C:\archive.zip` for `file:///C:/archive.zip` on Windows.
+#[must_use]
+pub fn local_path(url: &str) -> Option<PathBuf> {
+    Url::parse(url)
+        .ok()
+        .filter(|url| url.scheme() == "file")
+        .and_then(|url| url.to_file_path().ok())
+}
+
+/// Returns if a URL has the `s3` scheme, which curl can't fetch
+#[must_use]
+pub fn is_s3_url(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|url| url.scheme() == "s3")
+}
+
+/// Returns if a URL has the `https` scheme
+#[must_use]
+pub fn is_https_url(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|url| url.scheme() == "https")
+}
+
+/// Returns if a URL has the `http` or the `https` scheme
+#[cfg(feature = "async")]
+#[must_use]
+pub fn is_http_url(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+/// Returns the bucket and the key of an `s3://bucket/key` URL, or `None` for the other schemes
+///
+/// The key is percent-decoded, `s3://bucket/my%20archive.zip` is the key `my archive.zip`.
+#[cfg(feature = "s3")]
+#[must_use]
+pub fn s3_location(url: &str) -> Option<(String, String)> {
+    let url = Url::parse(url).ok().filter(|url| url.scheme() == "s3")?;
+    let bucket = url.host_str().filter(|bucket| !bucket.is_empty())?;
+    let key = percent_encoding::percent_decode_str(url.path().strip_prefix('/')?)
+        .decode_utf8()
+        .ok()
+        .filter(|key| !key.is_empty())?;
+    Some((bucket.to_string(), key.into_owned()))
+}
+
+/// Returns if two URLs have the same scheme, host and port, the default one of the scheme when it's not explicit
+#[must_use]
+pub fn same_origin(a: &str, b: &str) -> bool {
+    match (Url::parse(a), Url::parse(b)) {
+        (Ok(a), Ok(b)) => {
+            a.scheme() == b.scheme()
+                && a.host_str() == b.host_str()
+                && a.port_or_known_default() == b.port_or_known_default()
+        }
+        _ => false,
+    }
+}
+
+/// Returns the URL of the volume number `volume` of a split archive from the URL of its last volume, ending with `.zip`
+///
+/// The volumes are numbered from 1, `archive.zip` has the volumes `archive.z01`, `archive.z02`, ...
+#[must_use]
+pub fn split_volume_url(url: &str, volume: u32) -> Option<String> {
+    let mut url = Url::parse(url).ok()?;
+    let path = url.path();
+    let stem = path
+        .len()
+        .checked_sub(4)
+        .filter(|stem| {
+            path.get(*stem..)
+                .is_some_and(|ext| ext.eq_ignore_ascii_case(".zip"))
+        })
+        .and_then(|stem| path.get(..stem))?;
+    let path = format!("{stem}.z{volume:02}");
+    url.set_path(&path);
+    Some(url.to_string())
+}
+
+/// Returns the total size from a `Content-Range: bytes 0-0/1234` response header line, or `None` for any other header
+#[must_use]
+pub fn content_range_total(header: &str) -> Option<u64> {
+    let (name, value) = header.split_once(':')?;
+    if !name.trim().eq_ignore_ascii_case("content-range") {
+        return None;
+    }
+    value
+        .trim()
+        .strip_prefix("bytes ")?
+        .split_once('/')?
+        .1
+        .parse()
+        .ok()
+}
+
+/// Returns the value for an `If-Range` header from the header lines of a response, after the redirects
+///
+/// It's the `ETag` when it's a strong one, the weak ones can't be used for ranges, or the `Last-Modified` date.
+#[must_use]
+pub fn range_validator(headers: &[String]) -> Option<String> {
+    // the headers of the last response start after its status line
+    let last = headers
+        .iter()
+        .rposition(|header| header.starts_with("HTTP/"))?;
+    let value = |wanted: &str| {
+        headers[last..].iter().find_map(|header| {
+            let (name, value) = header.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case(wanted)
+                .then(|| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        })
+    };
+    value("etag")
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| value("last-modified"))
+}
+
+/// Returns the relative path where an archive entry should be extracted,
+/// or `None` if it would escape the destination directory (absolute paths or `..` components)
+#[must_use]
+pub fn entry_path(name: &str) -> Option<PathBuf> {
+    let mut path = PathBuf::new();
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    if path.as_os_str().is_empty() {
+        None
+    } else {
+        Some(path)
+    }
+}
+
+/// Characters of the bytes from 0x80 to 0xff in the CP437 encoding, the lower ones are the same as ASCII
+const CP437_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', 'É', 'æ', 'Æ',
+    'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', 'á', 'í', 'ó', 'ú', 'ñ', 'Ñ',
+    'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»', '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕',
+    '╣', '║', '╗', '╝', '╜', '╛', '┐', '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦',
+    '╠', '═', '╬', '╧', '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐',
+    '▀', 'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', '≡', '±',
+    '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+/// Describe the unsupported compression method `id`, with the feature of this crate supporting it if there is one
+#[must_use]
+pub fn unsupported_compression(id: u16) -> String {
+    let name = match id {
+        1 => "Shrink",
+        2..=5 => "Reduce",
+        6 => "Implode",
+        10 => "PKWARE DCL Implode",
+        16 => "IBM z/OS CMPSC",
+        18 => "IBM TERSE",
+        19 => "IBM LZ77",
+        95 => "XZ",
+        96 => "JPEG",
+        97 => "WavPack",
+        98 => "PPMd",
+        _ => "Unknown",
+    };
+    let hint = if id == 98 && !cfg!(feature = "ppmd") {
+        ", it needs the `ppmd` feature"
+    } else {
+        ""
+    };
+    format!("{name} compression (method {id}) is not supported{hint}")
+}
+
+/// Returns the bytes of `name` encoded in CP437, the encoding of the names without the UTF-8 flag,
+/// or `None` if it has a character which doesn't exist in CP437
+#[must_use]
+pub fn to_cp437(name: &str) -> Option<Vec<u8>> {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii() {
+                u8::try_from(c).ok()
+            } else {
+                let position = CP437_HIGH.iter().position(|high| *high == c)?;
+                u8::try_from(position).ok().map(|byte| byte | 0x80)
+            }
+        })
+        .collect()
 }
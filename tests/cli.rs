@@ -36,6 +36,22 @@ mod cli_tests {
                 .or(predicate::str::contains("partialzip.exe download")),
         );
 
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("extract");
+        cmd.assert().failure().stderr(
+            predicate::str::contains("partialzip extract")
+                .or(predicate::str::contains("partialzip.exe extract")),
+        );
+
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("-q")
+            .arg("-vv")
+            .arg("list")
+            .arg("file:///nothing.zip");
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+
         let mut cmd = Command::cargo_bin("partialzip")?;
         cmd.arg("pipe");
         cmd.assert().failure().stderr(
@@ -61,7 +77,7 @@ mod cli_tests {
         cmd.arg("list").arg("-d").arg(&target_arg);
         cmd.assert().success().stdout(
             predicate::str::is_match(
-                "1.txt - 7 B - Supported: true\n2.txt - 7 B - Supported: true\n",
+                "1.txt - 7 B - Supported: true\n2.txt - 7 B - Supported: true\n2 files - 14 B compressed - 10 B uncompressed - ratio 140.0%\n",
             )
             .unwrap(),
         );
@@ -76,11 +92,34 @@ mod cli_tests {
             .success()
             .stdout(predicate::str::contains("2.txt\n"));
 
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("list").arg("--json").arg(&target_arg);
+        cmd.assert().success().stdout(
+            predicate::str::starts_with("[")
+                .and(predicate::str::contains(r#""name": "2.txt""#))
+                .and(predicate::str::contains(r#""uncompressed_size": 5"#))
+                .and(predicate::str::contains(
+                    r#""compression_method": "Deflated""#,
+                )),
+        );
+
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("--limit-rate")
+            .arg("1MB")
+            .arg("--user-agent")
+            .arg("partialzip-test")
+            .arg("--insecure")
+            .arg("list")
+            .arg(&target_arg);
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("1.txt\n"));
+
         let mut cmd = Command::cargo_bin("partialzip")?;
         cmd.arg("-r").arg("list").arg(&target_arg);
         cmd.assert()
-            .failure()
-            .stderr(predicate::str::contains("Range request not supported\n"));
+            .success()
+            .stdout(predicate::str::contains("1.txt\n"));
 
         let output_file = NamedTempFile::new()?.path().display().to_string();
 
@@ -104,10 +143,340 @@ mod cli_tests {
 
         fs::remove_file(&output_file)?;
 
+        let output_dir = tempfile::tempdir()?;
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("download")
+            .arg("--glob")
+            .arg(&target_arg)
+            .arg("*.txt")
+            .arg(output_dir.path());
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("1.txt").and(predicate::str::contains("2.txt")));
+        assert_eq!(fs::read(output_dir.path().join("2.txt"))?, b"BBBB\n");
+
         let mut cmd = Command::cargo_bin("partialzip")?;
         cmd.arg("pipe").arg(&target_arg).arg("1.txt");
         cmd.assert().success();
 
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("stat").arg(&target_arg).arg("2.txt");
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff("2.txt - 7 B - Supported: true\n"));
+
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("testdata/aes.zip");
+        let encrypted_arg = format!("file://localhost{}", d.display());
+        let output_file = output_dir.path().join("secret.txt");
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("download")
+            .arg("--password")
+            .arg("partialzip")
+            .arg(&encrypted_arg)
+            .arg("secret.txt")
+            .arg(&output_file);
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("secret.txt extracted to"));
+        assert!(fs::read(&output_file)?.starts_with(b"secret line 0\n"));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cli_other_commands_work() -> Result<()> {
+        use std::{fs, path::PathBuf};
+
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("testdata/test.zip");
+        let target_arg = format!("file://localhost{}", d.display());
+
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("list").arg("-vvv").arg(&target_arg);
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("1.txt\n"));
+
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("repack").arg(&target_arg);
+        cmd.assert().success().stdout(
+            predicate::str::contains("1.txt")
+                .and(predicate::str::contains("AAAA\n"))
+                .and(predicate::str::contains("BBBB\n")),
+        );
+
+        let output_dir = tempfile::tempdir()?;
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("testdata/tail.zip");
+        let nested_arg = format!("file://localhost{}", d.display());
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("--stats")
+            .arg("download")
+            .arg("--dir")
+            .arg(output_dir.path())
+            .arg(&nested_arg)
+            .arg("small/07.txt");
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("small/07.txt extracted to"))
+            // local files are read directly
+            .stderr(predicate::str::diff("0 requests, 0 B received\n"));
+        assert!(output_dir.path().join("small/07.txt").is_file());
+
+        let extract_dir = tempfile::tempdir()?;
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("extract")
+            .arg("-l")
+            .arg("-d")
+            .arg(extract_dir.path())
+            .arg(&nested_arg)
+            .arg("small/0[0-2].txt")
+            .arg("big.bin");
+        cmd.assert().success().stdout(predicate::str::diff(
+            "big.bin\nsmall/00.txt\nsmall/01.txt\nsmall/02.txt\n",
+        ));
+        assert!(!extract_dir.path().join("big.bin").exists());
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("extract")
+            .arg("-d")
+            .arg(extract_dir.path())
+            .arg(&nested_arg)
+            .arg("small/0[0-2].txt");
+        cmd.assert().success();
+        assert_eq!(fs::read(extract_dir.path().join("small/01.txt"))?.len(), 2);
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("use --force to overwrite"));
+        cmd.arg("--force");
+        cmd.assert().success();
+
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("testdata/traversal.zip");
+        let traversal_arg = format!("file://localhost{}", d.display());
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("download")
+            .arg("--dir")
+            .arg(output_dir.path())
+            .arg(&traversal_arg)
+            .arg("../evil.txt");
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("Unsafe path"));
+
+        let output_file = output_dir.path().join("second.txt");
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("download")
+            .arg("--index")
+            .arg("1")
+            .arg(&target_arg)
+            .arg(&output_file);
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("#1 extracted to"));
+        assert_eq!(fs::read(&output_file)?, b"BBBB\n");
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cli_list_comment_works() -> Result<()> {
+        use std::path::PathBuf;
+
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("testdata/comment.zip");
+        let comment_arg = format!("file://localhost{}", d.display());
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("list").arg("--comment").arg(&comment_arg);
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff("build 1234\n1.txt\n"));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cli_extract_unsafe_names_fail() -> Result<()> {
+        use std::{fs, path::PathBuf};
+
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("testdata/traversal.zip");
+        let traversal_arg = format!("file://localhost{}", d.display());
+        // the file outside the output directory isn't taken for an extracted one
+        let parent_dir = tempfile::tempdir()?;
+        fs::write(parent_dir.path().join("evil.txt"), b"mine")?;
+        let extract_dir = parent_dir.path().join("out");
+        fs::create_dir(&extract_dir)?;
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("extract")
+            .arg("-d")
+            .arg(&extract_dir)
+            .arg(&traversal_arg)
+            .arg("*");
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("Unsafe path"));
+        assert_eq!(fs::read(parent_dir.path().join("evil.txt"))?, b"mine");
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cli_list_sorted_works() -> Result<()> {
+        use std::path::PathBuf;
+
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("testdata/tail.zip");
+        let tail_arg = format!("file://localhost{}", d.display());
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("list")
+            .arg("-d")
+            .arg("--sort")
+            .arg("uncompressed")
+            .arg(&tail_arg);
+        cmd.assert().success().stdout(
+            predicate::str::starts_with("small/00.txt")
+                .and(predicate::str::is_match(r"\nbig\.bin - [^\n]*\n[0-9]+ files").unwrap()),
+        );
+
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("list").arg("--sort").arg("name").arg(&tail_arg);
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("--detailed"));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cli_list_version_needed_works() -> Result<()> {
+        use std::path::PathBuf;
+
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("testdata/tail.zip");
+        let tail_arg = format!("file://localhost{}", d.display());
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("list")
+            .arg("-d")
+            .arg("--version-needed")
+            .arg(&tail_arg);
+        cmd.assert().success().stdout(predicate::str::starts_with(
+            "big.bin - 62.0 KB - Supported: true - Version needed: 2.0\n",
+        ));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cli_reads_stdin() -> Result<()> {
+        use std::{fs, path::PathBuf};
+
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("testdata/test.zip");
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("list")
+            .arg("-d")
+            .arg("-")
+            .stdin(fs::File::open(&d)?);
+        cmd.assert().success().stdout(predicate::str::starts_with(
+            "1.txt - 7 B - Supported: true\n",
+        ));
+
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("pipe")
+            .arg("-")
+            .arg("2.txt")
+            .stdin(fs::File::open(&d)?);
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff("BBBB\n"));
+
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("checksum")
+            .arg("--algo")
+            .arg("md5")
+            .arg("-")
+            .arg("2.txt")
+            .stdin(fs::File::open(&d)?);
+        cmd.assert().success().stdout(predicate::str::diff(
+            "6d799daf4e343f58d5050f6ea99c70e7  2.txt\n",
+        ));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cli_continue_on_error_works() -> Result<()> {
+        use std::path::PathBuf;
+
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("testdata/badcrc.zip");
+        let target_arg = format!("file://localhost{}", d.display());
+
+        let output_dir = tempfile::tempdir()?;
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("download")
+            .arg("--dir")
+            .arg(output_dir.path())
+            .arg(&target_arg)
+            .arg("2.txt")
+            .arg("1.txt");
+        cmd.assert()
+            .failure()
+            .stdout(predicate::str::contains("2.txt extracted to"));
+
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("download")
+            .arg("--dir")
+            .arg(output_dir.path())
+            .arg("--continue-on-error")
+            .arg(&target_arg)
+            .arg("1.txt")
+            .arg("missing.txt")
+            .arg("2.txt");
+        cmd.assert()
+            .failure()
+            .stdout(
+                predicate::str::contains("2.txt extracted to")
+                    .and(predicate::str::contains("1 extracted, 2 failed")),
+            )
+            .stderr(
+                predicate::str::contains("1.txt failed")
+                    .and(predicate::str::contains("missing.txt failed"))
+                    .and(predicate::str::contains("2 of 3 files failed")),
+            );
+
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("extract")
+            .arg("--force")
+            .arg("--continue-on-error")
+            .arg("-d")
+            .arg(output_dir.path())
+            .arg(&target_arg);
+        cmd.assert()
+            .failure()
+            .stdout(predicate::str::contains("1 extracted, 1 failed"))
+            .stderr(predicate::str::contains("1.txt failed"));
+
+        let mut cmd = Command::cargo_bin("partialzip")?;
+        cmd.arg("extract")
+            .arg("--force")
+            .arg("--continue-on-error")
+            .arg("-d")
+            .arg(output_dir.path())
+            .arg(&target_arg)
+            .arg("2.txt");
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("1 extracted, 0 failed"));
+
         Ok(())
     }
 }
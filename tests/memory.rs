@@ -0,0 +1,74 @@
+/// Those are the tests of the memory used by the downloads, in their own binary since they replace the global allocator
+mod memory_tests {
+    use anyhow::Result;
+    use partialzip::PartialZip;
+
+    /// Allocator recording the largest allocation done by the current thread while tracking it
+    struct PeakAllocator;
+
+    thread_local! {
+        /// Largest allocation of the thread, `None` when it isn't tracked
+        static LARGEST_ALLOCATION: std::cell::Cell<Option<usize>> = const { std::cell::Cell::new(None) };
+    }
+
+    impl PeakAllocator {
+        fn record(size: usize) {
+            // the thread local is const initialized, accessing it doesn't allocate
+            let _ = LARGEST_ALLOCATION.try_with(|largest| {
+                if let Some(current) = largest.get() {
+                    largest.set(Some(current.max(size)));
+                }
+            });
+        }
+    }
+
+    unsafe impl std::alloc::GlobalAlloc for PeakAllocator {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            Self::record(layout.size());
+            std::alloc::System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            std::alloc::System.dealloc(ptr, layout);
+        }
+
+        unsafe fn realloc(
+            &self,
+            ptr: *mut u8,
+            layout: std::alloc::Layout,
+            new_size: usize,
+        ) -> *mut u8 {
+            Self::record(new_size);
+            std::alloc::System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: PeakAllocator = PeakAllocator;
+
+    #[test]
+    /// Test that the entries are streamed to their files, without allocating anything as big as them
+    fn test_download_all_memory() -> Result<()> {
+        const ENTRY_SIZE: usize = 64 * 1024 * 1024;
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        writer.start_file("zeros.bin", zip::write::SimpleFileOptions::default())?;
+        let chunk = vec![0; 1024 * 1024];
+        for _ in 0..ENTRY_SIZE / chunk.len() {
+            std::io::Write::write_all(&mut writer, &chunk)?;
+        }
+        let pz = PartialZip::from_bytes(writer.finish()?.into_inner())?;
+        let out_dir = tempfile::tempdir()?;
+        LARGEST_ALLOCATION.set(Some(0));
+        let written = pz.download_all_with_buffer(out_dir.path(), 16 * 1024);
+        let largest = LARGEST_ALLOCATION.replace(None).unwrap_or_default();
+        assert_eq!(
+            std::fs::metadata(&written?[0])?.len(),
+            u64::try_from(ENTRY_SIZE)?
+        );
+        assert!(
+            largest < 1024 * 1024,
+            "allocated {largest} bytes at once for an entry of {ENTRY_SIZE}"
+        );
+        Ok(())
+    }
+}